@@ -0,0 +1,2346 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, thread};
+
+use chrono::prelude::*;
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::auth::{
+    aquire_access_token, cache_dir, client_id, refresh_user_token, renew_access_token,
+    user_access_token, user_token_from_env,
+};
+use crate::entry::{parse_streams, StreamEntry};
+use crate::error::{AccessTokenError, AppError};
+use crate::filter::{closest_names, SearchFilter};
+use crate::helix::{
+    BitsLeaderboard, BlockedUser, Channel, Clip, CreatedClip, Followers, Game, GameAnalytics, Page,
+    SearchedChannel, Stream, Subscribers, Subscription, User, Video,
+};
+
+pub use crate::entry::UserInfo;
+
+pub const HELIX_URL: &str = "https://api.twitch.tv/helix";
+
+/// Searched when no --game is given: Software and Game Development, and
+/// Science & Technology
+pub const DEFAULT_GAME_IDS: [&str; 2] = ["1469308723", "509658"];
+
+/// How many category names to suggest for an unknown `--game`
+pub(crate) const SUGGESTED_GAMES: usize = 3;
+
+// -----------------------------------------------------------------------------
+//     - Request and parsing -
+// -----------------------------------------------------------------------------
+
+pub(crate) fn configure_agent() -> ureq::Agent {
+    let proxy = env::var("https_proxy")
+        .ok()
+        .and_then(|p| ureq::Proxy::new(p).ok());
+
+    let mut agent = ureq::AgentBuilder::new();
+    if let Some(proxy) = proxy {
+        agent = agent.proxy(proxy);
+    }
+
+    agent.build()
+}
+
+/// Minimum time between Helix requests, set from `--throttle`
+static THROTTLE: OnceLock<Duration> = OnceLock::new();
+
+/// Earliest time the next Helix request may be sent
+static NEXT_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Sleeps until the next request slot when throttling is enabled. Slots are
+/// handed out in order, so concurrent callers are paced as well.
+fn throttle() {
+    let Some(interval) = THROTTLE.get() else {
+        return;
+    };
+
+    let wait = {
+        let mut next = NEXT_REQUEST.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let slot = next.map_or(now, |next| next.max(now));
+        *next = Some(slot + *interval);
+        slot - now
+    };
+
+    thread::sleep(wait);
+}
+
+/// Rate limit bucket as reported by the last Helix response
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    fn from_response(resp: &ureq::Response) -> Option<Self> {
+        let header = |name| resp.header(name).and_then(|v| v.parse::<u64>().ok());
+        Some(RateLimit {
+            limit: header("Ratelimit-Limit")?,
+            remaining: header("Ratelimit-Remaining")?,
+            reset: header("Ratelimit-Reset")
+                .and_then(|reset| Utc.timestamp_opt(reset as i64, 0).single()),
+        })
+    }
+
+    /// How long to wait at `now` before the next request, if the bucket is
+    /// nearly empty and has yet to reset
+    fn wait(&self, now: DateTime<Utc>) -> Option<Duration> {
+        let reset = self
+            .reset
+            .filter(|_| self.remaining <= RATE_LIMIT_RESERVE)?;
+        (reset - now).to_std().ok()
+    }
+}
+
+static RATE_LIMIT: Mutex<Option<RateLimit>> = Mutex::new(None);
+
+/// Slowest `--throttle` taken, in requests per second
+pub const MIN_THROTTLE: f64 = 0.001;
+
+/// Limits all Helix requests in this process to `rate` per second. Only the
+/// first call has any effect. Fails unless `rate` is a finite number from
+/// [`MIN_THROTTLE`] up.
+pub fn set_throttle(rate: f64) -> Result<(), AppError> {
+    let interval = Some(rate)
+        .filter(|rate| rate.is_finite() && *rate >= MIN_THROTTLE)
+        .and_then(|rate| Duration::try_from_secs_f64(1.0 / rate).ok())
+        .ok_or(AppError::InvalidThrottle(rate))?;
+    let _ = THROTTLE.set(interval);
+    Ok(())
+}
+
+/// Rate limit bucket from the most recent Helix response, if any
+pub fn last_rate_limit() -> Option<RateLimit> {
+    *RATE_LIMIT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// How a client gets a new access token when Helix stops accepting its own
+#[derive(Debug, Clone, Copy)]
+enum Renewal {
+    /// It can't, the token was handed to us
+    Never,
+    /// Request a new app access token
+    App { use_cache: bool },
+    /// Refresh the user token stored by `login`
+    User,
+}
+
+/// Attempts at a request that keeps getting 429 Too Many Requests
+const RATE_LIMITED_ATTEMPTS: u32 = 5;
+
+/// Requests wait for the bucket to refill once this few points are left
+const RATE_LIMIT_RESERVE: u64 = 2;
+
+/// Sleeps until the rate limit bucket refills if the last response said it
+/// is nearly empty, telling `on_wait` first
+fn wait_for_rate_limit(on_wait: &mut dyn FnMut(Duration)) {
+    if let Some(wait) = last_rate_limit().and_then(|limit| limit.wait(Utc::now())) {
+        on_wait(wait);
+        thread::sleep(wait);
+    }
+}
+
+/// Tells on stderr that the rate limit holds a request up
+fn report_rate_limit(wait: Duration) {
+    eprintln!("Rate limited, waiting {}s", wait.as_secs_f64().ceil());
+}
+
+/// How long a 429 response asks us to wait: its `Retry-After` header, or
+/// else until its rate limit bucket resets, at most [`MAX_BACKOFF`]
+fn retry_after(resp: &ureq::Response) -> Option<Duration> {
+    let wait = match resp.header("Retry-After").and_then(|v| v.parse().ok()) {
+        Some(seconds) => Duration::from_secs(seconds),
+        None => {
+            let reset = RateLimit::from_response(resp)?.reset?;
+            (reset - Utc::now()).to_std().ok()?
+        }
+    };
+    Some(wait.min(MAX_BACKOFF))
+}
+
+/// How often and how soon requests failing with a network error or a 5xx
+/// response are sent again
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 3,
+            delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Longest wait between retries, however many came before
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The wait before retrying after `failures` failed attempts, twice as long
+/// as the one before up to [`MAX_BACKOFF`]
+fn retry_backoff(delay: Duration, failures: u32) -> Duration {
+    delay
+        .checked_mul(2u32.saturating_pow(failures))
+        .map_or(MAX_BACKOFF, |wait| wait.min(MAX_BACKOFF))
+}
+
+static RETRY_POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+
+/// Sets how Helix requests are retried, see [`RetryPolicy`]. Only the first
+/// call has any effect.
+pub fn set_retry_policy(policy: RetryPolicy) {
+    let _ = RETRY_POLICY.set(policy);
+}
+
+/// Whether a failed request may well succeed if sent again
+fn is_transient(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::Status(status, _) => (500..600).contains(status),
+        ureq::Error::Transport(_) => true,
+    }
+}
+
+/// Stretches `delay` by up to half again, so clients failing together don't
+/// all retry at the same moment
+fn jitter(delay: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.subsec_nanos());
+    delay.mul_f64(1.0 + (nanos % 500) as f64 / 1000.0)
+}
+
+/// Sends a Helix request, with `body` as JSON if given, keeping within
+/// `--throttle` and the rate limit. Requests answered with 429 are sent again after the wait the response
+/// asks for, or with exponential backoff if it doesn't say. Network errors
+/// and 5xx responses are retried as the [`RetryPolicy`] says. Waits for the
+/// rate limit are told to `on_rate_limit` before they start.
+fn send(
+    request: ureq::Request,
+    body: Option<&Value>,
+    on_rate_limit: &mut dyn FnMut(Duration),
+) -> Result<ureq::Response, AppError> {
+    let policy = RETRY_POLICY.get().copied().unwrap_or_default();
+
+    let mut backoff = Duration::from_secs(1);
+    let mut attempt = 1;
+    let mut failures = 0;
+    let resp = loop {
+        throttle();
+        wait_for_rate_limit(on_rate_limit);
+
+        let result = match body {
+            Some(body) => request.clone().send_json(body),
+            None => request.clone().call(),
+        };
+        match result {
+            Err(ureq::Error::Status(429, resp)) if attempt < RATE_LIMITED_ATTEMPTS => {
+                let wait = retry_after(&resp).unwrap_or(backoff);
+                on_rate_limit(wait);
+                thread::sleep(wait);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) if is_transient(&e) && failures < policy.retries => {
+                let wait = jitter(retry_backoff(policy.delay, failures));
+                eprintln!(
+                    "Request failed ({e}), retrying in {:.1}s",
+                    wait.as_secs_f64()
+                );
+                thread::sleep(wait);
+                failures += 1;
+            }
+            result => break result?,
+        }
+    };
+
+    if let Some(rate_limit) = RateLimit::from_response(&resp) {
+        *RATE_LIMIT.lock().unwrap_or_else(|e| e.into_inner()) = Some(rate_limit);
+    }
+
+    Ok(resp)
+}
+
+/// Pages fetched at most from the list endpoints that have no `--max-pages`,
+/// like the blocked users or the analytics reports
+const MAX_LIST_PAGES: usize = 100;
+
+/// Warns that the list of `endpoint` was cut off at [`MAX_LIST_PAGES`]
+fn warn_list_cut(endpoint: &str) {
+    eprintln!("Warning: stopped after {MAX_LIST_PAGES} pages of {endpoint}, the rest is left out");
+}
+
+/// The cursors one pagination was handed, to stop it if the API hands one
+/// back again, which would otherwise go on forever
+#[derive(Debug, Default)]
+pub(crate) struct SeenCursors(HashSet<String>);
+
+impl SeenCursors {
+    /// `next` as the cursor to follow, or [`AppError::RunawayPagination`] if
+    /// it was handed back before, `place` saying where, like "for game 1"
+    pub(crate) fn check(
+        &mut self,
+        next: Option<String>,
+        place: impl FnOnce() -> String,
+    ) -> Result<Option<String>, AppError> {
+        match next {
+            Some(cursor) if !self.0.insert(cursor.clone()) => Err(AppError::RunawayPagination(
+                format!("the API returned cursor {cursor:?} twice {}", place()),
+            )),
+            next => Ok(next),
+        }
+    }
+}
+
+/// Follows the pagination cursors of `endpoint` for at most `max_pages`
+/// pages, fetching the first one however low that is, like a stream scan.
+/// `fetch` gets the cursor of the page to fetch, none for the first, and
+/// returns that of the page after it. Returns whether there were pages left.
+///
+/// Stops with [`AppError::RunawayPagination`] if the API hands back a cursor
+/// it has already given us, which would otherwise go on forever.
+fn paginate(
+    endpoint: &str,
+    max_pages: usize,
+    mut fetch: impl FnMut(Option<&str>) -> Result<Option<String>, AppError>,
+) -> Result<bool, AppError> {
+    let mut seen_cursors = SeenCursors::default();
+    let mut cursor: Option<String> = None;
+    for pages in 1..=max_pages.max(1) {
+        let next = fetch(cursor.as_deref())?;
+        cursor = seen_cursors.check(next, || format!("after {pages} pages of {endpoint}"))?;
+        if cursor.is_none() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Authenticated client for the Helix API. A token that expires or is
+/// revoked mid-run is renewed once per request, where possible.
+///
+/// All requests of a client, including those for its tokens, share one agent
+/// and with it the connection pool.
+#[derive(Debug)]
+pub struct TwitchClient {
+    agent: ureq::Agent,
+    access_token: RwLock<String>,
+    renewal: Renewal,
+    /// Where Helix is, [`HELIX_URL`] unless told otherwise
+    helix_url: String,
+}
+
+impl TwitchClient {
+    pub fn new(agent: ureq::Agent, access_token: String) -> Self {
+        TwitchClient::with_renewal(agent, access_token, Renewal::Never)
+    }
+
+    fn with_renewal(agent: ureq::Agent, access_token: String, renewal: Renewal) -> Self {
+        TwitchClient {
+            agent,
+            access_token: RwLock::new(access_token),
+            renewal,
+            helix_url: HELIX_URL.to_string(),
+        }
+    }
+
+    /// Sends the Helix requests to `url` instead of [`HELIX_URL`], like the
+    /// mock API of the Twitch CLI
+    pub fn with_helix_url(mut self, url: impl Into<String>) -> Self {
+        self.helix_url = url.into();
+        self
+    }
+
+    /// Creates a client with an app access token for the credentials in
+    /// `TWITCH_CLIENT_ID` and `TWITCH_CLIENT_SECRET`, see [`aquire_access_token`].
+    pub fn from_env(use_cache: bool) -> Result<Self, AccessTokenError> {
+        let agent = configure_agent();
+        let access_token = aquire_access_token(&agent, use_cache)?;
+        Ok(TwitchClient::with_renewal(
+            agent,
+            access_token,
+            Renewal::App { use_cache },
+        ))
+    }
+
+    /// Creates a client acting as the user, see [`user_access_token`]
+    pub fn for_user() -> Result<Self, AccessTokenError> {
+        let agent = configure_agent();
+        let access_token = user_access_token(&agent)?;
+        let renewal = match user_token_from_env() {
+            true => Renewal::Never,
+            false => Renewal::User,
+        };
+        Ok(TwitchClient::with_renewal(agent, access_token, renewal))
+    }
+
+    /// Whether cached data may be used, which `--no-cache` turns off
+    fn uses_cache(&self) -> bool {
+        !matches!(self.renewal, Renewal::App { use_cache: false })
+    }
+
+    pub fn agent(&self) -> &ureq::Agent {
+        &self.agent
+    }
+
+    pub fn access_token(&self) -> String {
+        self.access_token
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replaces `rejected` with a new token, returning whether there is one.
+    /// When other requests failed with the same token, only the first renews
+    /// it and the rest reuse the result.
+    fn renew_token(&self, rejected: &str) -> Result<bool, AccessTokenError> {
+        let mut access_token = self.access_token.write().unwrap_or_else(|e| e.into_inner());
+        if *access_token != rejected {
+            return Ok(true);
+        }
+
+        *access_token = match self.renewal {
+            Renewal::Never => return Ok(false),
+            Renewal::App { use_cache } => renew_access_token(&self.agent, use_cache)?,
+            Renewal::User => refresh_user_token(&self.agent)?,
+        };
+        Ok(true)
+    }
+
+    /// GETs `endpoint` (relative to the Helix URL) with the given query
+    /// parameters. On 401 the token is renewed and the request sent once more.
+    fn get(&self, endpoint: &str, query: &[(&str, &str)]) -> Result<Value, AppError> {
+        self.get_observed(endpoint, query, &mut report_rate_limit)
+    }
+
+    /// Like [`get`](Self::get), but tells `on_rate_limit` about waits for the
+    /// rate limit instead of printing them
+    fn get_observed(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        on_rate_limit: &mut dyn FnMut(Duration),
+    ) -> Result<Value, AppError> {
+        self.call("GET", endpoint, query, None, on_rate_limit)
+    }
+
+    /// POSTs `body` to `endpoint`, see [`get`](Self::get)
+    pub(crate) fn post(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        body: &Value,
+    ) -> Result<Value, AppError> {
+        self.call("POST", endpoint, query, Some(body), &mut report_rate_limit)
+    }
+
+    /// Sends `method` to `endpoint` without a body, see [`get`](Self::get)
+    pub(crate) fn send_empty(
+        &self,
+        method: &str,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Value, AppError> {
+        self.call(method, endpoint, query, None, &mut report_rate_limit)
+    }
+
+    /// Sends a request with the client's token, renewing it on 401 and
+    /// sending the request once more
+    fn call(
+        &self,
+        method: &str,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        body: Option<&Value>,
+        on_rate_limit: &mut dyn FnMut(Duration),
+    ) -> Result<Value, AppError> {
+        self.renewing(|access_token| {
+            self.call_with(access_token, method, endpoint, query, body, on_rate_limit)
+        })
+    }
+
+    /// Calls `call` with the client's token. If Helix rejects it, the token
+    /// is renewed and `call` tried once more with the new one.
+    fn renewing<T>(
+        &self,
+        mut call: impl FnMut(&str) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let access_token = self.access_token();
+        match call(&access_token) {
+            Err(e) if e.is_unauthorized() => match self.renew_token(&access_token) {
+                Ok(true) => call(&self.access_token()),
+                // Failing to renew leaves the original error as the more telling one
+                _ => Err(e),
+            },
+            result => result,
+        }
+    }
+
+    fn get_with(
+        &self,
+        access_token: &str,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        on_rate_limit: &mut dyn FnMut(Duration),
+    ) -> Result<Value, AppError> {
+        self.call_with(access_token, "GET", endpoint, query, None, on_rate_limit)
+    }
+
+    /// Sends a request with `access_token`. Responses without a body, like
+    /// 204 No Content, come back as null.
+    fn call_with(
+        &self,
+        access_token: &str,
+        method: &str,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        body: Option<&Value>,
+        on_rate_limit: &mut dyn FnMut(Duration),
+    ) -> Result<Value, AppError> {
+        let client_id = client_id()?;
+
+        let mut request = self
+            .agent
+            .request(method, &format!("{}/{endpoint}", self.helix_url))
+            .set("Authorization", &format!("Bearer {access_token}"))
+            .set("Client-Id", &client_id);
+        for (key, value) in query {
+            request = request.query(key, value);
+        }
+
+        let text = send(request, body, on_rate_limit)?.into_string()?;
+        match text.trim().is_empty() {
+            true => Ok(Value::Null),
+            false => Ok(serde_json::from_str(&text)?),
+        }
+    }
+
+    /// Like [`get`](Self::get), but deserializes the response into `T`
+    fn get_typed<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, AppError> {
+        let json = self.get(endpoint, query)?;
+        T::deserialize(json).map_err(|e| AppError::UnexpectedResponse(endpoint.to_string(), e))
+    }
+
+    /// Fetches `endpoint` page by page, following the cursor for at most
+    /// `max_pages` pages, see [`paginate`]
+    fn get_pages<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        max_pages: usize,
+    ) -> Result<Vec<T>, AppError> {
+        self.get_pages_left(endpoint, query, max_pages)
+            .map(|(items, _)| items)
+    }
+
+    /// Fetches every page of `endpoint` up to [`MAX_LIST_PAGES`], warning
+    /// when that leaves some out
+    fn get_list<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<T>, AppError> {
+        let (items, left) = self.get_pages_left(endpoint, query, MAX_LIST_PAGES)?;
+        if left {
+            warn_list_cut(endpoint);
+        }
+        Ok(items)
+    }
+
+    /// The items of at most `max_pages` pages of `endpoint`, and whether
+    /// there were pages left
+    fn get_pages_left<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        max_pages: usize,
+    ) -> Result<(Vec<T>, bool), AppError> {
+        let mut items = Vec::new();
+        let left = paginate(endpoint, max_pages, |cursor| {
+            let mut page_query = query.to_vec();
+            page_query.extend(cursor.map(|cursor| ("after", cursor)));
+            let page = self.get_typed::<Page<T>>(endpoint, &page_query)?;
+            items.extend(page.data);
+            Ok(page.pagination.cursor)
+        })?;
+        Ok((items, left))
+    }
+
+    /// Channels whose name or title matches `query`, best matches first
+    pub fn search_channels(
+        &self,
+        query: &str,
+        live_only: bool,
+        max_pages: usize,
+    ) -> Result<Vec<SearchedChannel>, AppError> {
+        let live_only = live_only.to_string();
+        let query = [
+            ("query", query),
+            ("live_only", &live_only),
+            ("first", "100"),
+        ];
+        self.get_pages("search/channels", &query, max_pages)
+    }
+
+    /// Categories whose name matches `query`
+    pub fn search_games(&self, query: &str, max_pages: usize) -> Result<Vec<Game>, AppError> {
+        let query = [("query", query), ("first", "100")];
+        self.get_pages("search/categories", &query, max_pages)
+    }
+
+    /// The users `user_id` has blocked, which takes a user token of theirs
+    /// with the `user:read:blocked_users` scope
+    pub fn blocked_users(&self, user_id: &str) -> Result<Vec<BlockedUser>, AppError> {
+        let query = [("broadcaster_id", user_id), ("first", "100")];
+        self.get_list("users/blocks", &query)
+    }
+
+    /// Clips the last seconds of the live stream of `broadcaster_id`, for
+    /// the owner of the client's user token, which needs the `clips:edit`
+    /// scope
+    pub fn create_clip(&self, broadcaster_id: &str) -> Result<CreatedClip, AppError> {
+        let json = self.send_empty("POST", "clips", &[("broadcaster_id", broadcaster_id)])?;
+        created_clip(json)
+    }
+
+    /// The top `count` cheerers of the channel of the client's user token
+    /// over `period` (`day`, `week`, `month`, `year` or `all`), the one
+    /// containing `started_at` if given. Needs the `bits:read` scope.
+    pub fn bits_leaderboard(
+        &self,
+        count: u32,
+        period: &str,
+        started_at: Option<&str>,
+    ) -> Result<BitsLeaderboard, AppError> {
+        let count = count.to_string();
+        let mut query = vec![("count", count.as_str()), ("period", period)];
+        query.extend(started_at.map(|time| ("started_at", time)));
+        self.get_typed("bits/leaderboard", &query)
+    }
+
+    /// The subscriber totals of `broadcaster_id`, with every subscriber when
+    /// `all` and none otherwise. Takes a user token of theirs with the
+    /// `channel:read:subscriptions` scope.
+    pub fn subscribers(&self, broadcaster_id: &str, all: bool) -> Result<Subscribers, AppError> {
+        let (first, max_pages) = if all {
+            ("100", MAX_LIST_PAGES)
+        } else {
+            ("1", 1)
+        };
+        let query = [("broadcaster_id", broadcaster_id), ("first", first)];
+        let (mut subscribers, left) = collect_subscribers(max_pages, |cursor| {
+            let mut page_query = query.to_vec();
+            page_query.extend(cursor.map(|cursor| ("after", cursor)));
+            self.get_typed::<Subscribers>("subscriptions", &page_query)
+        })?;
+        match all {
+            true if left => warn_list_cut("subscriptions"),
+            true => {}
+            false => subscribers.data.clear(),
+        }
+        Ok(subscribers)
+    }
+
+    /// The analytics reports of the games of the owner of the client's user
+    /// token, or of `game_id` only, over `range` (start and end in RFC 3339)
+    /// or as long as there are any. Needs the `analytics:read:games` scope.
+    pub fn game_analytics(
+        &self,
+        game_id: Option<&str>,
+        range: Option<(&str, &str)>,
+    ) -> Result<Vec<GameAnalytics>, AppError> {
+        let mut query = vec![("first", "100")];
+        query.extend(game_id.map(|id| ("game_id", id)));
+        if let Some((started_at, ended_at)) = range {
+            query.extend([("started_at", started_at), ("ended_at", ended_at)]);
+        }
+        self.get_list("analytics/games", &query)
+    }
+
+    /// Blocks the user `target_user_id` for the owner of the client's user
+    /// token, which needs the `user:manage:blocked_users` scope
+    pub fn block_user(&self, target_user_id: &str) -> Result<(), AppError> {
+        self.send_empty("PUT", "users/blocks", &[("target_user_id", target_user_id)])
+            .map(|_| ())
+    }
+
+    /// Categories with the most viewers right now, most watched first
+    pub fn top_games(&self, max_pages: usize) -> Result<Vec<Game>, AppError> {
+        self.get_pages("games/top", &[("first", "100")], max_pages)
+    }
+
+    /// Videos of one game published within `period` (all, day, week or
+    /// month), newest first. `kind` is all, archive, highlight or upload.
+    pub fn fetch_videos(
+        &self,
+        game_id: &str,
+        period: &str,
+        kind: &str,
+        max_pages: usize,
+    ) -> Result<Vec<Video>, AppError> {
+        let query = [
+            ("game_id", game_id),
+            ("period", period),
+            ("type", kind),
+            ("sort", "time"),
+            ("first", "100"),
+        ];
+        self.get_pages("videos", &query, max_pages)
+    }
+
+    /// Turns a channel login into its user id
+    pub fn resolve_user(&self, login: &str) -> Result<String, AppError> {
+        let users = self.get_typed::<Page<User>>("users", &[("login", login)])?;
+        users
+            .data
+            .into_iter()
+            .next()
+            .map(|user| user.id)
+            .ok_or_else(|| AppError::UnknownChannel(login.to_string()))
+    }
+
+    /// The stream of the channel `login`, if it is live
+    pub fn fetch_live(&self, login: &str) -> Result<Option<Stream>, AppError> {
+        let streams = self.get_typed::<Page<Stream>>("streams", &[("user_login", login)])?;
+        Ok(streams.data.into_iter().next())
+    }
+
+    /// The streams of the channels in `logins` that are live
+    pub fn fetch_live_channels(&self, logins: &[String]) -> Result<Vec<Stream>, AppError> {
+        let mut live = Vec::new();
+        for chunk in logins.chunks(100) {
+            let mut params = vec![("first", "100")];
+            params.extend(chunk.iter().map(|login| ("user_login", login.as_str())));
+            live.extend(self.get_typed::<Page<Stream>>("streams", &params)?.data);
+        }
+        Ok(live)
+    }
+
+    /// Clips created between `since` and `until`, most viewed first. `filter`
+    /// is `("game_id", id)` or `("broadcaster_id", id)`.
+    pub fn fetch_clips(
+        &self,
+        filter: (&str, &str),
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        max_pages: usize,
+    ) -> Result<Vec<Clip>, AppError> {
+        let since = since.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true));
+        let until = until.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true));
+
+        let mut query = vec![filter, ("first", "100")];
+        if let Some(since) = &since {
+            query.push(("started_at", since));
+        }
+        if let Some(until) = &until {
+            query.push(("ended_at", until));
+        }
+        self.get_pages("clips", &query, max_pages)
+    }
+
+    /// Fetches a page of `list`, most watched first
+    fn fetch_streams(
+        &self,
+        list: StreamList,
+        languages: &[String],
+        after: Option<String>,
+        on_rate_limit: &mut dyn FnMut(Duration),
+    ) -> Result<Value, AppError> {
+        let (endpoint, query) = list.query(languages, after.as_deref());
+        self.get_observed(endpoint, &query, on_rate_limit)
+    }
+
+    /// Turns a `--game` argument into a game id. Numbers are taken as ids as-is,
+    /// anything else is looked up by its exact category name.
+    pub fn resolve_game(&self, name_or_id: &str) -> Result<String, AppError> {
+        if !name_or_id.is_empty() && name_or_id.chars().all(|c| c.is_ascii_digit()) {
+            return Ok(name_or_id.to_string());
+        }
+
+        let games = self.get_typed::<Page<Game>>("games", &[("name", name_or_id)])?;
+        match games.data.into_iter().next() {
+            Some(game) => Ok(game.id),
+            None => Err(AppError::UnknownGame(
+                name_or_id.to_string(),
+                self.similar_games(name_or_id),
+            )),
+        }
+    }
+
+    /// The category with this id, if there is one
+    pub fn game_by_id(&self, id: &str) -> Result<Option<Game>, AppError> {
+        let games = self.get_typed::<Page<Game>>("games", &[("id", id)])?;
+        Ok(games.data.into_iter().next())
+    }
+
+    /// Names of categories close to `name`, among those Twitch finds for it
+    /// and the most watched ones. Nothing if those can't be fetched.
+    fn similar_games(&self, name: &str) -> Vec<String> {
+        let found = self.search_games(name, 1).unwrap_or_default();
+        let top = self.top_games(1).unwrap_or_default();
+        let names = found
+            .into_iter()
+            .chain(top)
+            .map(|game| game.name)
+            .collect::<Vec<_>>();
+        closest_names(name, &names, SUGGESTED_GAMES)
+    }
+
+    /// Fetches every page of streams of `list`, see [`scan_streams`]
+    fn fetch_all_streams(
+        &self,
+        list: StreamList,
+        options: &FetchOptions,
+        raw: Option<&RawDump>,
+        progress: &ScanProgress,
+    ) -> Result<(Vec<StreamEntry>, FetchStats), AppError> {
+        scan_streams(options, raw, progress, |page| {
+            self.fetch_streams(list, &options.languages, page, &mut report_rate_limit)
+        })
+    }
+
+    /// Fetches the streams of each game in turn and merges them.
+    ///
+    /// Up to `options.concurrency` games are scanned at the same time. Every
+    /// raw page is also written to `save_raw`, if given.
+    pub fn fetch_games(
+        &self,
+        game_ids: &[String],
+        options: &FetchOptions,
+        save_raw: Option<&Path>,
+    ) -> Result<(Vec<StreamEntry>, FetchStats), AppError> {
+        let raw = save_raw.map(RawDump::create).transpose()?;
+
+        let progress = ScanProgress::new(options.progress);
+        let results = concurrently(game_ids, options.concurrency, |game_id| {
+            let list = StreamList::Game(game_id);
+            self.fetch_all_streams(list, options, raw.as_ref(), &progress)
+        });
+        progress.finish();
+        let results = results?;
+
+        Ok(merge_games(results))
+    }
+
+    /// Fetches the live streams of the channels `user_id` follows. Needs a
+    /// client with that user's access token.
+    pub fn fetch_followed(
+        &self,
+        user_id: &str,
+        options: &FetchOptions,
+        save_raw: Option<&Path>,
+    ) -> Result<(Vec<StreamEntry>, FetchStats), AppError> {
+        let raw = save_raw.map(RawDump::create).transpose()?;
+        let progress = ScanProgress::new(options.progress);
+        let list = StreamList::Followed(user_id);
+        let result = self.fetch_all_streams(list, options, raw.as_ref(), &progress);
+        progress.finish();
+        result
+    }
+}
+
+/// The first page of subscribers that `fetch` gets, with the subscribers of
+/// up to `max_pages` pages, and whether there were pages left
+fn collect_subscribers(
+    max_pages: usize,
+    mut fetch: impl FnMut(Option<&str>) -> Result<Subscribers, AppError>,
+) -> Result<(Subscribers, bool), AppError> {
+    let mut subscribers: Option<Subscribers> = None;
+    let left = paginate("subscriptions", max_pages, |cursor| {
+        let mut page = fetch(cursor)?;
+        let next = page.pagination.cursor.take();
+        match &mut subscribers {
+            Some(subscribers) => subscribers.data.extend(page.data),
+            None => subscribers = Some(page),
+        }
+        Ok(next)
+    })?;
+    let subscribers = subscribers.expect("the first page is always fetched");
+    Ok((subscribers, left))
+}
+
+/// The clip in the answer of creating one
+fn created_clip(json: Value) -> Result<CreatedClip, AppError> {
+    let error = |e| AppError::UnexpectedResponse("clips".to_string(), e);
+    Page::<CreatedClip>::deserialize(json)
+        .map_err(error)?
+        .data
+        .pop()
+        .ok_or_else(|| error(serde::de::Error::custom("no clip")))
+}
+
+/// The streams one paginated scan goes through
+#[derive(Debug, Clone, Copy)]
+enum StreamList<'a> {
+    /// Streams of a game id
+    Game(&'a str),
+    /// Streams of the channels a user id follows
+    Followed(&'a str),
+}
+
+impl<'a> StreamList<'a> {
+    /// The endpoint and query of the page after `after`
+    fn query(
+        self,
+        languages: &'a [String],
+        after: Option<&'a str>,
+    ) -> (&'static str, Vec<(&'static str, &'a str)>) {
+        let (endpoint, mut query) = match self {
+            StreamList::Game(game_id) => ("streams", vec![("game_id", game_id)]),
+            StreamList::Followed(user_id) => ("streams/followed", vec![("user_id", user_id)]),
+        };
+        query.push(("first", "100"));
+        // Only the streams endpoint takes languages, the rest are filtered later
+        if let StreamList::Game(_) = self {
+            query.extend(languages.iter().map(|lang| ("language", lang.as_str())));
+        }
+        if let Some(after) = after {
+            query.push(("after", after));
+        }
+        (endpoint, query)
+    }
+}
+
+/// The streams of every game in one list, in the order of the games. A
+/// channel in more than one game (it switched while scanning) is kept once.
+fn merge_games(results: Vec<(Vec<StreamEntry>, FetchStats)>) -> (Vec<StreamEntry>, FetchStats) {
+    let mut entries = Vec::new();
+    let mut stats = FetchStats::default();
+    let mut seen = HashSet::new();
+    for (game_entries, game_stats) in results {
+        let fetched = game_entries.len();
+        let before = entries.len();
+        entries.extend(
+            game_entries
+                .into_iter()
+                .filter(|e| seen.insert(e.user_id.clone())),
+        );
+        stats.duplicates += game_stats.duplicates + fetched - (entries.len() - before);
+        stats.pages += game_stats.pages;
+        stats.capped |= game_stats.capped;
+        if let Some(incomplete) = game_stats.incomplete {
+            stats.incomplete = match stats.incomplete {
+                Some(previous) => Some(format!("{previous}; {incomplete}")),
+                None => Some(incomplete),
+            };
+        }
+    }
+    (entries, stats)
+}
+
+/// Fetches every page of streams with `fetch`, which gets the cursor of the
+/// page to fetch, telling `progress` about every page. Viewer counts shift
+/// while paginating, so a stream can turn up on two pages; the second time is
+/// skipped and counted in [`FetchStats::duplicates`].
+///
+/// Stops with [`AppError::RunawayPagination`] if the API hands back a cursor it has
+/// already given us. Ends early after `max_pages` pages or once `limit` streams
+/// match, see [`FetchStats::capped`]. With `keep_partial`, a failing page ends the
+/// scan and the pages fetched so far are returned instead of the error, with the
+/// reason in [`FetchStats::incomplete`].
+fn scan_streams(
+    options: &FetchOptions,
+    raw: Option<&RawDump>,
+    progress: &ScanProgress,
+    mut fetch: impl FnMut(Option<String>) -> Result<Value, AppError>,
+) -> Result<(Vec<StreamEntry>, FetchStats), AppError> {
+    let FetchOptions {
+        max_pages,
+        keep_partial,
+        min_viewers,
+        limit,
+        ref filter,
+        ..
+    } = *options;
+
+    let mut entries = Vec::new();
+    let mut seen_cursors = SeenCursors::default();
+    let mut seen_ids = HashSet::new();
+    let mut duplicates = 0;
+    let mut pages = 0;
+    let mut matched = 0;
+    let mut capped = false;
+    let mut page = None;
+    loop {
+        let result = fetch(page)
+            .and_then(|json| {
+                if let Some(raw) = raw {
+                    raw.write(&json)?;
+                }
+                parse_streams(&json)
+            })
+            .map(|(page_entries, next_page)| {
+                let below_min = min_viewers.is_some_and(|min| {
+                    page_entries
+                        .last()
+                        .is_some_and(|last| last.viewer_count < min)
+                });
+                let next_page = next_page.filter(|_| !below_min);
+                (page_entries, next_page)
+            })
+            .and_then(|(page_entries, next_page)| {
+                let next_page =
+                    seen_cursors.check(next_page, || format!("after {} pages", pages + 1))?;
+                Ok((page_entries, next_page))
+            });
+
+        let (page_entries, next_page) = match result {
+            Ok(result) => result,
+            Err(e) if keep_partial && pages > 0 => {
+                let incomplete =
+                    format!("stopped after {pages} pages, results are incomplete: {e}");
+                let stats = FetchStats {
+                    pages,
+                    incomplete: Some(incomplete),
+                    capped: false,
+                    duplicates,
+                };
+                return Ok((entries, stats));
+            }
+            Err(e) => return Err(e),
+        };
+
+        pages += 1;
+        let fetched = page_entries.len();
+        let page_entries = page_entries
+            .into_iter()
+            .filter(|e| seen_ids.insert(e.id.clone()))
+            .collect::<Vec<_>>();
+        duplicates += fetched - page_entries.len();
+
+        let page_matched = match filter {
+            Some(filter) => page_entries.iter().filter(|e| filter.matches(e)).count(),
+            None => page_entries.len(),
+        };
+        matched += page_matched;
+        progress.page(page_matched);
+        entries.extend(page_entries);
+        page = next_page;
+
+        if page.is_none() {
+            break;
+        }
+        if pages >= max_pages || limit.is_some_and(|limit| matched >= limit) {
+            capped = true;
+            break;
+        }
+    }
+
+    let stats = FetchStats {
+        pages,
+        incomplete: None,
+        capped,
+        duplicates,
+    };
+    Ok((entries, stats))
+}
+
+#[derive(Debug, Default)]
+pub struct FetchStats {
+    pub pages: usize,
+    /// Why the scan ended early, when partial results were kept
+    pub incomplete: Option<String>,
+    /// Whether `max_pages` or `limit` ended a scan before the last page
+    pub capped: bool,
+    /// Streams skipped for having been on an earlier page already
+    pub duplicates: usize,
+}
+
+/// File that `--save-raw` writes every fetched page to, one JSON object per line
+#[derive(Debug)]
+struct RawDump {
+    path: PathBuf,
+    file: Mutex<fs::File>,
+}
+
+impl RawDump {
+    fn create(path: &Path) -> Result<Self, AppError> {
+        let file = fs::File::create(path)
+            .map_err(|e| AppError::WriteDump(path.display().to_string(), e))?;
+        Ok(RawDump {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    fn write(&self, page: &Value) -> Result<(), AppError> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(file, "{page}")
+            .map_err(|e| AppError::WriteDump(self.path.display().to_string(), e))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// Pages to fetch at most per game
+    pub max_pages: usize,
+    pub keep_partial: bool,
+    pub progress: Progress,
+    /// Stop paginating once a page ends below this many viewers. Helix sorts
+    /// streams by viewer count, so later pages can't have any matches.
+    pub min_viewers: Option<i64>,
+    /// Only fetch streams in these languages (at most 100), or all if empty
+    pub languages: Vec<String>,
+    /// How many games to scan at the same time
+    pub concurrency: usize,
+    /// Stop paginating once this many streams match
+    pub limit: Option<usize>,
+    /// What counts as a match, for `limit` and the progress bar. Without
+    /// one, every stream does.
+    pub filter: Option<SearchFilter>,
+}
+
+/// How a scan shows that it is getting somewhere, on stderr
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Progress {
+    /// A dot per page
+    Dots,
+    /// Pages fetched and streams matched so far
+    Bar,
+    /// Nothing
+    None,
+}
+
+/// Progress of the scans of one fetch, which may run at the same time
+struct ScanProgress {
+    style: Progress,
+    bar: Option<ProgressBar>,
+    pages: AtomicUsize,
+    matched: AtomicUsize,
+}
+
+impl ScanProgress {
+    fn new(style: Progress) -> Self {
+        let bar = (style == Progress::Bar).then(|| {
+            let bar = ProgressBar::new_spinner();
+            bar.set_style(
+                ProgressStyle::with_template("{spinner} {msg} [{elapsed}]")
+                    .expect("the template is valid"),
+            );
+            bar.enable_steady_tick(Duration::from_millis(100));
+            bar
+        });
+        ScanProgress {
+            style,
+            bar,
+            pages: AtomicUsize::new(0),
+            matched: AtomicUsize::new(0),
+        }
+    }
+
+    fn page(&self, matched: usize) {
+        let pages = self.pages.fetch_add(1, Ordering::Relaxed) + 1;
+        let matched = self.matched.fetch_add(matched, Ordering::Relaxed) + matched;
+        match &self.bar {
+            Some(bar) => bar.set_message(format!("{pages} pages, {matched} matches")),
+            None if self.style == Progress::Dots => {
+                eprint!(".");
+                let _ = std::io::stderr().flush();
+            }
+            None => {}
+        }
+    }
+
+    fn finish(&self) {
+        match &self.bar {
+            Some(bar) => bar.finish_and_clear(),
+            None if self.style == Progress::Dots => eprintln!(),
+            None => {}
+        }
+    }
+}
+
+/// Reads pages saved with `--save-raw`, one JSON response per line
+pub fn load_streams(path: &Path) -> Result<(Vec<StreamEntry>, FetchStats), AppError> {
+    let read_error = |e| AppError::ReadDump(path.display().to_string(), e);
+    let file = fs::File::open(path).map_err(read_error)?;
+
+    let mut entries = Vec::new();
+    let mut stats = FetchStats::default();
+    let mut seen_ids = HashSet::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(read_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (page_entries, _) = parse_streams(&serde_json::from_str(&line)?)?;
+        let fetched = page_entries.len();
+        let before = entries.len();
+        entries.extend(
+            page_entries
+                .into_iter()
+                .filter(|e| seen_ids.insert(e.id.clone())),
+        );
+        stats.duplicates += fetched - (entries.len() - before);
+        stats.pages += 1;
+    }
+
+    Ok((entries, stats))
+}
+
+// -----------------------------------------------------------------------------
+//     - EventSub -
+// -----------------------------------------------------------------------------
+
+/// How EventSub delivers the events of a subscription
+#[derive(Debug, Clone, Copy)]
+pub enum EventSubTransport<'a> {
+    /// POSTed to `callback`, signed with `secret`. Needs an app access token.
+    Webhook { callback: &'a str, secret: &'a str },
+    /// Sent over the WebSocket session `session_id`. Needs a user access
+    /// token.
+    WebSocket { session_id: &'a str },
+}
+
+impl TwitchClient {
+    /// Has Twitch send an event over `transport` whenever `broadcaster_id`
+    /// goes live
+    pub fn subscribe_stream_online(
+        &self,
+        broadcaster_id: &str,
+        transport: EventSubTransport,
+    ) -> Result<Subscription, AppError> {
+        let transport = match transport {
+            EventSubTransport::Webhook { callback, secret } => serde_json::json!({
+                "method": "webhook",
+                "callback": callback,
+                "secret": secret,
+            }),
+            EventSubTransport::WebSocket { session_id } => serde_json::json!({
+                "method": "websocket",
+                "session_id": session_id,
+            }),
+        };
+        let body = serde_json::json!({
+            "type": "stream.online",
+            "version": "1",
+            "condition": { "broadcaster_user_id": broadcaster_id },
+            "transport": transport,
+        });
+        let json = self.post("eventsub/subscriptions", &[], &body)?;
+        let error = |e| AppError::UnexpectedResponse("eventsub/subscriptions".to_string(), e);
+        Page::<Subscription>::deserialize(json)
+            .map_err(error)?
+            .data
+            .pop()
+            .ok_or_else(|| error(serde::de::Error::custom("no subscription")))
+    }
+
+    /// The EventSub subscriptions of the app, of all transports
+    pub fn eventsub_subscriptions(&self) -> Result<Vec<Subscription>, AppError> {
+        self.get_list("eventsub/subscriptions", &[])
+    }
+
+    pub fn unsubscribe(&self, subscription_id: &str) -> Result<(), AppError> {
+        self.send_empty(
+            "DELETE",
+            "eventsub/subscriptions",
+            &[("id", subscription_id)],
+        )
+        .map(|_| ())
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Lazy scans -
+// -----------------------------------------------------------------------------
+
+/// Which live streams [`TwitchClient::streams`] goes through
+#[derive(Debug, Clone, Default)]
+pub struct StreamQuery {
+    /// Games or categories by id, scanned one after the other. None scans
+    /// [`DEFAULT_GAME_IDS`].
+    pub game_ids: Vec<String>,
+    /// Only streams in these languages (at most 100), or all if empty
+    pub languages: Vec<String>,
+}
+
+/// Pages of the streams of a [`StreamQuery`], fetched one at a time
+#[derive(Debug)]
+pub(crate) struct StreamPages<'a> {
+    client: &'a TwitchClient,
+    languages: Vec<String>,
+    games: std::vec::IntoIter<String>,
+    /// The game being scanned, and the cursor of its next page unless it
+    /// is on its first
+    current: Option<(String, Option<String>)>,
+    seen_cursors: SeenCursors,
+    seen_users: HashSet<String>,
+    failed: bool,
+}
+
+impl<'a> StreamPages<'a> {
+    pub(crate) fn new(client: &'a TwitchClient, query: StreamQuery) -> Self {
+        let games = match query.game_ids.is_empty() {
+            true => DEFAULT_GAME_IDS.iter().map(|id| id.to_string()).collect(),
+            false => query.game_ids,
+        };
+        StreamPages {
+            client,
+            languages: query.languages,
+            games: games.into_iter(),
+            current: None,
+            seen_cursors: SeenCursors::default(),
+            seen_users: HashSet::new(),
+            failed: false,
+        }
+    }
+
+    /// The next page, with the id of its game. Channels already seen, on an
+    /// earlier page or in an earlier game, are left out. Nothing once every
+    /// game is done, or after an error.
+    pub(crate) fn next_page(
+        &mut self,
+        on_rate_limit: &mut dyn FnMut(Duration),
+    ) -> Option<Result<(String, Vec<StreamEntry>), AppError>> {
+        if self.failed {
+            return None;
+        }
+        let (game_id, cursor) = match self.current.take() {
+            Some(current) => current,
+            None => (self.games.next()?, None),
+        };
+
+        let result = self
+            .client
+            .fetch_streams(
+                StreamList::Game(&game_id),
+                &self.languages,
+                cursor,
+                on_rate_limit,
+            )
+            .and_then(|json| parse_streams(&json))
+            .and_then(|(entries, next_page)| {
+                let next_page = self
+                    .seen_cursors
+                    .check(next_page, || format!("for game {game_id}"))?;
+                Ok((entries, next_page))
+            });
+        let (entries, next_page) = match result {
+            Ok(page) => page,
+            Err(e) => {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        };
+
+        if next_page.is_some() {
+            self.current = Some((game_id.clone(), next_page));
+        }
+        let entries = entries
+            .into_iter()
+            .filter(|e| self.seen_users.insert(e.user_id.clone()))
+            .collect();
+        Some(Ok((game_id, entries)))
+    }
+}
+
+/// The live streams of a [`StreamQuery`], see [`TwitchClient::streams`]
+#[derive(Debug)]
+pub struct Streams<'a> {
+    pages: StreamPages<'a>,
+    page: std::vec::IntoIter<StreamEntry>,
+}
+
+impl Iterator for Streams<'_> {
+    type Item = Result<StreamEntry, AppError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.page.next() {
+                return Some(Ok(entry));
+            }
+            match self.pages.next_page(&mut report_rate_limit)? {
+                Ok((_, entries)) => self.page = entries.into_iter(),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl TwitchClient {
+    /// The live streams of `query`, most watched first within each game,
+    /// fetched a page at a time as the iterator gets to them. Like the other
+    /// requests, those for pages renew the token and wait for the rate limit
+    /// as needed. A channel is only given once, even if it turns up again on
+    /// a later page; the iterator ends after the first error.
+    pub fn streams(&self, query: StreamQuery) -> Streams<'_> {
+        Streams {
+            pages: StreamPages::new(self, query),
+            page: Vec::new().into_iter(),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - User hydration -
+// -----------------------------------------------------------------------------
+
+/// Helix accepts at most this many ids per users/channels request
+const HYDRATION_BATCH: usize = 100;
+
+/// Number of hydration requests in flight at the same time
+const HYDRATION_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HydrateOptions {
+    /// Also fetch channel information (content classification labels)
+    pub channels: bool,
+    /// Also fetch follower counts, one request per channel. Only done with a
+    /// user access token (see [`user_access_token`]), which Helix requires.
+    pub followers: bool,
+}
+
+/// Runs `f` on every item, at most `limit` at a time, returning the results
+/// in the order of the items. Each of the `limit` workers takes the next item
+/// as soon as it is done with one, and none are taken after a failure.
+fn concurrently<T: Sync, R: Send>(
+    items: &[T],
+    limit: usize,
+    f: impl Fn(&T) -> Result<R, AppError> + Sync,
+) -> Result<Vec<R>, AppError> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let work = || {
+        let mut done = Vec::new();
+        while !failed.load(Ordering::Relaxed) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(index) else {
+                break;
+            };
+            let result = f(item);
+            if result.is_err() {
+                failed.store(true, Ordering::Relaxed);
+            }
+            done.push((index, result));
+        }
+        done
+    };
+    let mut done = thread::scope(|scope| {
+        let workers = (0..limit.clamp(1, items.len().max(1)))
+            .map(|_| scope.spawn(work))
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("worker thread panicked"))
+            .collect::<Vec<_>>()
+    });
+    // Items are taken in order, so those before a failure are all done
+    done.sort_by_key(|(index, _)| *index);
+    done.into_iter().map(|(_, result)| result).collect()
+}
+
+impl TwitchClient {
+    /// Fills in [`StreamEntry::user`] for every entry, fetching users (and channel
+    /// information if requested) in batches of [`HYDRATION_BATCH`].
+    pub fn hydrate(
+        &self,
+        entries: &mut [StreamEntry],
+        options: HydrateOptions,
+    ) -> Result<(), AppError> {
+        let mut ids = entries
+            .iter()
+            .map(|e| e.user_id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+
+        let now = Utc::now();
+        let mut cache = match self.uses_cache() {
+            true => UserCache::load(),
+            false => UserCache::default(),
+        };
+        let mut users = HashMap::new();
+        let mut missing = Vec::new();
+        for id in &ids {
+            match cache.get(id, options.channels, now) {
+                Some(user) => {
+                    users.insert(id.clone(), user.clone());
+                }
+                None => missing.push(id.clone()),
+            }
+        }
+
+        let batches = missing.chunks(HYDRATION_BATCH).collect::<Vec<_>>();
+        for batch in concurrently(&batches, HYDRATION_CONCURRENCY, |batch| {
+            self.fetch_user_batch(batch, options.channels)
+        })? {
+            for (id, user) in batch {
+                cache.insert(&id, user.clone(), options.channels, now);
+                users.insert(id, user);
+            }
+        }
+
+        // Follower counts need a user access token, so without one they are
+        // left out, as are those of channels whose request failed
+        let uncounted = ids
+            .iter()
+            .filter(|id| users.get(*id).is_some_and(|user| user.followers.is_none()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(user_token) = (options.followers && !uncounted.is_empty())
+            .then(|| user_access_token(&self.agent).ok())
+            .flatten()
+        {
+            let counts = concurrently(&uncounted, HYDRATION_CONCURRENCY, |id| {
+                Ok(self.fetch_follower_count(&user_token, id).ok())
+            })?;
+            for (id, count) in uncounted.iter().zip(counts) {
+                if let Some(user) = users.get_mut(id) {
+                    user.followers = count;
+                }
+                cache.set_followers(id, count);
+            }
+        }
+
+        // A cache that can't be written just means fetching again next time
+        if self.uses_cache() && cache.changed {
+            let _ = cache.store(now);
+        }
+
+        for entry in entries {
+            entry.user = users.get(&entry.user_id).cloned();
+        }
+
+        Ok(())
+    }
+
+    /// Sent with `user_token` rather than the client's own token, and never
+    /// renews it: a 401 here means the user token is missing a grant, not
+    /// that the app token expired
+    fn fetch_follower_count(&self, user_token: &str, id: &str) -> Result<i64, AppError> {
+        let query = [("broadcaster_id", id), ("first", "1")];
+        let json = self.get_with(
+            user_token,
+            "channels/followers",
+            &query,
+            &mut report_rate_limit,
+        )?;
+        let followers = Followers::deserialize(json)
+            .map_err(|e| AppError::UnexpectedResponse("channels/followers".to_string(), e))?;
+        Ok(followers.total)
+    }
+
+    fn fetch_user_batch(
+        &self,
+        ids: &[String],
+        channels: bool,
+    ) -> Result<HashMap<String, UserInfo>, AppError> {
+        let query = ids.iter().map(|id| ("id", id.as_str())).collect::<Vec<_>>();
+        let mut users = self
+            .get_typed::<Page<User>>("users", &query)?
+            .data
+            .into_iter()
+            .map(|u| {
+                let info = UserInfo {
+                    broadcaster_type: u.broadcaster_type,
+                    description: u.description,
+                    created_at: u.created_at.parse().ok(),
+                    ..Default::default()
+                };
+                (u.id, info)
+            })
+            .collect::<HashMap<_, _>>();
+
+        if channels {
+            let query = ids
+                .iter()
+                .map(|id| ("broadcaster_id", id.as_str()))
+                .collect::<Vec<_>>();
+            let channels = self.get_typed::<Page<Channel>>("channels", &query)?;
+            for channel in channels.data {
+                if let Some(user) = users.get_mut(&channel.broadcaster_id) {
+                    user.content_labels = channel.content_classification_labels;
+                }
+            }
+        }
+
+        Ok(users)
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - User cache -
+// -----------------------------------------------------------------------------
+
+/// How long hydrated user metadata is reused before it is fetched again
+const USER_CACHE_TTL: chrono::Duration = chrono::Duration::hours(24);
+
+/// User metadata from earlier runs, kept in `users.json` in the cache
+/// directory so repeated searches don't fetch the same channels every time
+#[derive(Debug, Default)]
+pub(crate) struct UserCache {
+    users: HashMap<String, CachedUser>,
+    /// Whether anything was added since it was loaded
+    changed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct CachedUser {
+    fetched_at: DateTime<Utc>,
+    info: UserInfo,
+    /// Whether the content labels were fetched, rather than just empty
+    channels: bool,
+}
+
+impl UserCache {
+    pub(crate) fn path() -> Option<PathBuf> {
+        cache_dir().map(|dir| dir.join("users.json"))
+    }
+
+    /// Reads the cache. Any problem just means an empty cache.
+    pub(crate) fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .map(|json| Self::from_json(&json))
+            .unwrap_or_default()
+    }
+
+    /// Writes the cache, leaving out users older than [`USER_CACHE_TTL`]
+    fn store(&self, now: DateTime<Utc>) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, format!("{}\n", self.to_json(now)))
+    }
+
+    fn from_json(json: &Value) -> Self {
+        let users = json
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(id, user)| {
+                let text = |key: &str| user.get(key).and_then(Value::as_str);
+                let info = UserInfo {
+                    broadcaster_type: text("broadcaster_type")?.to_string(),
+                    description: text("description")?.to_string(),
+                    created_at: text("created_at").and_then(|t| t.parse().ok()),
+                    content_labels: user
+                        .get("content_labels")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|label| label.as_str().map(str::to_string))
+                        .collect(),
+                    followers: user.get("followers").and_then(Value::as_i64),
+                };
+                let cached = CachedUser {
+                    fetched_at: text("fetched_at")?.parse().ok()?,
+                    channels: user.get("content_labels").is_some_and(Value::is_array),
+                    info,
+                };
+                Some((id.clone(), cached))
+            })
+            .collect();
+        UserCache {
+            users,
+            changed: false,
+        }
+    }
+
+    fn to_json(&self, now: DateTime<Utc>) -> Value {
+        let users = self
+            .users
+            .iter()
+            .filter(|(_, user)| now - user.fetched_at < USER_CACHE_TTL)
+            .map(|(id, user)| {
+                let json = serde_json::json!({
+                    "fetched_at": user.fetched_at.to_rfc3339(),
+                    "broadcaster_type": user.info.broadcaster_type,
+                    "description": user.info.description,
+                    "created_at": user.info.created_at.map(|t| t.to_rfc3339()),
+                    "content_labels": user.channels.then_some(&user.info.content_labels),
+                    "followers": user.info.followers,
+                });
+                (id.clone(), json)
+            })
+            .collect();
+        Value::Object(users)
+    }
+
+    /// The metadata of `id` if it was fetched less than [`USER_CACHE_TTL`]
+    /// ago, with the content labels if `channels` asks for them
+    fn get(&self, id: &str, channels: bool, now: DateTime<Utc>) -> Option<&UserInfo> {
+        self.users
+            .get(id)
+            .filter(|user| now - user.fetched_at < USER_CACHE_TTL)
+            .filter(|user| user.channels || !channels)
+            .map(|user| &user.info)
+    }
+
+    fn insert(&mut self, id: &str, info: UserInfo, channels: bool, now: DateTime<Utc>) {
+        let user = CachedUser {
+            fetched_at: now,
+            info,
+            channels,
+        };
+        self.users.insert(id.to_string(), user);
+        self.changed = true;
+    }
+
+    fn set_followers(&mut self, id: &str, followers: Option<i64>) {
+        if let Some(user) = self.users.get_mut(id) {
+            user.info.followers = followers;
+            self.changed = true;
+        }
+    }
+
+    /// When each cached user was fetched, fresh or not
+    pub(crate) fn fetched_at(&self) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        self.users.values().map(|user| user.fetched_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::{test_entry, test_stream};
+    use crate::filter::test_filter;
+
+    fn fetch_options(max_pages: usize, keep_partial: bool) -> FetchOptions {
+        FetchOptions {
+            max_pages,
+            keep_partial,
+            progress: Progress::None,
+            min_viewers: None,
+            languages: vec![],
+            concurrency: 1,
+            limit: None,
+            filter: None,
+        }
+    }
+
+    /// Pages of one stream each, handing out `cursors` in turn and then none
+    fn pages(cursors: &[&str]) -> impl FnMut(Option<String>) -> Result<Value, AppError> {
+        let mut cursors = cursors
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .into_iter();
+        let mut page = 0;
+        move |_| {
+            page += 1;
+            Ok(serde_json::json!({
+                "data": [test_stream(&page.to_string(), 10)],
+                "pagination": { "cursor": cursors.next() },
+            }))
+        }
+    }
+
+    #[test]
+    fn throttle_needs_a_sane_rate() {
+        for rate in [1e-300, 0.0, -1.0, f64::INFINITY, f64::NAN] {
+            assert!(matches!(
+                set_throttle(rate),
+                Err(AppError::InvalidThrottle(_))
+            ));
+        }
+        assert!(THROTTLE.get().is_none());
+    }
+
+    #[test]
+    fn scan_stops_at_a_repeated_cursor() {
+        let error = scan_streams(
+            &fetch_options(100, false),
+            None,
+            &ScanProgress::new(Progress::None),
+            pages(&["1", "2", "1"]),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"Stopped paginating: the API returned cursor "1" twice after 3 pages"#
+        );
+
+        let (entries, stats) = scan_streams(
+            &fetch_options(100, true),
+            None,
+            &ScanProgress::new(Progress::None),
+            pages(&["1", "2", "1"]),
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(stats.pages, 2);
+    }
+
+    #[test]
+    fn scan_skips_streams_seen_before() {
+        let mut pages = [("a", Some("1")), ("b", Some("2")), ("a", None)].into_iter();
+        let (entries, stats) = scan_streams(
+            &fetch_options(100, false),
+            None,
+            &ScanProgress::new(Progress::None),
+            |_| {
+                let (name, cursor) = pages.next().unwrap();
+                Ok(serde_json::json!({
+                    "data": [test_stream(name, 10)],
+                    "pagination": { "cursor": cursor },
+                }))
+            },
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((stats.pages, stats.duplicates, stats.capped), (3, 1, false));
+    }
+
+    #[test]
+    fn scan_stops_at_max_pages_or_the_limit() {
+        let cursors = ["1", "2", "3"];
+        let (entries, stats) = scan_streams(
+            &fetch_options(100, false),
+            None,
+            &ScanProgress::new(Progress::None),
+            pages(&cursors),
+        )
+        .unwrap();
+        assert_eq!((entries.len(), stats.pages, stats.capped), (4, 4, false));
+
+        let (entries, stats) = scan_streams(
+            &fetch_options(2, false),
+            None,
+            &ScanProgress::new(Progress::None),
+            pages(&cursors),
+        )
+        .unwrap();
+        assert_eq!((entries.len(), stats.pages, stats.capped), (2, 2, true));
+        assert_eq!(stats.incomplete, None);
+
+        // Only streams passing the filter count towards the limit
+        let limited = |term| FetchOptions {
+            limit: Some(3),
+            filter: Some(test_filter(&[term])),
+            ..fetch_options(100, false)
+        };
+        let (entries, stats) = scan_streams(
+            &limited("rust"),
+            None,
+            &ScanProgress::new(Progress::None),
+            pages(&cursors),
+        )
+        .unwrap();
+        assert_eq!((entries.len(), stats.capped), (3, true));
+        let (entries, stats) = scan_streams(
+            &limited("zig"),
+            None,
+            &ScanProgress::new(Progress::None),
+            pages(&cursors),
+        )
+        .unwrap();
+        assert_eq!((entries.len(), stats.capped), (4, false));
+    }
+
+    #[test]
+    fn scan_keeps_pages_before_a_failure() {
+        let failing_scan = |keep_partial| {
+            let mut pages = 0;
+            let fetch_page = |_| {
+                pages += 1;
+                match pages {
+                    1 => Ok(serde_json::json!({
+                        "data": [test_stream("a", 10)],
+                        "pagination": { "cursor": "1" },
+                    })),
+                    _ => Err(AppError::ReadStreams(std::io::ErrorKind::TimedOut.into())),
+                }
+            };
+            scan_streams(
+                &fetch_options(100, keep_partial),
+                None,
+                &ScanProgress::new(Progress::None),
+                fetch_page,
+            )
+        };
+
+        assert!(failing_scan(false).is_err());
+        let (entries, stats) = failing_scan(true).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].display_name, "a");
+        assert_eq!(stats.pages, 1);
+        assert!(stats
+            .incomplete
+            .unwrap()
+            .starts_with("stopped after 1 pages"));
+    }
+
+    #[test]
+    fn game_ids_need_no_lookup() {
+        // Any lookup would fail without a token
+        let client = TwitchClient::new(configure_agent(), String::new());
+        assert_eq!(client.resolve_game("509658").unwrap(), "509658");
+    }
+
+    #[test]
+    fn games_merge_into_one_list() {
+        let game = |names: &[&str], pages, incomplete: Option<&str>| {
+            let entries = names.iter().map(|name| test_entry(name, 10)).collect();
+            let stats = FetchStats {
+                pages,
+                incomplete: incomplete.map(str::to_string),
+                ..FetchStats::default()
+            };
+            (entries, stats)
+        };
+        let (entries, stats) = merge_games(vec![
+            game(&["a", "b"], 2, None),
+            game(&["c", "a"], 1, Some("game 2 failed")),
+            game(&["d"], 1, Some("game 3 failed")),
+        ]);
+        let names = entries.iter().map(|e| e.display_name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["a", "b", "c", "d"]);
+        assert_eq!(stats.pages, 4);
+        assert_eq!(
+            stats.incomplete.as_deref(),
+            Some("game 2 failed; game 3 failed")
+        );
+    }
+
+    #[test]
+    fn raw_dump_round_trip() {
+        let path = env::temp_dir().join(format!("twitch-search-dump-{}.jsonl", std::process::id()));
+        let raw = RawDump::create(&path).unwrap();
+        let scanned = scan_streams(
+            &fetch_options(100, false),
+            Some(&raw),
+            &ScanProgress::new(Progress::None),
+            pages(&["1", "2"]),
+        );
+        drop(raw);
+
+        let loaded = load_streams(&path);
+        fs::remove_file(&path).unwrap();
+        let (scanned, _) = scanned.unwrap();
+        let (loaded, stats) = loaded.unwrap();
+        assert_eq!(stats.pages, 3);
+        let names = |entries: &[StreamEntry]| {
+            entries
+                .iter()
+                .map(|e| e.display_name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&loaded), names(&scanned));
+        assert_eq!(names(&loaded), ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn created_clips() {
+        let clip = created_clip(serde_json::json!({
+            "data": [{
+                "id": "FiveWordsForClipSlug",
+                "edit_url": "https://clips.twitch.tv/FiveWordsForClipSlug/edit",
+            }],
+        }))
+        .unwrap();
+        assert_eq!(clip.id, "FiveWordsForClipSlug");
+        assert!(clip.edit_url.ends_with("/edit"));
+
+        let error = created_clip(serde_json::json!({ "data": [] })).unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected response from clips: no clip");
+    }
+
+    fn subscribers_page(names: &[&str], cursor: Option<&str>) -> Subscribers {
+        let data = names
+            .iter()
+            .map(|name| {
+                serde_json::json!({
+                    "user_id": name.to_lowercase(),
+                    "user_login": name.to_lowercase(),
+                    "user_name": name,
+                    "tier": "1000",
+                    "is_gift": false,
+                })
+            })
+            .collect::<Vec<_>>();
+        Subscribers::deserialize(serde_json::json!({
+            "data": data,
+            "pagination": { "cursor": cursor },
+            "total": 3,
+            "points": 4,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn subscribers_of_every_page() {
+        let mut after = vec![];
+        let (subscribers, left) = collect_subscribers(10, |cursor| {
+            after.push(cursor.map(str::to_string));
+            Ok(match cursor {
+                None => subscribers_page(&["Ferris", "Crab"], Some("1")),
+                _ => subscribers_page(&["Corro"], None),
+            })
+        })
+        .unwrap();
+        assert_eq!(after, [None, Some("1".to_string())]);
+        assert!(!left);
+        let names = subscribers.data.iter().map(|s| &s.user_name);
+        assert_eq!(names.collect::<Vec<_>>(), ["Ferris", "Crab", "Corro"]);
+        assert_eq!((subscribers.total, subscribers.points), (3, 4));
+
+        let looping = collect_subscribers(10, |_| Ok(subscribers_page(&["Ferris"], Some("1"))));
+        assert!(matches!(looping, Err(AppError::RunawayPagination(_))));
+
+        let (totals, left) =
+            collect_subscribers(1, |_| Ok(subscribers_page(&["Ferris"], Some("1")))).unwrap();
+        assert_eq!(totals.data.len(), 1);
+        assert!(left);
+    }
+
+    #[test]
+    fn concurrently_keeps_order_and_limit() {
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+        let items = (0..10).collect::<Vec<_>>();
+        let results = concurrently(&items, 3, |&i| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(i * 2)
+        })
+        .unwrap();
+        assert_eq!(results, (0..20).step_by(2).collect::<Vec<_>>());
+        assert!(most.load(Ordering::SeqCst) <= 3);
+
+        // A slow item holds up its worker only, the other takes the rest
+        let finished = Mutex::new(Vec::new());
+        concurrently(&items, 2, |&i| {
+            if i == 0 {
+                thread::sleep(Duration::from_millis(100));
+            }
+            finished.lock().unwrap().push(i);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(finished.into_inner().unwrap().last(), Some(&0));
+
+        let failed = concurrently(&items, 3, |&i| match i {
+            7 => Err(AppError::UnknownChannel(i.to_string())),
+            _ => Ok(i),
+        });
+        assert!(matches!(failed, Err(AppError::UnknownChannel(id)) if id == "7"));
+    }
+
+    #[test]
+    fn scan_stops_below_the_minimum_viewers() {
+        let options = FetchOptions {
+            min_viewers: Some(20),
+            ..fetch_options(100, false)
+        };
+        let (entries, stats) = scan_streams(
+            &options,
+            None,
+            &ScanProgress::new(Progress::None),
+            pages(&["1", "2"]),
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(stats.pages, 1);
+    }
+
+    #[test]
+    fn followed_channels_query() {
+        let languages = ["en".to_string(), "sv".to_string()];
+        assert_eq!(
+            StreamList::Game("509658").query(&languages, None),
+            (
+                "streams",
+                vec![
+                    ("game_id", "509658"),
+                    ("first", "100"),
+                    ("language", "en"),
+                    ("language", "sv"),
+                ]
+            )
+        );
+        // Languages are filtered after fetching the followed channels
+        assert_eq!(
+            StreamList::Followed("1234").query(&languages, Some("abc")),
+            (
+                "streams/followed",
+                vec![("user_id", "1234"), ("first", "100"), ("after", "abc")]
+            )
+        );
+    }
+
+    #[test]
+    fn rejected_tokens_are_renewed_once() {
+        let unauthorized = || {
+            let resp = ureq::Response::new(401, "Unauthorized", "").unwrap();
+            AppError::FetchStreams(Box::new(ureq::Error::Status(401, resp)))
+        };
+        let client = TwitchClient::new(configure_agent(), "old".to_string());
+
+        // A token handed to us can't be renewed
+        let mut tokens = Vec::new();
+        let result = client.renewing(|token| -> Result<(), _> {
+            tokens.push(token.to_string());
+            Err(unauthorized())
+        });
+        assert!(result.unwrap_err().is_unauthorized());
+        assert_eq!(tokens, ["old"]);
+
+        // Unless another request already renewed it
+        let mut tokens = Vec::new();
+        let result = client.renewing(|token| {
+            tokens.push(token.to_string());
+            match token {
+                "old" => {
+                    *client.access_token.write().unwrap() = "new".to_string();
+                    Err(unauthorized())
+                }
+                _ => Ok(()),
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(tokens, ["old", "new"]);
+
+        // Other errors are not retried
+        let mut calls = 0;
+        let result = client.renewing(|_| -> Result<(), _> {
+            calls += 1;
+            Err(AppError::RunawayPagination("cursor".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn rate_limit_waits() {
+        let resp = "HTTP/1.1 200 OK\r\nRatelimit-Limit: 800\r\nRatelimit-Remaining: 2\r\n\
+                    Ratelimit-Reset: 1792152010\r\n\r\n"
+            .parse::<ureq::Response>()
+            .unwrap();
+        let limit = RateLimit::from_response(&resp).unwrap();
+        assert_eq!((limit.limit, limit.remaining), (800, 2));
+
+        let reset = Utc.timestamp_opt(1792152010, 0).unwrap();
+        let now = reset - chrono::Duration::seconds(7);
+        assert_eq!(limit.wait(now), Some(Duration::from_secs(7)));
+        assert_eq!(limit.wait(reset + chrono::Duration::seconds(1)), None);
+        let plenty = RateLimit {
+            remaining: RATE_LIMIT_RESERVE + 1,
+            ..limit
+        };
+        assert_eq!(plenty.wait(now), None);
+
+        let resp = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 3\r\n\r\n"
+            .parse::<ureq::Response>()
+            .unwrap();
+        assert_eq!(retry_after(&resp), Some(Duration::from_secs(3)));
+        let resp = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 86400\r\n\r\n"
+            .parse::<ureq::Response>()
+            .unwrap();
+        assert_eq!(retry_after(&resp), Some(MAX_BACKOFF));
+        let resp = "HTTP/1.1 429 Too Many Requests\r\n\r\n"
+            .parse::<ureq::Response>()
+            .unwrap();
+        assert_eq!(retry_after(&resp), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_a_cap() {
+        let delay = Duration::from_millis(500);
+        assert_eq!(retry_backoff(delay, 0), delay);
+        assert_eq!(retry_backoff(delay, 3), Duration::from_secs(4));
+        assert_eq!(retry_backoff(delay, 29), MAX_BACKOFF);
+        assert_eq!(retry_backoff(Duration::from_secs(u64::MAX), 1), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn requests_share_connections() {
+        use std::io::Read;
+        use std::net::TcpListener;
+        use std::sync::Arc;
+
+        // Answers every request on a connection, counting the connections
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().map_while(Result::ok) {
+                counter.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let mut buf = [0; 1024];
+                    while stream.read(&mut buf).is_ok_and(|n| n > 0) {
+                        let resp = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+                        if stream.write_all(resp.as_bytes()).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        if env::var_os("TWITCH_CLIENT_ID").is_none() {
+            env::set_var("TWITCH_CLIENT_ID", "test");
+        }
+        let client =
+            TwitchClient::new(ureq::AgentBuilder::new().build(), String::new()).with_helix_url(url);
+        for _ in 0..3 {
+            assert_eq!(client.get("users", &[]).unwrap(), serde_json::json!({}));
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn games_scanned_at_the_same_time() {
+        let path =
+            env::temp_dir().join(format!("twitch-search-games-{}.jsonl", std::process::id()));
+        let raw = RawDump::create(&path).unwrap();
+        let games = (0..8).map(|i| format!("game{i}")).collect::<Vec<_>>();
+        let scanned = concurrently(&games, 4, |game| {
+            let page = serde_json::json!({
+                "data": [test_stream(game, 10)],
+                "pagination": {},
+            });
+            raw.write(&page)?;
+            Ok(game.clone())
+        })
+        .unwrap();
+        drop(raw);
+        assert_eq!(scanned, games);
+
+        // Pages written at the same time still end up on lines of their own
+        let loaded = load_streams(&path);
+        fs::remove_file(&path).unwrap();
+        let (loaded, stats) = loaded.unwrap();
+        assert_eq!((loaded.len(), stats.pages), (8, 8));
+
+        // No concurrency still scans them one at a time
+        assert_eq!(concurrently(&games, 0, |_| Ok(())).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn progress_adds_up_the_scans() {
+        let progress = ScanProgress::new(Progress::None);
+        let options = FetchOptions {
+            filter: Some(test_filter(&["rust"])),
+            ..fetch_options(100, false)
+        };
+        let games = ["a", "b", "c"];
+        concurrently(&games, 3, |game| {
+            let mut cursors = ["1", "2"].into_iter().map(Some).chain([None]);
+            scan_streams(&options, None, &progress, |_| {
+                Ok(serde_json::json!({
+                    "data": [test_stream(game, 10), test_stream(&game.repeat(2), 10)],
+                    "pagination": { "cursor": cursors.next().unwrap() },
+                }))
+            })
+        })
+        .unwrap();
+        assert_eq!(progress.pages.load(Ordering::Relaxed), 9);
+        // The same two streams on every page match only once
+        assert_eq!(progress.matched.load(Ordering::Relaxed), 6);
+    }
+
+    fn info(followers: Option<i64>) -> UserInfo {
+        UserInfo {
+            broadcaster_type: "affiliate".to_string(),
+            description: "Writing Rust".to_string(),
+            created_at: "2020-01-02T03:04:05Z".parse().ok(),
+            content_labels: vec!["ProfanityVulgarity".to_string()],
+            followers,
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2026-10-16T12:00:00Z".parse().unwrap()
+    }
+
+    /// Fetches pages whose cursors are `cursors`, one per page, returning
+    /// how many were fetched
+    fn paginate_cursors(max_pages: usize, cursors: &[&str]) -> Result<usize, AppError> {
+        let mut fetched = 0;
+        let left = paginate("test", max_pages, |_| {
+            fetched += 1;
+            Ok(cursors.get(fetched - 1).map(|c| c.to_string()))
+        })?;
+        assert_eq!(left, fetched <= cursors.len());
+        Ok(fetched)
+    }
+
+    #[test]
+    fn paginate_until_the_last_page() {
+        assert_eq!(paginate_cursors(10, &["a", "b"]).unwrap(), 3);
+        assert_eq!(paginate_cursors(2, &["a", "b", "c"]).unwrap(), 2);
+        assert_eq!(paginate_cursors(1, &["a"]).unwrap(), 1);
+        assert_eq!(paginate_cursors(0, &["a"]).unwrap(), 1);
+    }
+
+    #[test]
+    fn paginate_stops_at_a_repeated_cursor() {
+        let error = paginate_cursors(10, &["a", "b", "a", "c"]).unwrap_err();
+        assert!(matches!(error, AppError::RunawayPagination(_)));
+        assert!(error
+            .to_string()
+            .contains(r#""a" twice after 3 pages of test"#));
+    }
+
+    #[test]
+    fn user_cache_round_trip() {
+        let mut cache = UserCache::default();
+        cache.insert("1", info(Some(42)), true, now());
+        cache.insert("2", info(None), false, now());
+
+        let cache = UserCache::from_json(&cache.to_json(now()));
+        let user = cache.get("1", true, now()).unwrap();
+        assert_eq!(user.followers, Some(42));
+        assert_eq!(user.content_labels, ["ProfanityVulgarity"]);
+        assert_eq!(user.created_at, info(None).created_at);
+        assert!(cache
+            .get("2", false, now())
+            .unwrap()
+            .content_labels
+            .is_empty());
+        assert!(!cache.changed);
+    }
+
+    #[test]
+    fn user_cache_expires() {
+        let mut cache = UserCache::default();
+        cache.insert("1", info(None), false, now() - chrono::Duration::hours(25));
+        cache.insert("2", info(None), false, now() - chrono::Duration::hours(1));
+
+        assert!(cache.get("1", false, now()).is_none());
+        assert!(cache.get("2", false, now()).is_some());
+        let stored = cache.to_json(now());
+        assert!(stored.get("1").is_none());
+        assert!(stored.get("2").is_some());
+    }
+
+    #[test]
+    fn user_cache_without_channels() {
+        let mut cache = UserCache::default();
+        cache.insert("1", info(None), false, now());
+        assert!(cache.get("1", true, now()).is_none());
+
+        cache.set_followers("1", Some(7));
+        assert_eq!(cache.get("1", false, now()).unwrap().followers, Some(7));
+    }
+}
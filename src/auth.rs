@@ -0,0 +1,596 @@
+use std::env;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::AccessTokenError;
+
+/// Response of the client credentials flow
+#[derive(Debug, Deserialize)]
+pub(crate) struct TokenResponse {
+    pub(crate) access_token: String,
+    pub(crate) expires_in: Option<i64>,
+}
+
+/// Response of `oauth2/validate`
+#[derive(Debug, Deserialize)]
+struct Validation {
+    expires_in: i64,
+    /// Only set for user access tokens
+    user_id: Option<String>,
+    login: Option<String>,
+}
+
+/// Requests a new app access token with the client credentials flow
+fn request_access_token(
+    agent: &ureq::Agent,
+    client_id: &str,
+) -> Result<CachedToken, AccessTokenError> {
+    let client_secret = client_secret()?;
+
+    let resp = agent
+        .post("https://id.twitch.tv/oauth2/token")
+        .send_form(&[
+            ("client_id", client_id),
+            ("client_secret", &client_secret),
+            ("grant_type", "client_credentials"),
+        ])?;
+
+    let token = serde_json::from_value::<TokenResponse>(resp.into_json()?)?;
+
+    Ok(CachedToken {
+        client_id: client_id.to_string(),
+        access_token: token.access_token,
+        expires_at: token
+            .expires_in
+            .map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+    })
+}
+
+fn validate(agent: &ureq::Agent, token: &str) -> Result<Validation, AccessTokenError> {
+    let resp = agent
+        .get("https://id.twitch.tv/oauth2/validate")
+        .set("Authorization", &format!("OAuth {token}"))
+        .call()?;
+
+    Ok(serde_json::from_value::<Validation>(resp.into_json()?)?)
+}
+
+/// Asks Twitch whether `token` is still valid. Returns the seconds it has left.
+pub fn validate_access_token(agent: &ureq::Agent, token: &str) -> Result<i64, AccessTokenError> {
+    Ok(validate(agent, token)?.expires_in)
+}
+
+/// Returns an app access token, reusing the cached one while Twitch still
+/// accepts it unless `use_cache` is off.
+pub fn aquire_access_token(
+    agent: &ureq::Agent,
+    use_cache: bool,
+) -> Result<String, AccessTokenError> {
+    let client_id = client_id()?;
+
+    if use_cache {
+        if let Some(cached) = CachedToken::load() {
+            if cached.reusable(&client_id, Utc::now())
+                && validate_access_token(agent, &cached.access_token).is_ok()
+            {
+                return Ok(cached.access_token);
+            }
+        }
+    }
+
+    renew_access_token(agent, use_cache)
+}
+
+/// Requests a new app access token even if a cached one exists, for when
+/// Helix stops accepting it. The cache is updated unless `use_cache` is off.
+pub fn renew_access_token(
+    agent: &ureq::Agent,
+    use_cache: bool,
+) -> Result<String, AccessTokenError> {
+    let token = request_access_token(agent, &client_id()?)?;
+
+    if use_cache {
+        if let Err(e) = token.store() {
+            eprintln!("Warning: failed to cache the access token: {e}");
+        }
+    }
+
+    Ok(token.access_token)
+}
+
+// -----------------------------------------------------------------------------
+//     - User token -
+// -----------------------------------------------------------------------------
+
+/// Scopes asked for by `login`. Tokens from before one was added need a new
+/// `login` for the commands using it.
+const USER_SCOPES: &str = "user:read:follows user:read:blocked_users \
+    user:manage:blocked_users clips:edit bits:read channel:read:subscriptions \
+    analytics:read:games";
+
+const TOKEN_URL: &str = "https://id.twitch.tv/oauth2/token";
+
+/// Response of `oauth2/device`
+#[derive(Debug, Deserialize)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+/// Response of the device code and refresh token grants
+#[derive(Debug, Deserialize)]
+struct UserTokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+}
+
+/// A user access token, for endpoints that act on behalf of a user. Taken
+/// from `TWITCH_USER_TOKEN` if set (it must be issued to the same client id),
+/// otherwise the one stored by [`login`], refreshed when it has expired.
+pub fn user_access_token(agent: &ureq::Agent) -> Result<String, AccessTokenError> {
+    if user_token_from_env() {
+        return Ok(env::var("TWITCH_USER_TOKEN").unwrap_or_default());
+    }
+
+    let client_id = client_id()?;
+    let token = UserToken::load()
+        .filter(|token| token.client_id == client_id)
+        .ok_or(AccessTokenError::MissingUserToken)?;
+    if token.fresh(Utc::now()) {
+        return Ok(token.access_token);
+    }
+
+    refresh_user_token(agent)
+}
+
+/// Whether the user token comes from `TWITCH_USER_TOKEN`, which can't be refreshed
+pub fn user_token_from_env() -> bool {
+    env::var("TWITCH_USER_TOKEN").is_ok_and(|token| !token.trim().is_empty())
+}
+
+/// Replaces the token stored by [`login`] with a refreshed one, also for when
+/// Helix rejects it before it was due to expire
+pub fn refresh_user_token(agent: &ureq::Agent) -> Result<String, AccessTokenError> {
+    let token = UserToken::load()
+        .ok_or(AccessTokenError::MissingUserToken)?
+        .refresh(agent)?;
+    token.store().map_err(AccessTokenError::StoreUserToken)?;
+    Ok(token.access_token)
+}
+
+/// Signs a user in with the device code flow: prints a URL and a code to
+/// enter there, then waits for the user to do so. The token is stored for
+/// later runs. Returns the login name of the user.
+pub fn login(agent: &ureq::Agent) -> Result<String, AccessTokenError> {
+    let client_id = client_id()?;
+
+    let resp = agent
+        .post("https://id.twitch.tv/oauth2/device")
+        .send_form(&[("client_id", &client_id), ("scopes", USER_SCOPES)])?;
+    let device = serde_json::from_value::<DeviceCode>(resp.into_json()?)?;
+
+    println!(
+        "Open {} and enter the code {}",
+        device.verification_uri, device.user_code
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(device.expires_in);
+    let mut interval = Duration::from_secs(device.interval.max(1));
+    loop {
+        thread::sleep(interval);
+        if Instant::now() > deadline {
+            return Err(AccessTokenError::Login("the code expired".to_string()));
+        }
+
+        let result = agent.post(TOKEN_URL).send_form(&[
+            ("client_id", &client_id),
+            ("scopes", USER_SCOPES),
+            ("device_code", &device.device_code),
+            ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+        ]);
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(ureq::Error::Status(400, resp)) => {
+                let message = resp
+                    .into_json::<Value>()
+                    .ok()
+                    .and_then(|json| Some(json.get("message")?.as_str()?.to_string()))
+                    .unwrap_or_default();
+                match message.as_str() {
+                    "authorization_pending" => continue,
+                    "slow_down" => {
+                        interval += Duration::from_secs(5);
+                        continue;
+                    }
+                    _ => return Err(AccessTokenError::Login(message)),
+                }
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let response = serde_json::from_value::<UserTokenResponse>(resp.into_json()?)?;
+        let token = UserToken::new(client_id, response);
+        token.store().map_err(AccessTokenError::StoreUserToken)?;
+
+        return Ok(validate(agent, &token.access_token)?
+            .login
+            .unwrap_or_default());
+    }
+}
+
+/// Revokes the token stored by [`login`] and forgets it. Returns whether
+/// there was one.
+pub fn logout(agent: &ureq::Agent) -> Result<bool, AccessTokenError> {
+    let Some(token) = UserToken::load() else {
+        return Ok(false);
+    };
+
+    // A token Twitch no longer knows can't be revoked, but is forgotten all the same
+    let _ = agent
+        .post("https://id.twitch.tv/oauth2/revoke")
+        .send_form(&[
+            ("client_id", &token.client_id),
+            ("token", &token.access_token),
+        ]);
+
+    UserToken::remove().map_err(AccessTokenError::StoreUserToken)?;
+    Ok(true)
+}
+
+/// The id of the user a user access token was issued for
+pub fn token_user_id(agent: &ureq::Agent, token: &str) -> Result<String, AccessTokenError> {
+    validate(agent, token)?
+        .user_id
+        .ok_or(AccessTokenError::NotUserToken)
+}
+
+// -----------------------------------------------------------------------------
+//     - Credentials -
+// -----------------------------------------------------------------------------
+
+/// Client credentials from a file, for whatever the environment doesn't set
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Credentials {
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+/// The credentials file and what it holds
+static CREDENTIALS: OnceLock<(PathBuf, Credentials)> = OnceLock::new();
+
+/// Reads `client_id` and `client_secret` from a TOML file, to be used when
+/// `TWITCH_CLIENT_ID` or `TWITCH_CLIENT_SECRET` isn't set. Only the first call
+/// has any effect.
+pub fn load_credentials(path: &Path) -> Result<(), AccessTokenError> {
+    let error = |e: String| AccessTokenError::ReadCredentials(path.display().to_string(), e);
+    let text = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    let credentials = toml::from_str(&text).map_err(|e| error(e.to_string()))?;
+    let _ = CREDENTIALS.set((path.to_path_buf(), credentials));
+    Ok(())
+}
+
+/// Where a client credential was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CredentialSource {
+    /// This environment variable
+    Env(&'static str),
+    /// The credentials file at this path
+    File(PathBuf),
+}
+
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialSource::Env(name) => write!(f, "{name}"),
+            CredentialSource::File(path) => write!(f, "credentials file {}", path.display()),
+        }
+    }
+}
+
+/// Looks up a credential in the environment variable `var`, or else in the
+/// credentials file
+fn credential(
+    var: &'static str,
+    field: fn(&Credentials) -> &Option<String>,
+) -> Option<(String, CredentialSource)> {
+    if let Ok(value) = env::var(var) {
+        return Some((value, CredentialSource::Env(var)));
+    }
+    let (path, credentials) = CREDENTIALS.get()?;
+    let value = field(credentials).clone()?;
+    Some((value, CredentialSource::File(path.clone())))
+}
+
+/// The app's client id, from `TWITCH_CLIENT_ID` or the credentials file
+pub(crate) fn client_id() -> Result<String, AccessTokenError> {
+    client_id_source()
+        .map(|(id, _)| id)
+        .ok_or(AccessTokenError::MissingClientId)
+}
+
+pub(crate) fn client_secret() -> Result<String, AccessTokenError> {
+    client_secret_source()
+        .map(|(secret, _)| secret)
+        .ok_or(AccessTokenError::MissingClientSecret)
+}
+
+/// Like [`client_id`], also telling where it came from
+pub(crate) fn client_id_source() -> Option<(String, CredentialSource)> {
+    credential("TWITCH_CLIENT_ID", |c| &c.client_id)
+}
+
+/// Like [`client_secret`], also telling where it came from
+pub(crate) fn client_secret_source() -> Option<(String, CredentialSource)> {
+    credential("TWITCH_CLIENT_SECRET", |c| &c.client_secret)
+}
+
+// -----------------------------------------------------------------------------
+//     - Token cache -
+// -----------------------------------------------------------------------------
+
+/// Cached tokens this close to expiring are replaced rather than reused
+const TOKEN_EXPIRY_MARGIN: chrono::Duration = chrono::Duration::minutes(5);
+
+#[derive(Debug)]
+struct CachedToken {
+    /// The client the token was issued to, so switching apps isn't confused
+    client_id: String,
+    access_token: String,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// Writes `json` to `path`, readable by the current user only
+fn write_private(path: &Path, json: &Value) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    writeln!(options.open(path)?, "{json}")
+}
+
+/// Where cached tokens and user metadata are kept
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    let cache_home = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(cache_home.join("twitch-search"))
+}
+
+/// Where the app access token is cached
+pub(crate) fn token_cache_path() -> Option<PathBuf> {
+    CachedToken::path()
+}
+
+/// When the cached app access token expires, if there is one that says
+pub(crate) fn cached_token_expiry() -> Option<DateTime<Utc>> {
+    CachedToken::load()?.expires_at
+}
+
+impl CachedToken {
+    fn path() -> Option<PathBuf> {
+        cache_dir().map(|dir| dir.join("token.json"))
+    }
+
+    /// Reads the cached token. Any problem just means there is no usable cache.
+    fn load() -> Option<Self> {
+        let json = serde_json::from_str::<Value>(&fs::read_to_string(Self::path()?).ok()?).ok()?;
+        Self::from_json(&json)
+    }
+
+    fn from_json(json: &Value) -> Option<Self> {
+        Some(CachedToken {
+            client_id: json.get("client_id")?.as_str()?.to_string(),
+            access_token: json.get("access_token")?.as_str()?.to_string(),
+            expires_at: json
+                .get("expires_at")
+                .and_then(Value::as_str)
+                .and_then(|t| t.parse().ok()),
+        })
+    }
+
+    fn store(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        write_private(&path, &self.to_json())
+    }
+
+    fn to_json(&self) -> Value {
+        serde_json::json!({
+            "client_id": self.client_id,
+            "access_token": self.access_token,
+            "expires_at": self.expires_at.map(|t| t.to_rfc3339()),
+        })
+    }
+
+    /// Whether the token was issued to `client_id` and isn't about to expire
+    /// at `now`. Twitch may still have revoked it.
+    fn reusable(&self, client_id: &str, now: DateTime<Utc>) -> bool {
+        let fresh = self
+            .expires_at
+            .is_none_or(|expires_at| expires_at > now + TOKEN_EXPIRY_MARGIN);
+        self.client_id == client_id && fresh
+    }
+}
+
+/// A user token from [`login`]. Unlike the app token it can't simply be
+/// requested again, so it lives with the user's data rather than in the cache.
+#[derive(Debug)]
+struct UserToken {
+    client_id: String,
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl UserToken {
+    fn new(client_id: String, response: UserTokenResponse) -> Self {
+        UserToken {
+            client_id,
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: response
+                .expires_in
+                .map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+        }
+    }
+
+    fn path() -> Option<PathBuf> {
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+
+        Some(data_home.join("twitch-search").join("user-token.json"))
+    }
+
+    /// Reads the stored token. Any problem means there is none.
+    fn load() -> Option<Self> {
+        let json = serde_json::from_str::<Value>(&fs::read_to_string(Self::path()?).ok()?).ok()?;
+        Self::from_json(&json)
+    }
+
+    fn from_json(json: &Value) -> Option<Self> {
+        Some(UserToken {
+            client_id: json.get("client_id")?.as_str()?.to_string(),
+            access_token: json.get("access_token")?.as_str()?.to_string(),
+            refresh_token: json
+                .get("refresh_token")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            expires_at: json
+                .get("expires_at")
+                .and_then(Value::as_str)
+                .and_then(|t| t.parse().ok()),
+        })
+    }
+
+    fn store(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        write_private(&path, &self.to_json())
+    }
+
+    fn to_json(&self) -> Value {
+        serde_json::json!({
+            "client_id": self.client_id,
+            "access_token": self.access_token,
+            "refresh_token": self.refresh_token,
+            "expires_at": self.expires_at.map(|t| t.to_rfc3339()),
+        })
+    }
+
+    /// Whether the token isn't about to expire at `now`, so needs no refresh
+    fn fresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| expires_at > now + TOKEN_EXPIRY_MARGIN)
+    }
+
+    fn remove() -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Trades the refresh token for a new token. Public clients (as used by
+    /// the device code flow) need no client secret, so one is only sent if set.
+    fn refresh(&self, agent: &ureq::Agent) -> Result<Self, AccessTokenError> {
+        let refresh_token = self
+            .refresh_token
+            .as_deref()
+            .ok_or(AccessTokenError::MissingUserToken)?;
+
+        let mut form = vec![
+            ("client_id", self.client_id.clone()),
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.to_string()),
+        ];
+        if let Ok(client_secret) = client_secret() {
+            form.push(("client_secret", client_secret));
+        }
+        let form = form
+            .iter()
+            .map(|(k, v)| (*k, v.as_str()))
+            .collect::<Vec<_>>();
+
+        let resp = agent.post(TOKEN_URL).send_form(&form)?;
+        let response = serde_json::from_value::<UserTokenResponse>(resp.into_json()?)?;
+        Ok(UserToken::new(self.client_id.clone(), response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_tokens() {
+        let now = "2026-10-16T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let token = CachedToken {
+            client_id: "app".to_string(),
+            access_token: "secret".to_string(),
+            expires_at: Some(now + chrono::Duration::hours(1)),
+        };
+        let cached = CachedToken::from_json(&token.to_json()).unwrap();
+        assert_eq!(cached.access_token, "secret");
+        assert_eq!(cached.expires_at, token.expires_at);
+
+        assert!(cached.reusable("app", now));
+        assert!(!cached.reusable("other app", now));
+        assert!(!cached.reusable("app", now + chrono::Duration::minutes(56)));
+        assert!(CachedToken::from_json(&serde_json::json!({ "client_id": "app" })).is_none());
+    }
+
+    #[test]
+    fn user_tokens() {
+        let response = serde_json::from_value::<UserTokenResponse>(serde_json::json!({
+            "access_token": "secret",
+            "refresh_token": "refresh",
+            "expires_in": 14400,
+            "scope": ["user:read:follows"],
+            "token_type": "bearer",
+        }))
+        .unwrap();
+        let token = UserToken::new("app".to_string(), response);
+        let stored = UserToken::from_json(&token.to_json()).unwrap();
+        assert_eq!(stored.client_id, "app");
+        assert_eq!(stored.refresh_token.as_deref(), Some("refresh"));
+        assert_eq!(stored.expires_at, token.expires_at);
+
+        let now = Utc::now();
+        assert!(stored.fresh(now));
+        assert!(!stored.fresh(now + chrono::Duration::hours(4)));
+
+        // Tokens that never expire are always fresh, but can't be refreshed
+        let json = serde_json::json!({ "client_id": "app", "access_token": "secret" });
+        let stored = UserToken::from_json(&json).unwrap();
+        assert!(stored.fresh(now + chrono::Duration::weeks(52)));
+        assert!(stored.refresh_token.is_none());
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::api::UserCache;
+use crate::config::Config;
+use crate::AppError;
+
+use super::favorites::favorites_path;
+use super::history::history_path;
+use super::ignore::ignored_path;
+use super::runs::runs_path;
+use super::webhook::announced_path;
+
+/// Version of the archive format, refused by older versions when it grows
+const VERSION: u32 = 1;
+
+/// Options of the `backup` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct BackupArgs {
+    /// Archive to write
+    file: PathBuf,
+
+    /// Back up this config file instead of ~/.config/twitch-search/config.toml
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+}
+
+/// Options of the `restore` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct RestoreArgs {
+    /// Archive written by `backup`
+    file: PathBuf,
+
+    /// Restore the config file here instead of ~/.config/twitch-search/config.toml
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Replace the files that exist already
+    #[clap(long)]
+    force: bool,
+}
+
+/// The files kept in an archive, by their name in it. Tokens are left out:
+/// they are secrets, and `login` or a new app token replace them anyway.
+fn files(config: Option<PathBuf>) -> [(&'static str, Option<PathBuf>); 7] {
+    [
+        ("config.toml", config.or_else(Config::default_path)),
+        ("ignored.json", ignored_path()),
+        ("favorites.json", favorites_path()),
+        ("history.jsonl", history_path()),
+        ("runs.jsonl", runs_path()),
+        ("announced.json", announced_path()),
+        ("users.json", UserCache::path()),
+    ]
+}
+
+/// A backup: the contents of the files by their name
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Archive {
+    version: u32,
+    /// When it was written, in RFC 3339
+    created: String,
+    files: BTreeMap<String, String>,
+}
+
+fn error(path: &Path, message: impl ToString) -> AppError {
+    AppError::Backup(path.display().to_string(), message.to_string())
+}
+
+/// Runs the `backup` subcommand
+pub(super) fn backup(args: BackupArgs) -> Result<(), AppError> {
+    let mut archive = Archive {
+        version: VERSION,
+        created: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        files: BTreeMap::new(),
+    };
+    for (name, path) in files(args.config) {
+        let Some(path) = path else { continue };
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                println!("{name}: {}", path.display());
+                archive.files.insert(name.to_string(), text);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(error(&path, e)),
+        }
+    }
+    let json = serde_json::to_string_pretty(&archive).unwrap_or_default();
+    fs::write(&args.file, json + "\n").map_err(|e| error(&args.file, e))?;
+    println!(
+        "{} file(s) backed up to {}.",
+        archive.files.len(),
+        args.file.display()
+    );
+    Ok(())
+}
+
+/// Runs the `restore` subcommand. Writes nothing when a file would be
+/// replaced without `--force`.
+pub(super) fn restore(args: RestoreArgs) -> Result<(), AppError> {
+    let text = fs::read_to_string(&args.file).map_err(|e| error(&args.file, e))?;
+    let mut archive = serde_json::from_str::<Archive>(&text).map_err(|e| error(&args.file, e))?;
+    if archive.version > VERSION {
+        return Err(error(
+            &args.file,
+            format!("written by a newer version (format {})", archive.version),
+        ));
+    }
+
+    let mut restores = Vec::new();
+    for (name, path) in files(args.config) {
+        let Some(text) = archive.files.remove(name) else {
+            continue;
+        };
+        let path = path.ok_or_else(|| error(&args.file, format!("nowhere to restore {name}")))?;
+        if !args.force && path.exists() {
+            return Err(error(
+                &path,
+                "exists already, restore with --force to replace it",
+            ));
+        }
+        restores.push((name, path, text));
+    }
+    if let Some(name) = archive.files.keys().next() {
+        return Err(error(&args.file, format!("unknown file {name}")));
+    }
+
+    for (name, path, text) in &restores {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| error(path, e))?;
+        }
+        fs::write(path, text).map_err(|e| error(path, e))?;
+        println!("{name}: {}", path.display());
+    }
+    println!(
+        "{} file(s) restored from {}, backed up {}.",
+        restores.len(),
+        args.file.display(),
+        archive.created
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_archives() {
+        let json = r#"{
+            "version": 1,
+            "created": "2026-10-16T12:00:00Z",
+            "files": {"favorites.json": "{\"channels\": [\"crab\"]}\n"}
+        }"#;
+        let archive = serde_json::from_str::<Archive>(json).unwrap();
+        assert_eq!(archive.files.len(), 1);
+        assert!(serde_json::from_str::<Archive>(r#"{"version": 1}"#).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use clap::ValueEnum;
+use serde_json::json;
+
+use crate::auth::token_user_id;
+use crate::config::Config;
+use crate::helix::{BitsLeader, GameAnalytics};
+use crate::table::{Align, Table};
+use crate::{AppError, TwitchClient};
+
+use super::clips::parse_time;
+use super::{load_config, print_json, print_table, CommonArgs, OutputFormat};
+
+/// Options of the `bits` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct BitsArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    /// Rank the cheers of this period
+    #[clap(long, value_enum, default_value = "all")]
+    period: BitsPeriod,
+
+    /// A time within the period, by default now (RFC 3339)
+    #[clap(long, value_name = "TIME", value_parser = parse_time)]
+    started_at: Option<DateTime<Utc>>,
+
+    /// Show at most this many cheerers
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u32).range(1..=100)
+    )]
+    count: u32,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum BitsPeriod {
+    Day,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+/// Prints the top cheerers of the signed-in user's channel
+pub(super) fn bits(mut args: BitsArgs) -> Result<bool, AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+
+    let client = TwitchClient::for_user()?;
+    let period = args
+        .period
+        .to_possible_value()
+        .map_or("all", |v| v.get_name());
+    let started_at = args
+        .started_at
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true));
+    let leaderboard = client.bits_leaderboard(args.count, period, started_at.as_deref())?;
+
+    let found = !leaderboard.data.is_empty();
+    let output = args.common.output();
+    if output == OutputFormat::Json {
+        let json = leaderboard
+            .data
+            .iter()
+            .map(|leader| {
+                json!({
+                    "rank": leader.rank,
+                    "user_id": leader.user_id,
+                    "user_login": leader.user_login,
+                    "user_name": leader.user_name,
+                    "bits": leader.score,
+                })
+            })
+            .collect();
+        print_json(json, &args.common)?;
+        return Ok(found);
+    }
+
+    let range = &leaderboard.date_range;
+    if output == OutputFormat::Table && !range.started_at.is_empty() {
+        eprintln!("From {} to {}", range.started_at, range.ended_at);
+    }
+    print_table(&leaders_table(leaderboard.data), &args.common)?;
+
+    Ok(found)
+}
+
+fn leaders_table(leaders: Vec<BitsLeader>) -> Table {
+    let mut table = Table::new(3);
+    table.set_headers(["rank", "bits", "name"].map(str::to_string).to_vec());
+    table.set_align(0, Align::Right);
+    table.set_align(1, Align::Right);
+    for leader in leaders {
+        table.push(vec![
+            leader.rank.to_string(),
+            leader.score.to_string(),
+            leader.user_name,
+        ]);
+    }
+    table
+}
+
+/// Options of the `subs` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct SubsArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    /// List every subscriber instead of the totals
+    #[clap(long)]
+    list: bool,
+}
+
+/// Prints the subscriber count and points of the signed-in user's channel,
+/// or its subscribers
+pub(super) fn subs(mut args: SubsArgs) -> Result<bool, AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+
+    let client = TwitchClient::for_user()?;
+    let user_id = token_user_id(client.agent(), &client.access_token())?;
+    let subscribers = client.subscribers(&user_id, args.list)?;
+    let output = args.common.output();
+
+    if !args.list {
+        if output == OutputFormat::Json {
+            let json = json!({ "total": subscribers.total, "points": subscribers.points });
+            print_json(vec![json], &args.common)?;
+            return Ok(true);
+        }
+        let mut table = Table::new(2);
+        table.set_headers(["subscribers", "points"].map(str::to_string).to_vec());
+        table.set_align(0, Align::Right);
+        table.push(vec![
+            subscribers.total.to_string(),
+            subscribers.points.to_string(),
+        ]);
+        print_table(&table, &args.common)?;
+        return Ok(true);
+    }
+
+    let found = !subscribers.data.is_empty();
+    if output == OutputFormat::Json {
+        let json = subscribers
+            .data
+            .iter()
+            .map(|sub| {
+                json!({
+                    "user_id": sub.user_id,
+                    "user_login": sub.user_login,
+                    "user_name": sub.user_name,
+                    "tier": tier(&sub.tier),
+                    "gifter_login": (!sub.gifter_login.is_empty()).then_some(&sub.gifter_login),
+                })
+            })
+            .collect();
+        print_json(json, &args.common)?;
+        return Ok(found);
+    }
+
+    if output == OutputFormat::Table {
+        eprintln!(
+            "{} subscribers, {} points",
+            subscribers.total, subscribers.points
+        );
+    }
+    let mut table = Table::new(3);
+    table.set_headers(["tier", "name", "gifted by"].map(str::to_string).to_vec());
+    table.set_align(0, Align::Right);
+    for sub in subscribers.data {
+        table.push(vec![
+            tier(&sub.tier).to_string(),
+            sub.user_name,
+            sub.gifter_login,
+        ]);
+    }
+    print_table(&table, &args.common)?;
+
+    Ok(found)
+}
+
+/// `1`, `2` or `3` for the tiers Helix calls `1000`, `2000` and `3000`
+fn tier(tier: &str) -> &str {
+    tier.strip_suffix("000").unwrap_or(tier)
+}
+
+/// Options of the `analytics` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct AnalyticsArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    /// Only the reports of this game, by name or id
+    #[clap(short, long)]
+    game: Option<String>,
+
+    /// Reports from this day on (RFC 3339), with --until
+    #[clap(long, value_name = "TIME", value_parser = parse_time, requires = "until")]
+    since: Option<DateTime<Utc>>,
+
+    /// Reports up to this day (RFC 3339), with --since
+    #[clap(long, value_name = "TIME", value_parser = parse_time, requires = "since")]
+    until: Option<DateTime<Utc>>,
+
+    /// Also download the CSV reports into this directory
+    #[clap(long, value_name = "DIR")]
+    download: Option<PathBuf>,
+}
+
+/// Prints where to download the analytics reports of the signed-in user's
+/// games, and downloads them with `--download`
+pub(super) fn analytics(mut args: AnalyticsArgs) -> Result<bool, AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+
+    let client = TwitchClient::for_user()?;
+    let game_id = args
+        .game
+        .as_deref()
+        .map(|game| client.resolve_game(game))
+        .transpose()?;
+    let time = |time: DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let range = args.since.zip(args.until).map(|(s, u)| (time(s), time(u)));
+    let range = range.as_ref().map(|(s, u)| (s.as_str(), u.as_str()));
+    let reports = client.game_analytics(game_id.as_deref(), range)?;
+
+    let mut names = HashMap::new();
+    for report in &reports {
+        if !names.contains_key(&report.game_id) {
+            let game = client.game_by_id(&report.game_id)?;
+            let name = game.map_or_else(|| report.game_id.clone(), |game| game.name);
+            names.insert(report.game_id.clone(), name);
+        }
+    }
+
+    if let Some(dir) = &args.download {
+        fs::create_dir_all(dir)
+            .map_err(|e| AppError::DownloadReport(dir.display().to_string(), e.to_string()))?;
+        for report in &reports {
+            let path = dir.join(report_file(report));
+            download(client.agent(), &report.url, &path)?;
+            eprintln!("Downloaded {}", path.display());
+        }
+    }
+
+    let found = !reports.is_empty();
+    let output = args.common.output();
+    if output == OutputFormat::Json {
+        let json = reports
+            .iter()
+            .map(|report| {
+                json!({
+                    "game_id": report.game_id,
+                    "game_name": names[&report.game_id],
+                    "started_at": report.date_range.started_at,
+                    "ended_at": report.date_range.ended_at,
+                    "url": report.url,
+                })
+            })
+            .collect();
+        print_json(json, &args.common)?;
+        return Ok(found);
+    }
+
+    let mut table = Table::new(4);
+    table.set_headers(["game", "from", "to", "url"].map(str::to_string).to_vec());
+    for report in reports {
+        table.push(vec![
+            names[&report.game_id].clone(),
+            day(&report.date_range.started_at).to_string(),
+            day(&report.date_range.ended_at).to_string(),
+            report.url,
+        ]);
+    }
+    print_table(&table, &args.common)?;
+
+    Ok(found)
+}
+
+/// The date of an RFC 3339 time
+fn day(time: &str) -> &str {
+    time.get(..10).unwrap_or(time)
+}
+
+/// The name a downloaded report is saved under: its game and days
+fn report_file(report: &GameAnalytics) -> String {
+    let range = &report.date_range;
+    format!(
+        "{}-{}-{}.csv",
+        report.game_id,
+        day(&range.started_at),
+        day(&range.ended_at)
+    )
+}
+
+fn download(agent: &ureq::Agent, url: &str, path: &Path) -> Result<(), AppError> {
+    let error = |e: String| AppError::DownloadReport(path.display().to_string(), e);
+    let response = agent.get(url).call().map_err(|e| error(e.to_string()))?;
+    let mut file = fs::File::create(path).map_err(|e| error(e.to_string()))?;
+    io::copy(&mut response.into_reader(), &mut file).map_err(|e| error(e.to_string()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helix::BitsLeaderboard;
+
+    fn csv(table: &Table) -> String {
+        let mut out = Vec::new();
+        table.write_csv(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn leader(name: &str, rank: i64, score: i64) -> serde_json::Value {
+        let login = name.to_lowercase();
+        json!({
+            "user_id": login,
+            "user_login": login,
+            "user_name": name,
+            "rank": rank,
+            "score": score,
+        })
+    }
+
+    #[test]
+    fn bits_leaderboard() {
+        let leaderboard = serde_json::from_value::<BitsLeaderboard>(json!({
+            "data": [
+                leader("Ferris", 1, 12543),
+                leader("Crab", 2, 6900),
+            ],
+            "date_range": { "started_at": "", "ended_at": "" },
+            "total": 2,
+        }))
+        .unwrap();
+        assert_eq!(
+            csv(&leaders_table(leaderboard.data)),
+            "rank,bits,name\n1,12543,Ferris\n2,6900,Crab\n"
+        );
+    }
+
+    #[test]
+    fn subscription_tiers() {
+        assert_eq!(tier("1000"), "1");
+        assert_eq!(tier("3000"), "3");
+        assert_eq!(tier("Prime"), "Prime");
+    }
+
+    #[test]
+    fn analytics_report_files() {
+        let report = serde_json::from_value::<GameAnalytics>(json!({
+            "game_id": "493057",
+            "URL": "https://twitch-piper-reports.s3.amazonaws.com/games/493057/overview",
+            "type": "overview_v2",
+            "date_range": {
+                "started_at": "2018-01-01T00:00:00Z",
+                "ended_at": "2018-03-01T00:00:00Z",
+            },
+        }))
+        .unwrap();
+        assert!(report.url.starts_with("https://"));
+        assert_eq!(report_file(&report), "493057-2018-01-01-2018-03-01.csv");
+        assert_eq!(day(""), "");
+    }
+}
@@ -0,0 +1,153 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+
+use crate::api::UserCache;
+use crate::auth::{cached_token_expiry, token_cache_path};
+use crate::AppError;
+
+/// Options of the `cache` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct CacheArgs {
+    #[clap(subcommand)]
+    action: CacheAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum CacheAction {
+    /// Show what is cached, its size and age
+    Status,
+    /// Delete cached data, all of it unless some kinds are picked
+    Clear {
+        /// Delete the cached app access token
+        #[clap(long)]
+        tokens: bool,
+
+        /// Delete the cached channel metadata of the extra columns
+        #[clap(long)]
+        users: bool,
+    },
+}
+
+/// Runs the `cache` subcommand
+pub(super) fn cache(args: CacheArgs) -> Result<(), AppError> {
+    match args.action {
+        CacheAction::Status => {
+            status();
+            Ok(())
+        }
+        CacheAction::Clear { tokens, users } => {
+            let all = !tokens && !users;
+            if tokens || all {
+                clear("app access token", token_cache_path())?;
+            }
+            if users || all {
+                clear("user metadata", UserCache::path())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn status() {
+    let now = Utc::now();
+
+    match token_cache_path().filter(|path| path.exists()) {
+        Some(path) => {
+            let expiry = match cached_token_expiry() {
+                Some(expires_at) if expires_at > now => {
+                    format!("expires in {}", age(expires_at - now))
+                }
+                Some(_) => "expired".to_string(),
+                None => "expiry unknown".to_string(),
+            };
+            println!(
+                "App access token: {}, {}, saved {} ago, {expiry}",
+                path.display(),
+                size(&path),
+                modified_ago(&path, now),
+            );
+        }
+        None => println!("App access token: not cached"),
+    }
+
+    match UserCache::path().filter(|path| path.exists()) {
+        Some(path) => {
+            let cache = UserCache::load();
+            let mut fetched = cache.fetched_at().collect::<Vec<_>>();
+            fetched.sort();
+            let ages = match (fetched.first(), fetched.last()) {
+                (Some(&oldest), Some(&newest)) => {
+                    format!(
+                        ", fetched {} to {} ago",
+                        age(now - newest),
+                        age(now - oldest)
+                    )
+                }
+                _ => String::new(),
+            };
+            println!(
+                "User metadata: {}, {}, {} channel(s){ages}",
+                path.display(),
+                size(&path),
+                fetched.len(),
+            );
+        }
+        None => println!("User metadata: not cached"),
+    }
+}
+
+/// Deletes the cache file at `path`, telling what was done
+fn clear(what: &str, path: Option<PathBuf>) -> Result<(), AppError> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    match fs::remove_file(&path) {
+        Ok(()) => println!("Deleted the cached {what}."),
+        Err(e) if e.kind() == ErrorKind::NotFound => println!("No cached {what}."),
+        Err(e) => return Err(AppError::ClearCache(path.display().to_string(), e)),
+    }
+    Ok(())
+}
+
+fn size(path: &Path) -> String {
+    let bytes = fs::metadata(path).map(|m| m.len()).unwrap_or_default();
+    match bytes {
+        0..1024 => format!("{bytes} B"),
+        1024..1048576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+    }
+}
+
+fn modified_ago(path: &Path, now: DateTime<Utc>) -> String {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|modified| age(now - DateTime::<Utc>::from(modified)))
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+/// A duration in its largest whole unit, like `3h` or `12d`
+fn age(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ages() {
+        assert_eq!(age(chrono::Duration::seconds(-5)), "0s");
+        assert_eq!(age(chrono::Duration::seconds(59)), "59s");
+        assert_eq!(age(chrono::Duration::minutes(90)), "1h");
+        assert_eq!(age(chrono::Duration::days(59)), "59d");
+    }
+}
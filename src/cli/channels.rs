@@ -0,0 +1,87 @@
+use serde_json::json;
+
+use crate::config::Config;
+use crate::table::Table;
+use crate::AppError;
+
+use super::{load_config, parse_from_one, print_json, print_table, CommonArgs, OutputFormat};
+
+/// Options of the `channels` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct ChannelsArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    /// Words to look for in channel names and titles
+    #[clap(required = true)]
+    query: Vec<String>,
+
+    /// Only show channels that are live right now
+    #[clap(long)]
+    live: bool,
+
+    /// Fetch at most this many pages of 100 channels
+    #[clap(long, default_value_t = 1, value_parser = parse_from_one)]
+    max_pages: usize,
+}
+
+/// Searches channels with the Search Channels endpoint, which matches names
+/// and the last stream title, and prints them best match first
+pub(super) fn channels(mut args: ChannelsArgs) -> Result<bool, AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+
+    let languages = args.common.languages()?;
+    let client = args.common.client()?;
+    let query = args.query.join(" ");
+    let channels = client
+        .search_channels(&query, args.live, args.max_pages)?
+        .into_iter()
+        .filter(|c| languages.is_empty() || languages.contains(&c.broadcaster_language))
+        .collect::<Vec<_>>();
+
+    let found = !channels.is_empty();
+    let output = args.common.output();
+    if output == OutputFormat::Json {
+        let json = channels
+            .iter()
+            .map(|c| {
+                json!({
+                    "user_login": c.broadcaster_login,
+                    "user_name": c.display_name,
+                    "language": c.broadcaster_language,
+                    "game_name": c.game_name,
+                    "title": c.title,
+                    "is_live": c.is_live,
+                    "tags": c.tags,
+                    "url": format!("https://twitch.tv/{}", c.broadcaster_login),
+                })
+            })
+            .collect();
+        print_json(json, &args.common)?;
+        return Ok(found);
+    }
+
+    let mut table = Table::new(5);
+    table.set_headers(
+        ["lang", "channel", "live", "game", "title"]
+            .map(str::to_string)
+            .to_vec(),
+    );
+    for channel in channels {
+        table.push(vec![
+            channel.broadcaster_language,
+            format!("https://twitch.tv/{}", channel.broadcaster_login),
+            match channel.is_live {
+                true => "live".to_string(),
+                false => String::new(),
+            },
+            channel.game_name,
+            channel.title,
+        ]);
+    }
+    print_table(&table, &args.common)?;
+
+    Ok(found)
+}
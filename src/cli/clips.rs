@@ -0,0 +1,212 @@
+use std::cmp::Reverse;
+
+use chrono::prelude::*;
+use serde_json::json;
+
+use crate::api::DEFAULT_GAME_IDS;
+use crate::config::Config;
+use crate::table::{Align, Table};
+use crate::{AppError, TwitchClient};
+
+use super::{
+    load_config, parse_from_one, print_json, print_table, CommonArgs, OutputFormat, TermArgs,
+};
+
+/// Options of the `clip` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct ClipArgs {
+    /// Login of the live channel to clip
+    channel: String,
+}
+
+/// Runs the `clip` subcommand
+pub(super) fn clip(args: ClipArgs) -> Result<(), AppError> {
+    load_config(None)?;
+    let client = TwitchClient::for_user()?;
+    let login = args.channel.trim_start_matches('@').to_lowercase();
+    let broadcaster_id = client.resolve_user(&login)?;
+    let edit_url = clip_channel(&client, &broadcaster_id)?;
+    println!("Clipped {login}, edit it at {edit_url}");
+    Ok(())
+}
+
+/// Clips the live stream of `broadcaster_id` with the user token of
+/// `client`, returning where to edit the clip
+pub(super) fn clip_channel(
+    client: &TwitchClient,
+    broadcaster_id: &str,
+) -> Result<String, AppError> {
+    Ok(client.create_clip(broadcaster_id)?.edit_url)
+}
+
+// Options of the `clips` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct ClipsArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    #[clap(flatten)]
+    terms: TermArgs,
+
+    /// Game or category to search, by name or id (can be repeated)
+    #[clap(short, long, multiple_occurrences = true, number_of_values = 1)]
+    game: Vec<String>,
+
+    /// Channel to search instead of a game, by login (can be repeated)
+    #[clap(
+        short,
+        long,
+        multiple_occurrences = true,
+        number_of_values = 1,
+        conflicts_with = "game"
+    )]
+    channel: Vec<String>,
+
+    /// Only clips created at or after this time (RFC 3339, e.g. 2024-05-01T00:00:00Z)
+    #[clap(long, value_name = "TIME", value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+
+    /// Only clips created before this time (RFC 3339)
+    #[clap(long, value_name = "TIME", value_parser = parse_time)]
+    until: Option<DateTime<Utc>>,
+
+    /// Fetch at most this many pages of 100 clips per game or channel
+    #[clap(long, default_value_t = 10, value_parser = parse_from_one)]
+    max_pages: usize,
+}
+
+pub(super) fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("expected an RFC 3339 time like 2024-05-01T00:00:00Z: {e}"))
+}
+
+/// Searches the titles of clips of some games or channels, most viewed first
+pub(super) fn clips(mut args: ClipsArgs) -> Result<bool, AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+    args.terms.blocklist = config.blocklist;
+    if args.game.is_empty() && args.channel.is_empty() {
+        args.game = config.games;
+    }
+
+    let filter = args.terms.title_filter()?;
+    let languages = args.common.languages()?;
+    let client = args.common.client()?;
+    let sources = match (args.channel.is_empty(), args.game.is_empty()) {
+        (false, _) => args
+            .channel
+            .iter()
+            .map(|login| Ok(("broadcaster_id", client.resolve_user(login)?)))
+            .collect::<Result<Vec<_>, AppError>>()?,
+        (true, true) => DEFAULT_GAME_IDS
+            .iter()
+            .map(|id| ("game_id", id.to_string()))
+            .collect(),
+        (true, false) => args
+            .game
+            .iter()
+            .map(|game| Ok(("game_id", client.resolve_game(game)?)))
+            .collect::<Result<_, AppError>>()?,
+    };
+
+    let mut clips = Vec::new();
+    for (key, id) in &sources {
+        clips.extend(
+            client
+                .fetch_clips((key, id), args.since, args.until, args.max_pages)?
+                .into_iter()
+                .filter(|c| languages.is_empty() || languages.contains(&c.language))
+                .filter(|c| filter.matches(&c.title)),
+        );
+    }
+    clips.sort_by_key(|c| Reverse(c.view_count));
+
+    let found = !clips.is_empty();
+    let output = args.common.output();
+    if output == OutputFormat::Json {
+        let json = clips
+            .iter()
+            .map(|c| {
+                json!({
+                    "id": c.id,
+                    "broadcaster_name": c.broadcaster_name,
+                    "creator_name": c.creator_name,
+                    "title": c.title,
+                    "language": c.language,
+                    "created_at": c.created_at,
+                    "duration": c.duration,
+                    "view_count": c.view_count,
+                    "url": c.url,
+                })
+            })
+            .collect();
+        print_json(json, &args.common)?;
+        return Ok(found);
+    }
+
+    let mut table = Table::new(7);
+    table.set_align(2, Align::Right);
+    table.set_align(3, Align::Right);
+    table.set_headers(
+        [
+            "channel", "creator", "views", "duration", "created", "url", "title",
+        ]
+        .map(str::to_string)
+        .to_vec(),
+    );
+    for clip in clips {
+        let created = clip
+            .created_at
+            .parse::<DateTime<Utc>>()
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d").to_string())
+            .unwrap_or(clip.created_at);
+        table.push(vec![
+            clip.broadcaster_name,
+            clip.creator_name,
+            clip.view_count.to_string(),
+            format!("{:.0}s", clip.duration),
+            created,
+            clip.url,
+            clip.title,
+        ]);
+    }
+    print_table(&table, &args.common)?;
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::{Cli, Command};
+
+    fn clips_args(args: &[&str]) -> ClipsArgs {
+        let args = ["twitch-search", "clips"]
+            .into_iter()
+            .chain(args.iter().copied());
+        match Cli::parse_from(args).command {
+            Some(Command::Clips(args)) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn time_window() {
+        let utc = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        assert_eq!(parse_time("2024-05-01T00:00:00Z"), Ok(utc));
+        assert_eq!(parse_time("2024-05-01T02:00:00+02:00"), Ok(utc));
+        for time in ["2024-05-01", "yesterday", ""] {
+            assert!(parse_time(time).is_err(), "{time}");
+        }
+
+        let args = clips_args(&["--since", "2024-05-01T00:00:00Z", "-c", "someone", "rust"]);
+        assert_eq!(args.since, Some(utc));
+        assert_eq!(args.until, None);
+        assert_eq!(args.channel, ["someone"]);
+        assert!(Cli::try_parse_from(["twitch-search", "clips", "--until", "may"]).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::auth::load_credentials;
+use crate::config::Config;
+use crate::{AppError, TwitchClient};
+
+use super::config_problems;
+
+/// Options of the `config` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct ConfigArgs {
+    #[clap(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigAction {
+    /// Report the problems in the config file, with their line numbers
+    Check {
+        /// Check this file instead of ~/.config/twitch-search/config.toml
+        #[clap(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+
+        /// Also look up the games of the config and its saved searches on Twitch
+        #[clap(long)]
+        online: bool,
+    },
+}
+
+/// Runs the `config` subcommand
+pub(super) fn config(args: ConfigArgs) -> Result<(), AppError> {
+    match args.action {
+        ConfigAction::Check { config, online } => check(config, online),
+    }
+}
+
+fn check(path: Option<PathBuf>, online: bool) -> Result<(), AppError> {
+    let Some(path) = path.or_else(Config::default_path) else {
+        println!("No config file location; set HOME or XDG_CONFIG_HOME.");
+        return Ok(());
+    };
+    if !path.exists() {
+        println!("No config file at {}, using the defaults.", path.display());
+        return Ok(());
+    }
+    // Syntax errors and unknown keys come with their line from the parser
+    let config = Config::load(&path)?;
+    let text = fs::read_to_string(&path)
+        .map_err(|e| AppError::ReadConfig(path.display().to_string(), e.to_string()))?;
+
+    let mut problems = config_problems(&config);
+    if let Some(credentials) = &config.credentials {
+        if let Err(e) = load_credentials(credentials) {
+            problems.push(("credentials".to_string(), e.to_string()));
+        }
+    }
+    if online {
+        problems.extend(game_problems(&config)?);
+    }
+
+    // In the order of the file, the ones without a line last
+    problems.sort_by_key(|(key, _)| key_line(&text, key).unwrap_or(usize::MAX));
+    for (key, problem) in &problems {
+        println!("{}: {key}: {problem}", location(&path, &text, key));
+    }
+    match problems.len() {
+        0 => {
+            println!("{} is valid.", path.display());
+            Ok(())
+        }
+        n => Err(AppError::InvalidConfig(n)),
+    }
+}
+
+/// The games of the config and its saved searches that Twitch doesn't know
+fn game_problems(config: &Config) -> Result<Vec<(String, String)>, AppError> {
+    let mut games = config
+        .games
+        .iter()
+        .map(|game| ("games".to_string(), game))
+        .collect::<Vec<_>>();
+    for (name, profile) in &config.search {
+        games.extend(
+            profile
+                .games
+                .iter()
+                .map(|game| (format!("search.{name}.games"), game)),
+        );
+    }
+    if games.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let client = TwitchClient::from_env(true)?;
+    let mut problems = Vec::new();
+    for (key, game) in games {
+        let known = match game.chars().all(|c| c.is_ascii_digit()) {
+            true => client
+                .game_by_id(game)?
+                .map(|_| ())
+                .ok_or_else(|| AppError::UnknownGame(game.clone(), vec![])),
+            false => client.resolve_game(game).map(|_| ()),
+        };
+        match known {
+            Ok(()) => {}
+            Err(e @ AppError::UnknownGame(..)) => problems.push((key, e.to_string())),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(problems)
+}
+
+/// `path:line` of `key` in the config file, or just the path if it can't be
+/// told where the key is
+fn location(path: &Path, text: &str, key: &str) -> String {
+    match key_line(text, key) {
+        Some(line) => format!("{}:{line}", path.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// The line number of a dotted `key` like `search.rust.lang`, set either
+/// under its `[table]` header or as a dotted key
+fn key_line(text: &str, key: &str) -> Option<usize> {
+    let unquote = |name: &str| name.trim().replace(['"', '\''], "");
+    let mut table = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_start_matches('[');
+            let header = header.split(']').next().unwrap_or_default();
+            table = unquote(header);
+            continue;
+        }
+        let Some((name, _)) = line.split_once('=') else {
+            continue;
+        };
+        if line.starts_with('#') {
+            continue;
+        }
+        let name = unquote(name)
+            .split('.')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(".");
+        let full = match table.is_empty() {
+            true => name,
+            false => format!("{table}.{name}"),
+        };
+        if full == key {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = r#"# Defaults
+lang = ["en", "xx"]
+output = "table"
+
+[search.rust]
+terms = ["rust"]
+lang = ["sv"]
+
+[search."game dev"]
+games = ["Software and Game Development"]
+"#;
+
+    #[test]
+    fn top_level_keys() {
+        assert_eq!(key_line(TEXT, "lang"), Some(2));
+        assert_eq!(key_line(TEXT, "output"), Some(3));
+        assert_eq!(key_line(TEXT, "player"), None);
+    }
+
+    #[test]
+    fn keys_in_tables() {
+        assert_eq!(key_line(TEXT, "search.rust.lang"), Some(7));
+        assert_eq!(key_line(TEXT, "search.game dev.games"), Some(10));
+    }
+
+    #[test]
+    fn dotted_keys() {
+        let text = "search.rust.terms = [\"rust\"]\nsearch.rust.lang = [\"xx\"]\n";
+        assert_eq!(key_line(text, "search.rust.lang"), Some(2));
+    }
+}
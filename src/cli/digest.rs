@@ -0,0 +1,81 @@
+use chrono::prelude::*;
+
+use crate::StreamEntry;
+
+/// Streams gathered to be told about at once, when the first of them has
+/// waited a whole window
+#[derive(Debug)]
+pub(super) struct Digest {
+    window: chrono::Duration,
+    /// When the first of `pending` came in
+    since: Option<DateTime<Utc>>,
+    pending: Vec<StreamEntry>,
+}
+
+impl Digest {
+    pub(super) fn new(minutes: u64) -> Self {
+        Digest {
+            window: chrono::Duration::minutes(minutes as i64),
+            since: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Adds the streams of `entries` not pending already, and updates the
+    /// ones that are
+    pub(super) fn add<'a>(
+        &mut self,
+        entries: impl IntoIterator<Item = &'a StreamEntry>,
+        now: DateTime<Utc>,
+    ) {
+        for entry in entries {
+            match self.pending.iter_mut().find(|e| e.id == entry.id) {
+                Some(pending) => *pending = entry.clone(),
+                None => {
+                    self.since.get_or_insert(now);
+                    self.pending.push(entry.clone());
+                }
+            }
+        }
+    }
+
+    /// The pending streams once the window is over, which starts a new one
+    pub(super) fn take(&mut self, now: DateTime<Utc>) -> Option<Vec<StreamEntry>> {
+        let since = self.since?;
+        if now - since < self.window {
+            return None;
+        }
+        self.since = None;
+        Some(std::mem::take(&mut self.pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+
+    #[test]
+    fn gathers_a_window() {
+        let start: DateTime<Utc> = "2026-10-16T12:00:00Z".parse().unwrap();
+        let minutes = |m| start + chrono::Duration::minutes(m);
+        let mut digest = Digest::new(60);
+        assert!(digest.take(start).is_none());
+
+        digest.add(&[test_entry("Crab", 5)], minutes(10));
+        digest.add(
+            &[test_entry("Crab", 8), test_entry("Ferris", 3)],
+            minutes(40),
+        );
+        assert!(digest.take(minutes(69)).is_none());
+
+        let sent = digest.take(minutes(70)).unwrap();
+        assert_eq!(
+            sent.iter()
+                .map(|e| (&*e.display_name, e.viewer_count))
+                .collect::<Vec<_>>(),
+            [("Crab", 8), ("Ferris", 3)]
+        );
+        assert!(digest.take(minutes(200)).is_none());
+    }
+}
@@ -0,0 +1,237 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+
+use chrono::prelude::*;
+
+use crate::api::{configure_agent, HELIX_URL};
+use crate::auth::{
+    aquire_access_token, client_id_source, client_secret_source, load_credentials,
+    validate_access_token, CredentialSource,
+};
+use crate::config::Config;
+use crate::AppError;
+
+use super::config_problems;
+use super::history::history_path;
+
+/// Clock differences beyond this are reported, since they break token expiry
+const MAX_CLOCK_SKEW: i64 = 60;
+
+#[derive(Debug)]
+struct Doctor {
+    agent: ureq::Agent,
+    failures: usize,
+}
+
+impl Doctor {
+    fn ok(&self, check: &str) {
+        println!("[ ok ] {check}");
+    }
+
+    fn fail(&mut self, check: &str, fix: &str) {
+        self.failures += 1;
+        println!("[FAIL] {check}");
+        println!("       {fix}");
+    }
+
+    /// Checks a client credential, found the same way the client finds it
+    fn credential(&mut self, name: &str, found: Option<(String, CredentialSource)>) {
+        match found {
+            Some((value, source)) if !value.trim().is_empty() => {
+                self.ok(&format!("{name} is set (from {source})"))
+            }
+            Some((_, source)) => self.fail(
+                &format!("{name} is empty (in {source})"),
+                "Fill it in with the credentials of your application at https://dev.twitch.tv/console.",
+            ),
+            None => self.fail(
+                &format!("{name} is not set"),
+                "Register an application at https://dev.twitch.tv/console and export its credentials, or name a credentials file in the config.",
+            ),
+        }
+    }
+
+    fn proxy(&mut self) {
+        match env::var("https_proxy") {
+            Err(_) => self.ok("No https_proxy configured"),
+            Ok(proxy) => match ureq::Proxy::new(&proxy) {
+                Ok(_) => self.ok(&format!("Using proxy {proxy}")),
+                Err(e) => self.fail(
+                    &format!("https_proxy {proxy:?} is invalid: {e}"),
+                    "Use the form http://host:port, or unset https_proxy. An invalid proxy is silently ignored.",
+                ),
+            },
+        }
+    }
+
+    /// Any HTTP response, even an error status, proves the host is reachable.
+    /// Returns the server's clock from the `Date` header, if it sent one.
+    fn reachable(&mut self, host: &str, url: &str) -> Option<DateTime<Utc>> {
+        let resp = match self.agent.get(url).call() {
+            Ok(resp) => resp,
+            Err(ureq::Error::Status(_, resp)) => resp,
+            Err(e) => {
+                self.fail(
+                    &format!("Cannot reach {host}: {e}"),
+                    "Check your network connection, firewall and https_proxy setting.",
+                );
+                return None;
+            }
+        };
+        self.ok(&format!("{host} is reachable"));
+
+        resp.header("date")
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.with_timezone(&Utc))
+    }
+
+    fn clock(&mut self, server_time: DateTime<Utc>) {
+        let skew = (Utc::now() - server_time).num_seconds();
+        if skew.abs() <= MAX_CLOCK_SKEW {
+            self.ok(&format!(
+                "Clock is within {MAX_CLOCK_SKEW}s of Twitch ({skew:+}s)"
+            ));
+        } else {
+            self.fail(
+                &format!("Clock is off by {skew:+}s compared to Twitch"),
+                "Enable time synchronisation (NTP) on this machine.",
+            );
+        }
+    }
+
+    fn token(&mut self) {
+        let token = match aquire_access_token(&self.agent, false) {
+            Ok(token) => token,
+            Err(e) => {
+                self.fail(
+                    &format!("Could not get an access token: {e}"),
+                    "Double-check the client id and secret; the secret may have been regenerated.",
+                );
+                return;
+            }
+        };
+
+        match validate_access_token(&self.agent, &token) {
+            Ok(expires_in) => self.ok(&format!("Access token is valid (expires in {expires_in}s)")),
+            Err(e) => self.fail(
+                &format!("Access token did not validate: {e}"),
+                "Try again in a moment; if it persists, regenerate the client secret.",
+            ),
+        }
+    }
+
+    /// Checks the config file, and loads the credentials file it names for
+    /// the checks after it
+    fn config(&mut self) {
+        let Some(path) = Config::default_path().filter(|path| path.exists()) else {
+            self.ok("No config file, using the defaults");
+            return;
+        };
+        let config = match Config::load(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                self.fail(&e.to_string(), "Fix the config file at the line given.");
+                return;
+            }
+        };
+
+        let mut problems = config_problems(&config);
+        if let Some(credentials) = &config.credentials {
+            if let Err(e) = load_credentials(credentials) {
+                problems.push(("credentials".to_string(), e.to_string()));
+            }
+        }
+        if problems.is_empty() {
+            self.ok(&format!("Config file {} is valid", path.display()));
+        }
+        for (key, problem) in problems {
+            self.fail(
+                &format!("Config file {}: {key}: {problem}", path.display()),
+                "Fix or remove that setting; `twitch-search config check` gives its line.",
+            );
+        }
+    }
+
+    fn history(&mut self) {
+        let Some(path) = history_path() else {
+            self.fail(
+                "No location for the search history",
+                "Set HOME or XDG_DATA_HOME.",
+            );
+            return;
+        };
+        match check_writable(&path) {
+            Ok(()) => self.ok(&format!("History is writable at {}", path.display())),
+            Err(e) => self.fail(
+                &format!("Cannot write history at {}: {e}", path.display()),
+                "Fix the permissions of that directory, or point XDG_DATA_HOME elsewhere.",
+            ),
+        }
+    }
+}
+
+fn check_writable(path: &std::path::Path) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(())
+}
+
+pub(super) fn doctor() -> Result<(), AppError> {
+    let mut doctor = Doctor {
+        agent: configure_agent(),
+        failures: 0,
+    };
+
+    doctor.config();
+    doctor.credential("Client id", client_id_source());
+    doctor.credential("Client secret", client_secret_source());
+    doctor.proxy();
+
+    let server_time = doctor.reachable("id.twitch.tv", "https://id.twitch.tv/oauth2/validate");
+    let api_time = doctor.reachable("api.twitch.tv", &format!("{HELIX_URL}/streams"));
+    if let Some(server_time) = server_time.or(api_time) {
+        doctor.clock(server_time);
+    }
+
+    doctor.token();
+    doctor.history();
+
+    match doctor.failures {
+        0 => Ok(()),
+        n => Err(AppError::DoctorFailed(n)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doctor() -> Doctor {
+        Doctor {
+            agent: configure_agent(),
+            failures: 0,
+        }
+    }
+
+    #[test]
+    fn doctor_clock_skew() {
+        let mut doctor = doctor();
+        doctor.clock(Utc::now() - chrono::Duration::seconds(MAX_CLOCK_SKEW - 5));
+        assert_eq!(doctor.failures, 0);
+        doctor.clock(Utc::now() + chrono::Duration::minutes(5));
+        assert_eq!(doctor.failures, 1);
+    }
+
+    #[test]
+    fn doctor_credentials_must_be_filled_in() {
+        let mut doctor = doctor();
+        let env = CredentialSource::Env("TWITCH_CLIENT_ID");
+        doctor.credential("Client id", Some(("abc".to_string(), env.clone())));
+        assert_eq!(doctor.failures, 0);
+        doctor.credential("Client id", Some((" ".to_string(), env)));
+        doctor.credential("Client id", None);
+        assert_eq!(doctor.failures, 2);
+    }
+}
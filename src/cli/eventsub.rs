@@ -0,0 +1,862 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+use serde_json::Value;
+use tungstenite::http::Uri;
+use tungstenite::{Message, WebSocket};
+
+use crate::api::EventSubTransport;
+use crate::config::Config;
+use crate::{AppError, StreamEntry, TwitchClient};
+
+use super::template::Messages;
+use super::watch::{notify, timestamp};
+use super::webhook::{announce, Announced, WebhookFormat};
+use super::{load_config, CommonArgs};
+
+/// Options of the `eventsub` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct EventSubArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    /// Channels to hear about when they go live
+    #[clap(required = true)]
+    channels: Vec<String>,
+
+    /// Public HTTPS address that Twitch sends the events to, and that ends up
+    /// at --listen. Without it the events come over a WebSocket, which needs
+    /// no public address but a user access token (see `login`).
+    #[clap(long, value_name = "URL")]
+    callback: Option<String>,
+
+    /// Address to take the events on, behind a reverse proxy unless
+    /// --tls-cert is given
+    #[clap(
+        long,
+        value_name = "ADDRESS",
+        default_value = "127.0.0.1:8080",
+        requires = "callback"
+    )]
+    listen: String,
+
+    /// Certificate chain (PEM) to serve HTTPS with, without a reverse proxy
+    #[clap(long, value_name = "FILE", requires_all = &["tls-key", "callback"])]
+    tls_cert: Option<PathBuf>,
+
+    /// Private key (PEM) of --tls-cert
+    #[clap(long, value_name = "FILE", requires = "tls-cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Raise a desktop notification for every channel going live
+    #[clap(long)]
+    notify: bool,
+
+    /// Post the channels going live to this webhook
+    #[clap(long, value_name = "URL")]
+    webhook_url: Option<String>,
+
+    /// Message format of --webhook-url, by default guessed from its address
+    #[clap(long, value_enum, value_name = "FORMAT", requires = "webhook-url")]
+    webhook_format: Option<WebhookFormat>,
+}
+
+/// Messages sent longer ago than this, or this far ahead of our clock, are
+/// turned away, as replays
+const MAX_MESSAGE_AGE: chrono::Duration = chrono::Duration::minutes(10);
+
+/// Number of message ids remembered to drop the ones Twitch sends again
+const SEEN_MESSAGES: usize = 1000;
+
+/// Larger requests aren't from EventSub
+const MAX_BODY: usize = 1 << 20;
+
+/// Most bytes of request line and headers taken in a request
+const MAX_HEAD: u64 = 16 << 10;
+
+/// Most headers taken in a request
+const MAX_HEADERS: usize = 100;
+
+/// Time a client gets to send its request and read the answer, TLS
+/// handshake included, so stalled ones don't pile up. Also the time the
+/// WebSocket server gets to welcome us.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Twitch's EventSub WebSocket server
+const EVENTSUB_WEBSOCKET: &str = "wss://eventsub.wss.twitch.tv/ws";
+
+/// Time past the keepalive interval of a WebSocket session before it is
+/// taken as lost
+const KEEPALIVE_SLACK: Duration = Duration::from_secs(5);
+
+/// Pause before opening a new WebSocket session after losing one
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Subscribes to the channels going live, with the EventSub webhook
+/// transport when given a callback and the WebSocket one otherwise, then
+/// takes the events until interrupted
+pub(super) fn eventsub(mut args: EventSubArgs) -> Result<(), AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+    let announcer = Announcer {
+        messages: Messages::new(&config.templates)?,
+        announced: args
+            .webhook_url
+            .as_deref()
+            .map(|url| Announced::load_or_warn(url, "posted", |e| eprintln!("Warning: {e}"))),
+    };
+    match &args.callback {
+        Some(callback) => webhook(&args, callback, announcer),
+        None => websocket(&args, announcer),
+    }
+}
+
+/// The user ids of `channels`
+fn resolve(client: &TwitchClient, channels: &[String]) -> Result<Vec<String>, AppError> {
+    channels
+        .iter()
+        .map(|channel| client.resolve_user(channel))
+        .collect()
+}
+
+/// Takes the events Twitch POSTs to `callback`, which ends up at `--listen`
+fn webhook(args: &EventSubArgs, callback: &str, mut announcer: Announcer) -> Result<(), AppError> {
+    let tls = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(tls_config(cert, key)?),
+        _ => None,
+    };
+    let secret = secret()?;
+    let client = args.common.client()?;
+    let ids = resolve(&client, &args.channels)?;
+
+    // Listening first, so the challenge Twitch sends right after subscribing
+    // waits for us rather than failing
+    let listener = TcpListener::bind(&args.listen)
+        .map_err(|e| AppError::EventSubListen(args.listen.clone(), e))?;
+    subscribe(&client, &ids, callback, &secret)?;
+    eprintln!("Listening on {} for {} channel(s)", args.listen, ids.len());
+
+    let receiver = Mutex::new(Receiver {
+        secret,
+        seen: Seen::default(),
+    });
+    let (online, went_live) = mpsc::channel::<String>();
+    let (client, receiver, tls) = (&client, &receiver, &tls);
+    thread::scope(|scope| {
+        // Looking the channels up and posting about them takes a while, so
+        // it is done here, one at a time, while requests keep being answered
+        scope.spawn(move || {
+            for login in went_live {
+                announcer.went_live(client, args, &login);
+            }
+        });
+        for connection in listener.incoming() {
+            let tcp = match connection {
+                Ok(tcp) => tcp,
+                Err(e) => {
+                    eprintln!("Warning: failed to accept a connection: {e}");
+                    continue;
+                }
+            };
+            let online = online.clone();
+            scope.spawn(move || {
+                let mut tcp = Deadline::new(tcp, CONNECTION_TIMEOUT);
+                let result = match tls {
+                    Some(tls) => ServerConnection::new(tls.clone())
+                        .map_err(io::Error::other)
+                        .and_then(|server| answer(receiver, &mut StreamOwned::new(server, tcp))),
+                    None => answer(receiver, &mut tcp),
+                };
+                match result {
+                    Ok(Some(login)) => {
+                        // Only fails once the listener is gone
+                        let _ = online.send(login);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Warning: failed to handle a request: {e}"),
+                }
+            });
+        }
+        drop(online);
+    });
+    Ok(())
+}
+
+/// `TWITCH_EVENTSUB_SECRET`, or else a new random one
+fn secret() -> Result<String, AppError> {
+    if let Ok(secret) = env::var("TWITCH_EVENTSUB_SECRET") {
+        return match (10..=100).contains(&secret.len()) {
+            true => Ok(secret),
+            false => Err(AppError::EventSubSecret),
+        };
+    }
+    let mut bytes = [0; 20];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| AppError::EventSubSecret)?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Subscribes `callback` to the channels of `ids` going live, replacing the
+/// subscriptions of earlier runs, whose secret is no longer known
+fn subscribe(
+    client: &TwitchClient,
+    ids: &[String],
+    callback: &str,
+    secret: &str,
+) -> Result<(), AppError> {
+    let wanted = ids.iter().map(String::as_str).collect::<HashSet<_>>();
+    for subscription in client.eventsub_subscriptions()? {
+        let ours = subscription.kind == "stream.online"
+            && subscription.transport.callback.as_deref() == Some(callback)
+            && subscription
+                .condition
+                .broadcaster_user_id
+                .as_deref()
+                .is_some_and(|id| wanted.contains(id));
+        if ours {
+            client.unsubscribe(&subscription.id)?;
+        }
+    }
+    for id in ids {
+        let transport = EventSubTransport::Webhook { callback, secret };
+        let subscription = client.subscribe_stream_online(id, transport)?;
+        eprintln!("Subscribed to {id} ({})", subscription.status);
+    }
+    Ok(())
+}
+
+fn tls_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>, AppError> {
+    let error = |path: &Path, e: &dyn std::fmt::Display| {
+        AppError::EventSubTls(format!("{}: {e}", path.display()))
+    };
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| error(cert, &e))?;
+    let key = PrivateKeyDer::from_pem_file(key).map_err(|e| error(key, &e))?;
+    let config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+            .map_err(|e| AppError::EventSubTls(e.to_string()))?;
+    Ok(Arc::new(config))
+}
+
+// -----------------------------------------------------------------------------
+//     - Callback -
+// -----------------------------------------------------------------------------
+
+/// Answers one request with `receiver`, which is only locked while the
+/// answer is worked out. Returns the login of the channel that went live, if
+/// it was about that, so the answer isn't held up by acting on it.
+fn answer(
+    receiver: &Mutex<Receiver>,
+    connection: &mut (impl Read + Write),
+) -> io::Result<Option<String>> {
+    let request = Request::read(connection)?;
+    let (status, body, online) = receiver
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .respond(&request, Utc::now());
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        _ => "Not Found",
+    };
+    write!(
+        connection,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    connection.flush()?;
+    Ok(online)
+}
+
+struct Receiver {
+    secret: String,
+    seen: Seen,
+}
+
+impl Receiver {
+    /// The status and body to answer `request` with, and the login of the
+    /// channel that went live, if any
+    fn respond(&mut self, request: &Request, now: DateTime<Utc>) -> (u16, String, Option<String>) {
+        let header = |name: &str| request.headers.get(name).map(String::as_str);
+        let (Some(id), Some(timestamp), Some(signature), Some(kind)) = (
+            header("twitch-eventsub-message-id"),
+            header("twitch-eventsub-message-timestamp"),
+            header("twitch-eventsub-message-signature"),
+            header("twitch-eventsub-message-type"),
+        ) else {
+            return (404, String::new(), None);
+        };
+        if request.method != "POST" {
+            return (404, String::new(), None);
+        }
+
+        let fresh = DateTime::parse_from_rfc3339(timestamp)
+            .is_ok_and(|sent| (now - sent.with_timezone(&Utc)).abs() < MAX_MESSAGE_AGE);
+        if !fresh || !verify(&self.secret, id, timestamp, &request.body, signature) {
+            return (403, String::new(), None);
+        }
+        // Twitch sends messages again until they are answered, so one
+        // answered twice was already handled
+        if !self.seen.first_time(id) {
+            return (204, String::new(), None);
+        }
+
+        let Ok(json) = serde_json::from_slice::<Value>(&request.body) else {
+            return (400, String::new(), None);
+        };
+        match kind {
+            "webhook_callback_verification" => match json["challenge"].as_str() {
+                Some(challenge) => (200, challenge.to_string(), None),
+                None => (400, String::new(), None),
+            },
+            "notification" => {
+                let login = json["event"]["broadcaster_user_login"]
+                    .as_str()
+                    .map(str::to_string);
+                (204, String::new(), login)
+            }
+            "revocation" => {
+                revoked(&json["subscription"]);
+                (204, String::new(), None)
+            }
+            _ => (204, String::new(), None),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - WebSocket -
+// -----------------------------------------------------------------------------
+
+type Socket = WebSocket<StreamOwned<ClientConnection, TcpStream>>;
+
+/// Takes the events of a WebSocket session, in which the channels are
+/// subscribed to. Twitch only takes these subscriptions with a user access
+/// token, and ends them with the session, so a new session after losing one
+/// subscribes again.
+fn websocket(args: &EventSubArgs, mut announcer: Announcer) -> Result<(), AppError> {
+    let client = TwitchClient::for_user()?;
+    let ids = resolve(&client, &args.channels)?;
+
+    let mut seen = Seen::default();
+    let mut socket = connect(EVENTSUB_WEBSOCKET)?;
+    let mut subscribed = false;
+    loop {
+        let lost = match socket.read() {
+            Ok(Message::Text(text)) => {
+                match session_event(&text, &mut seen) {
+                    SessionEvent::Welcome { id, keepalive } => {
+                        let timeout = keepalive + KEEPALIVE_SLACK;
+                        if let Err(e) = socket.get_ref().sock.set_read_timeout(Some(timeout)) {
+                            eprintln!("Warning: failed to watch for keepalives: {e}");
+                        }
+                        if !subscribed {
+                            for broadcaster_id in &ids {
+                                let transport = EventSubTransport::WebSocket { session_id: &id };
+                                client.subscribe_stream_online(broadcaster_id, transport)?;
+                            }
+                            eprintln!("Subscribed to {} channel(s) over a WebSocket", ids.len());
+                            subscribed = true;
+                        }
+                    }
+                    SessionEvent::Live(login) => announcer.went_live(&client, args, &login),
+                    // The subscriptions move along, and the old connection
+                    // is closed once the new one is open
+                    SessionEvent::Reconnect(url) => socket = connect(&url)?,
+                    SessionEvent::Nothing => {}
+                }
+                continue;
+            }
+            Ok(Message::Close(_)) => "closed by Twitch".to_string(),
+            Ok(_) => continue,
+            Err(e) => e.to_string(),
+        };
+        eprintln!(
+            "{} Warning: lost the EventSub WebSocket ({lost}), reconnecting",
+            timestamp()
+        );
+        thread::sleep(RECONNECT_DELAY);
+        socket = connect(EVENTSUB_WEBSOCKET)?;
+        subscribed = false;
+    }
+}
+
+/// Opens the WebSocket at `url`, over TLS
+fn connect(url: &str) -> Result<Socket, AppError> {
+    let error =
+        |e: &dyn std::fmt::Display| AppError::EventSubWebSocket(url.to_string(), e.to_string());
+    let uri = url.parse::<Uri>().map_err(|e| error(&e))?;
+    let host = uri.host().ok_or_else(|| error(&"no host"))?;
+    let tcp = TcpStream::connect((host, uri.port_u16().unwrap_or(443))).map_err(|e| error(&e))?;
+    tcp.set_read_timeout(Some(CONNECTION_TIMEOUT))
+        .map_err(|e| error(&e))?;
+
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| error(&e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    let name = ServerName::try_from(host.to_string()).map_err(|e| error(&e))?;
+    let tls = ClientConnection::new(Arc::new(config), name).map_err(|e| error(&e))?;
+    let (socket, _) =
+        tungstenite::client(url, StreamOwned::new(tls, tcp)).map_err(|e| error(&e))?;
+    Ok(socket)
+}
+
+/// What a message of a WebSocket session asks of us
+#[derive(Debug, PartialEq)]
+enum SessionEvent {
+    /// The session started, with its id and the longest time between
+    /// messages
+    Welcome { id: String, keepalive: Duration },
+    /// The channel of this login went live
+    Live(String),
+    /// The session moves to this address
+    Reconnect(String),
+    /// Keepalives, revocations and messages seen before
+    Nothing,
+}
+
+fn session_event(text: &str, seen: &mut Seen) -> SessionEvent {
+    let Ok(json) = serde_json::from_str::<Value>(text) else {
+        return SessionEvent::Nothing;
+    };
+    let (metadata, payload) = (&json["metadata"], &json["payload"]);
+    if let Some(id) = metadata["message_id"].as_str() {
+        if !seen.first_time(id) {
+            return SessionEvent::Nothing;
+        }
+    }
+    let session = &payload["session"];
+    match metadata["message_type"].as_str().unwrap_or_default() {
+        "session_welcome" => match session["id"].as_str() {
+            Some(id) => SessionEvent::Welcome {
+                id: id.to_string(),
+                keepalive: session["keepalive_timeout_seconds"]
+                    .as_u64()
+                    .map_or(CONNECTION_TIMEOUT, Duration::from_secs),
+            },
+            None => SessionEvent::Nothing,
+        },
+        "notification" => match payload["event"]["broadcaster_user_login"].as_str() {
+            Some(login) => SessionEvent::Live(login.to_string()),
+            None => SessionEvent::Nothing,
+        },
+        "session_reconnect" => match session["reconnect_url"].as_str() {
+            Some(url) => SessionEvent::Reconnect(url.to_string()),
+            None => SessionEvent::Nothing,
+        },
+        "revocation" => {
+            revoked(&payload["subscription"]);
+            SessionEvent::Nothing
+        }
+        _ => SessionEvent::Nothing,
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Events -
+// -----------------------------------------------------------------------------
+
+/// Ids of the latest messages, oldest first, to drop the ones Twitch sends
+/// again
+#[derive(Default)]
+struct Seen(VecDeque<String>);
+
+impl Seen {
+    /// Whether `id` wasn't seen before, remembering it
+    fn first_time(&mut self, id: &str) -> bool {
+        if self.0.iter().any(|seen| seen == id) {
+            return false;
+        }
+        self.0.push_back(id.to_string());
+        if self.0.len() > SEEN_MESSAGES {
+            self.0.pop_front();
+        }
+        true
+    }
+}
+
+/// Warns that Twitch ended `subscription`
+fn revoked(subscription: &Value) {
+    eprintln!(
+        "{} Warning: Twitch ended the subscription for {}: {}",
+        timestamp(),
+        subscription["condition"]["broadcaster_user_id"],
+        subscription["status"]
+    );
+}
+
+/// Tells about the channels going live
+struct Announcer {
+    messages: Messages,
+    announced: Option<Announced>,
+}
+
+impl Announcer {
+    /// Tells about `login` going live, in every way asked for
+    fn went_live(&mut self, client: &TwitchClient, args: &EventSubArgs, login: &str) {
+        let entry = match client.fetch_live(login) {
+            Ok(Some(stream)) => StreamEntry::from(stream),
+            // Helix can lag a little behind the event
+            Ok(None) => {
+                println!("{} {login} is live: https://twitch.tv/{login}", timestamp());
+                return;
+            }
+            Err(e) => {
+                eprintln!("{} Warning: failed to look up {login}: {e}", timestamp());
+                return;
+            }
+        };
+        println!(
+            "{} {} is live: {} ({})",
+            timestamp(),
+            entry.display_name,
+            entry.title,
+            entry.url()
+        );
+        if args.notify {
+            notify(&entry, &self.messages.notify);
+        }
+        if let Some(announced) = &mut self.announced {
+            if let Err(e) = announce(args.webhook_format, &self.messages, &[entry], announced) {
+                eprintln!("{} Warning: {e}", timestamp());
+            }
+            if let Err(e) = announced.store() {
+                eprintln!("Warning: failed to remember the posted streams: {e}");
+            }
+        }
+    }
+}
+
+/// Whether `signature` (`sha256=` and hex) is the HMAC-SHA256 of the
+/// message id, timestamp and body under `secret`
+fn verify(secret: &str, id: &str, timestamp: &str, body: &[u8], signature: &str) -> bool {
+    let Some(tag) = signature.strip_prefix("sha256=").and_then(decode_hex) else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let message = [id.as_bytes(), timestamp.as_bytes(), body].concat();
+    hmac::verify(&key, &message, &tag).is_ok()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A connection that has to be done with by a deadline, however slowly the
+/// other end sends or reads
+struct Deadline {
+    tcp: TcpStream,
+    deadline: Instant,
+}
+
+impl Deadline {
+    fn new(tcp: TcpStream, timeout: Duration) -> Self {
+        Deadline {
+            tcp,
+            deadline: Instant::now() + timeout,
+        }
+    }
+
+    /// Time left until the deadline, or a timeout error once it has passed
+    fn remaining(&self) -> io::Result<Duration> {
+        self.deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "the client took too long"))
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.tcp.set_read_timeout(Some(self.remaining()?))?;
+        self.tcp.read(buf)
+    }
+}
+
+impl Write for Deadline {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tcp.set_write_timeout(Some(self.remaining()?))?;
+        self.tcp.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.tcp.flush()
+    }
+}
+
+/// The parts of an HTTP request the callback looks at
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    /// By lowercase name
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Reads a request, turning away those with more than [`MAX_HEAD`]
+    /// bytes before the body or more than [`MAX_HEADERS`] headers
+    fn read(connection: &mut impl Read) -> io::Result<Self> {
+        let too_large = |what| io::Error::new(io::ErrorKind::InvalidData, what);
+
+        let mut reader = BufReader::new(connection);
+        let mut head = (&mut reader).take(MAX_HEAD);
+        let mut line = String::new();
+        head.read_line(&mut line)?;
+        let mut request = Request {
+            method: line.split(' ').next().unwrap_or_default().to_string(),
+            ..Default::default()
+        };
+
+        for headers in 0.. {
+            line.clear();
+            if head.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if headers == MAX_HEADERS {
+                return Err(too_large("too many request headers"));
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                request
+                    .headers
+                    .insert(name.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+        if head.limit() == 0 {
+            return Err(too_large("request headers too large"));
+        }
+
+        let length = request
+            .headers
+            .get("content-length")
+            .and_then(|length| length.parse::<usize>().ok())
+            .unwrap_or(0);
+        if length > MAX_BODY {
+            return Err(too_large("request body too large"));
+        }
+        request.body = vec![0; length];
+        reader.read_exact(&mut request.body)?;
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef";
+
+    fn signed(kind: &str, id: &str, timestamp: &str, body: &str) -> Request {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET.as_bytes());
+        let tag = hmac::sign(&key, &[id, timestamp, body].concat().into_bytes());
+        let signature = tag
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        let headers = [
+            ("twitch-eventsub-message-id", id),
+            ("twitch-eventsub-message-timestamp", timestamp),
+            ("twitch-eventsub-message-type", kind),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .chain([(
+            "twitch-eventsub-message-signature".to_string(),
+            format!("sha256={signature}"),
+        )])
+        .collect();
+        Request {
+            method: "POST".to_string(),
+            headers,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn receiver() -> Receiver {
+        Receiver {
+            secret: SECRET.to_string(),
+            seen: Seen::default(),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2026-10-16T12:00:00Z".parse().unwrap()
+    }
+
+    const SENT: &str = "2026-10-16T11:59:30.123Z";
+
+    #[test]
+    fn answers_the_challenge() {
+        let request = signed(
+            "webhook_callback_verification",
+            "1",
+            SENT,
+            r#"{"challenge": "pogchamp-kappa-360noscope", "subscription": {}}"#,
+        );
+        let (status, body, _) = receiver().respond(&request, now());
+        assert_eq!(status, 200);
+        assert_eq!(body, "pogchamp-kappa-360noscope");
+    }
+
+    #[test]
+    fn notifications_once() {
+        let body = r#"{"event": {"broadcaster_user_login": "crab"}}"#;
+        let request = signed("notification", "2", SENT, body);
+        let mut receiver = receiver();
+        let (status, _, online) = receiver.respond(&request, now());
+        assert_eq!((status, online.as_deref()), (204, Some("crab")));
+        let (status, _, online) = receiver.respond(&request, now());
+        assert_eq!((status, online), (204, None));
+    }
+
+    #[test]
+    fn rejects_forged_and_old_messages() {
+        let body = r#"{"event": {"broadcaster_user_login": "crab"}}"#;
+        let mut forged = signed("notification", "3", SENT, body);
+        forged.body = br#"{"event": {"broadcaster_user_login": "crook"}}"#.to_vec();
+        assert_eq!(receiver().respond(&forged, now()).0, 403);
+
+        let old = signed("notification", "4", "2026-10-16T11:40:00Z", body);
+        assert_eq!(receiver().respond(&old, now()).0, 403);
+        let ahead = signed("notification", "5", "2026-10-16T12:20:00Z", body);
+        assert_eq!(receiver().respond(&ahead, now()).0, 403);
+
+        let unsigned = Request {
+            method: "POST".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(receiver().respond(&unsigned, now()).0, 404);
+    }
+
+    #[test]
+    fn websocket_sessions() {
+        let message = |id: &str, kind: &str, payload: &str| {
+            format!(
+                r#"{{"metadata": {{"message_id": "{id}", "message_type": "{kind}"}}, "payload": {payload}}}"#
+            )
+        };
+        let mut seen = Seen::default();
+        let welcome = message(
+            "1",
+            "session_welcome",
+            r#"{"session": {"id": "AQoQ", "keepalive_timeout_seconds": 10}}"#,
+        );
+        assert_eq!(
+            session_event(&welcome, &mut seen),
+            SessionEvent::Welcome {
+                id: "AQoQ".to_string(),
+                keepalive: Duration::from_secs(10)
+            }
+        );
+
+        let live = message(
+            "2",
+            "notification",
+            r#"{"event": {"broadcaster_user_login": "crab"}}"#,
+        );
+        assert_eq!(
+            session_event(&live, &mut seen),
+            SessionEvent::Live("crab".to_string())
+        );
+        assert_eq!(session_event(&live, &mut seen), SessionEvent::Nothing);
+
+        let reconnect = message(
+            "3",
+            "session_reconnect",
+            r#"{"session": {"reconnect_url": "wss://eventsub.wss.twitch.tv/ws?id=1"}}"#,
+        );
+        assert_eq!(
+            session_event(&reconnect, &mut seen),
+            SessionEvent::Reconnect("wss://eventsub.wss.twitch.tv/ws?id=1".to_string())
+        );
+        let keepalive = message("4", "session_keepalive", "{}");
+        assert_eq!(session_event(&keepalive, &mut seen), SessionEvent::Nothing);
+    }
+
+    #[test]
+    fn listening_needs_a_callback() {
+        use super::super::{Cli, Command};
+        use clap::Parser;
+
+        let parse =
+            |args: &[&str]| Cli::try_parse_from(["twitch-search", "eventsub"].iter().chain(args));
+        match parse(&["crab"]).unwrap().command {
+            Some(Command::Eventsub(args)) => assert_eq!(args.callback, None),
+            _ => panic!("not the eventsub subcommand"),
+        }
+        assert!(parse(&["--listen", "0.0.0.0:8080", "crab"]).is_err());
+        let callback = ["--callback", "https://example.com/eventsub"];
+        assert!(parse(&[&callback[..], &["--listen", "0.0.0.0:8080", "crab"]].concat()).is_ok());
+    }
+
+    #[test]
+    fn reads_requests() {
+        let raw = "POST /eventsub HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\nTwitch-Eventsub-Message-Id: 5\r\n\r\nbody";
+        let request = Request::read(&mut raw.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.headers["twitch-eventsub-message-id"], "5");
+        assert_eq!(request.body, b"body");
+    }
+
+    #[test]
+    fn turns_away_oversized_requests() {
+        let long_line = format!("POST /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD as usize));
+        let error = Request::read(&mut long_line.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let many_headers = format!(
+            "POST / HTTP/1.1\r\n{}\r\n",
+            "X-Header: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        let error = Request::read(&mut many_headers.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "too many request headers");
+    }
+
+    #[test]
+    fn a_silent_client_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (tcp, _) = listener.accept().unwrap();
+        let start = Instant::now();
+        let error = Request::read(&mut Deadline::new(tcp, Duration::from_millis(100))).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
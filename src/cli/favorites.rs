@@ -0,0 +1,218 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{AppError, TwitchClient};
+
+use super::history::data_dir;
+use super::load_config;
+
+/// Options of the `fav` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct FavArgs {
+    #[clap(subcommand)]
+    action: FavAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum FavAction {
+    /// Add channels to the favorites
+    Add {
+        /// Logins of the channels
+        #[clap(required = true)]
+        channels: Vec<String>,
+    },
+    /// Remove channels from the favorites
+    Remove {
+        /// Logins of the channels
+        #[clap(required = true)]
+        channels: Vec<String>,
+    },
+    /// List the favorites
+    List {
+        /// Only those live now, with their titles
+        #[clap(long)]
+        live: bool,
+    },
+    /// Write the favorites to a file, as JSON if it ends in .json and one
+    /// login per line otherwise
+    Export {
+        /// File to write
+        file: PathBuf,
+    },
+    /// Add the channels of a file written by `fav export`, or any list of
+    /// logins one per line or as a JSON array
+    Import {
+        /// File to read
+        file: PathBuf,
+    },
+}
+
+/// Runs the `fav` subcommand
+pub(super) fn fav(args: FavArgs) -> Result<(), AppError> {
+    let mut favorites = Favorites::load()?;
+    match args.action {
+        FavAction::Add { channels } => {
+            let added = favorites.merge(channels);
+            favorites.store()?;
+            println!("{added} favorite(s) added.");
+        }
+        FavAction::Remove { channels } => {
+            let before = favorites.channels.len();
+            for channel in channels {
+                favorites.channels.remove(&login(&channel));
+            }
+            favorites.store()?;
+            println!("{} favorite(s) removed.", before - favorites.channels.len());
+        }
+        FavAction::List { live: false } => {
+            for channel in &favorites.channels {
+                println!("{channel}");
+            }
+        }
+        FavAction::List { live: true } => {
+            load_config(None)?;
+            let client = TwitchClient::from_env(true)?;
+            let logins = favorites.channels.into_iter().collect::<Vec<_>>();
+            for stream in client.fetch_live_channels(&logins)? {
+                println!(
+                    "{} ({} viewers): {}",
+                    stream.user_login, stream.viewer_count, stream.title
+                );
+            }
+        }
+        FavAction::Export { file } => {
+            let json = file.extension().is_some_and(|ext| ext == "json");
+            let text = match json {
+                true => serde_json::to_string_pretty(&favorites).unwrap_or_default() + "\n",
+                false => favorites
+                    .channels
+                    .iter()
+                    .map(|c| format!("{c}\n"))
+                    .collect(),
+            };
+            fs::write(&file, text).map_err(|e| error(&file, e.to_string()))?;
+            println!("{} favorite(s) exported.", favorites.channels.len());
+        }
+        FavAction::Import { file } => {
+            let text = fs::read_to_string(&file).map_err(|e| error(&file, e.to_string()))?;
+            let channels = parse_list(&text).map_err(|e| error(&file, e))?;
+            let read = channels.len();
+            let added = favorites.merge(channels);
+            favorites.store()?;
+            println!("{added} new favorite(s) of {read} in the file.");
+        }
+    }
+    Ok(())
+}
+
+fn error(path: &Path, message: String) -> AppError {
+    AppError::Favorites(path.display().to_string(), message)
+}
+
+/// A channel as typed, `@Name` or `name`, as its login
+fn login(channel: &str) -> String {
+    channel.trim().trim_start_matches('@').to_lowercase()
+}
+
+/// The logins of an exported file: JSON like the favorites file or a JSON
+/// array, or else one per line, skipping blank lines and `#` comments
+fn parse_list(text: &str) -> Result<Vec<String>, String> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Favorites(Favorites),
+        Logins(Vec<String>),
+    }
+
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        return match serde_json::from_str(text).map_err(|e| e.to_string())? {
+            List::Favorites(favorites) => Ok(favorites.channels.into_iter().collect()),
+            List::Logins(logins) => Ok(logins),
+        };
+    }
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+pub(super) fn favorites_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("favorites.json"))
+}
+
+/// Channels kept to check on, in the data directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct Favorites {
+    channels: BTreeSet<String>,
+}
+
+impl Favorites {
+    fn load() -> Result<Self, AppError> {
+        let Some(path) = favorites_path() else {
+            return Ok(Favorites::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| error(&path, e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Favorites::default()),
+            Err(e) => Err(error(&path, e.to_string())),
+        }
+    }
+
+    fn store(&self) -> Result<(), AppError> {
+        let Some(path) = favorites_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| error(&path, e.to_string()))?;
+        }
+        let json = serde_json::to_string_pretty(self).unwrap_or_default();
+        fs::write(&path, json + "\n").map_err(|e| error(&path, e.to_string()))
+    }
+
+    /// Adds `channels`, returning how many were not favorites already
+    fn merge(&mut self, channels: impl IntoIterator<Item = String>) -> usize {
+        let before = self.channels.len();
+        self.channels.extend(
+            channels
+                .into_iter()
+                .map(|channel| login(&channel))
+                .filter(|login| !login.is_empty()),
+        );
+        self.channels.len() - before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_text_and_json() {
+        let text = "# team\nCrab\n\n@ferris\n";
+        assert_eq!(parse_list(text).unwrap(), ["Crab", "@ferris"]);
+        let json = r#"{"channels": ["crab"]}"#;
+        assert_eq!(parse_list(json).unwrap(), ["crab"]);
+        assert_eq!(parse_list(r#"["bot"]"#).unwrap(), ["bot"]);
+        assert!(parse_list("{").is_err());
+    }
+
+    #[test]
+    fn import_merges() {
+        let mut favorites = Favorites::default();
+        assert_eq!(favorites.merge(["crab".to_string()]), 1);
+        let imported = parse_list("Crab\n@ferris\n").unwrap();
+        assert_eq!(favorites.merge(imported), 1);
+        assert_eq!(
+            favorites.channels.iter().collect::<Vec<_>>(),
+            ["crab", "ferris"]
+        );
+    }
+}
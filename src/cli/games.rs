@@ -0,0 +1,55 @@
+use serde_json::json;
+
+use crate::config::Config;
+use crate::table::Table;
+use crate::AppError;
+
+use super::{load_config, parse_from_one, print_json, print_table, CommonArgs, OutputFormat};
+
+/// Options of the `games` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct GamesArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    /// Part of the category name, or nothing for the most watched categories
+    query: Vec<String>,
+
+    /// Fetch at most this many pages of 100 categories
+    #[clap(long, default_value_t = 1, value_parser = parse_from_one)]
+    max_pages: usize,
+}
+
+/// Prints the categories matching the query, or the most watched ones, with
+/// the ids `--game` accepts
+pub(super) fn games(mut args: GamesArgs) -> Result<bool, AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+
+    let client = args.common.client()?;
+    let games = match args.query.is_empty() {
+        true => client.top_games(args.max_pages)?,
+        false => client.search_games(&args.query.join(" "), args.max_pages)?,
+    };
+
+    let found = !games.is_empty();
+    let output = args.common.output();
+    if output == OutputFormat::Json {
+        let json = games
+            .iter()
+            .map(|game| json!({ "id": game.id, "name": game.name }))
+            .collect();
+        print_json(json, &args.common)?;
+        return Ok(found);
+    }
+
+    let mut table = Table::new(2);
+    table.set_headers(["id", "name"].map(str::to_string).to_vec());
+    for game in games {
+        table.push(vec![game.id, game.name]);
+    }
+    print_table(&table, &args.common)?;
+
+    Ok(found)
+}
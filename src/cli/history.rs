@@ -0,0 +1,170 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use serde_json::Value;
+
+use crate::AppError;
+
+/// Options of the `history` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct HistoryArgs {
+    #[clap(subcommand)]
+    action: HistoryAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum HistoryAction {
+    /// List previous searches, or only those containing any of the terms
+    Search {
+        /// Words to look for in the command lines
+        terms: Vec<String>,
+    },
+}
+
+/// Runs the `history` subcommand
+pub(super) fn history(args: HistoryArgs) -> Result<(), AppError> {
+    match args.action {
+        HistoryAction::Search { terms } => print_history(&terms),
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct HistoryEntry {
+    pub(super) time: String,
+    pub(super) args: Vec<String>,
+}
+
+impl HistoryEntry {
+    pub(super) fn command_line(&self) -> String {
+        self.args
+            .iter()
+            .map(|a| match a.contains(char::is_whitespace) {
+                true => format!("{a:?}"),
+                false => a.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Where twitch-search keeps what it remembers between runs
+pub(super) fn data_dir() -> Option<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+
+    Some(data_home.join("twitch-search"))
+}
+
+pub(super) fn history_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("history.jsonl"))
+}
+
+pub(super) fn record_history(args: &[String]) -> std::io::Result<()> {
+    let Some(path) = history_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let line = serde_json::json!({
+        "time": Utc::now().to_rfc3339(),
+        "args": args,
+    });
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")
+}
+
+pub(super) fn read_history() -> Result<Vec<HistoryEntry>, AppError> {
+    let Some(path) = history_path() else {
+        return Ok(vec![]);
+    };
+    match fs::File::open(path) {
+        Ok(file) => parse_history(BufReader::new(file)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(AppError::ReadHistory(e)),
+    }
+}
+
+fn parse_history(reader: impl BufRead) -> Result<Vec<HistoryEntry>, AppError> {
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(AppError::ReadHistory)?;
+        // Skip lines we can't make sense of rather than losing the whole history
+        let Ok(json) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let time = json.get("time").and_then(Value::as_str).unwrap_or_default();
+        let Some(args) = json.get("args").and_then(Value::as_array) else {
+            continue;
+        };
+        entries.push(HistoryEntry {
+            time: time.to_string(),
+            args: args
+                .iter()
+                .filter_map(|a| a.as_str().map(str::to_string))
+                .collect(),
+        });
+    }
+
+    Ok(entries)
+}
+
+fn print_history(terms: &[String]) -> Result<(), AppError> {
+    let terms = terms
+        .iter()
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect::<Vec<_>>();
+
+    for (i, entry) in read_history()?.iter().enumerate() {
+        let line = entry.command_line();
+
+        if !terms.is_empty() && !terms.iter().any(|t| line.to_lowercase().contains(t)) {
+            continue;
+        }
+
+        let time = match entry.time.parse::<DateTime<Utc>>() {
+            Ok(time) => time
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            Err(_) => entry.time.clone(),
+        };
+        println!("{:>5}  {time}  {line}", i + 1);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_lines() {
+        let history = concat!(
+            r#"{"time":"2026-10-16T12:00:00Z","args":["twitch-search","rust"]}"#,
+            "\n",
+            "not json\n",
+            r#"{"time":"2026-10-16T12:05:00Z"}"#,
+            "\n",
+            r#"{"args":["twitch-search","game dev","--all"]}"#,
+            "\n",
+        );
+        let entries = parse_history(history.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].time, "2026-10-16T12:00:00Z");
+        assert_eq!(entries[0].command_line(), "twitch-search rust");
+        assert_eq!(entries[1].time, "");
+        assert_eq!(
+            entries[1].command_line(),
+            r#"twitch-search "game dev" --all"#
+        );
+    }
+}
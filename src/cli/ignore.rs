@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::auth::token_user_id;
+use crate::{AppError, TwitchClient};
+
+use super::history::data_dir;
+use super::load_config;
+
+/// Options of the `ignore` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct IgnoreArgs {
+    #[clap(subcommand)]
+    action: IgnoreAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum IgnoreAction {
+    /// Replace the blocked channels in the ignore list with those blocked on
+    /// Twitch now (needs `login`)
+    Sync,
+    /// List the ignored channels
+    List,
+}
+
+/// Options of the `block` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct BlockArgs {
+    /// Login of the channel to block
+    channel: String,
+}
+
+/// Runs the `block` subcommand
+pub(super) fn block(args: BlockArgs) -> Result<(), AppError> {
+    load_config(None)?;
+    let client = TwitchClient::for_user()?;
+    let login = args.channel.trim_start_matches('@').to_lowercase();
+    let user_id = client.resolve_user(&login)?;
+    block_channel(&client, &user_id, &login)?;
+    println!("Blocked {login}.");
+    Ok(())
+}
+
+/// Blocks a channel on Twitch with the user token of `client`, and ignores
+/// it right away rather than from the next `ignore sync`
+pub(super) fn block_channel(
+    client: &TwitchClient,
+    user_id: &str,
+    login: &str,
+) -> Result<(), AppError> {
+    client.block_user(user_id)?;
+    let mut ignored = IgnoreList::load()?;
+    ignored.blocked.insert(login.to_string());
+    ignored.store()
+}
+
+/// Runs the `ignore` subcommand
+pub(super) fn ignore(args: IgnoreArgs) -> Result<(), AppError> {
+    match args.action {
+        IgnoreAction::Sync => {
+            load_config(None)?;
+            let client = TwitchClient::for_user()?;
+            let user_id = token_user_id(client.agent(), &client.access_token())?;
+            let blocked = client.blocked_users(&user_id)?;
+
+            let mut ignored = IgnoreList::load()?;
+            let before = ignored.blocked.len();
+            ignored.blocked = blocked.into_iter().map(|user| user.user_login).collect();
+            ignored.store()?;
+            println!(
+                "{} blocked channel(s) ignored, {} before.",
+                ignored.blocked.len(),
+                before
+            );
+        }
+        IgnoreAction::List => {
+            for login in &IgnoreList::load()?.blocked {
+                println!("{login} (blocked on Twitch)");
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn ignored_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("ignored.json"))
+}
+
+/// Channels left out of every search, on top of `--exclude` and
+/// `TWITCH_IGNORE`. Kept in the data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct IgnoreList {
+    /// Logins of the channels blocked on Twitch, as of the last `ignore sync`
+    blocked: BTreeSet<String>,
+}
+
+impl IgnoreList {
+    pub(super) fn load() -> Result<Self, AppError> {
+        let Some(path) = ignored_path() else {
+            return Ok(IgnoreList::default());
+        };
+        let error = |e: String| AppError::IgnoreList(path.display().to_string(), e);
+        match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| error(e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(IgnoreList::default()),
+            Err(e) => Err(error(e.to_string())),
+        }
+    }
+
+    fn store(&self) -> Result<(), AppError> {
+        let Some(path) = ignored_path() else {
+            return Ok(());
+        };
+        let error = |e: io::Error| AppError::IgnoreList(path.display().to_string(), e.to_string());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(error)?;
+        }
+        let json = serde_json::to_string_pretty(self).unwrap_or_default();
+        fs::write(&path, json + "\n").map_err(error)
+    }
+
+    /// Logins of the ignored channels
+    pub(super) fn channels(&self) -> impl Iterator<Item = &str> {
+        self.blocked.iter().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_file() {
+        let ignored =
+            serde_json::from_str::<IgnoreList>(r#"{"blocked": ["crab", "bot"]}"#).unwrap();
+        assert_eq!(ignored.channels().collect::<Vec<_>>(), ["bot", "crab"]);
+        assert!(serde_json::from_str::<IgnoreList>("{}").is_ok());
+    }
+}
@@ -0,0 +1,2036 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use chrono::prelude::*;
+use clap::{Parser, Subcommand, ValueEnum};
+use ratatui::crossterm::terminal;
+use serde_json::Value;
+
+use crate::api::{
+    configure_agent, load_streams, set_retry_policy, set_throttle, FetchOptions, FetchStats,
+    HydrateOptions, Progress, RetryPolicy, DEFAULT_GAME_IDS, MAX_BACKOFF, MIN_THROTTLE,
+};
+use crate::auth::{load_credentials, login, logout, token_user_id};
+use crate::config::{Blocklist, Config, Profile, Sheets, Theme};
+use crate::error::EXIT_NO_MATCHES;
+use crate::filter::{
+    detect_duplicate_titles, exclusions, DedupeTitles, MatchField, SearchFilter, Terms,
+    TitleBlocklist, TitleFilter, ViewerLimits,
+};
+use crate::languages::resolve_language;
+use crate::matcher::fold;
+use crate::player::{self, DEFAULT_PLAYER, DEFAULT_QUALITY};
+#[cfg(feature = "sqlite")]
+use crate::sessions::SessionDb;
+use crate::table::{
+    group_entries, sort_entries, write_rss, ColorChoice, Column, Columns, ExtraColumn, GroupBy,
+    SortKey, Table, Wrap,
+};
+use crate::ui::{self, Styles};
+use crate::{json_schema, AppError, Change, StreamEntry, TwitchClient};
+
+use self::backup::{backup, restore, BackupArgs, RestoreArgs};
+use self::broadcaster::{analytics, bits, subs, AnalyticsArgs, BitsArgs, SubsArgs};
+use self::cache::{cache, CacheArgs};
+use self::channels::{channels, ChannelsArgs};
+use self::clips::{clip, clip_channel, clips, ClipArgs, ClipsArgs};
+use self::config::{config, ConfigArgs};
+use self::doctor::doctor;
+use self::eventsub::{eventsub, EventSubArgs};
+use self::favorites::{fav, FavArgs};
+use self::games::{games, GamesArgs};
+use self::history::{history, read_history, record_history, HistoryArgs};
+use self::ignore::{block, block_channel, ignore, BlockArgs, IgnoreArgs, IgnoreList};
+use self::play::{play, PlayArgs};
+use self::quiet::QuietHours;
+use self::repl::{repl, ReplState};
+use self::report::{
+    append_csv_log, print_histogram, print_percentiles, RunReport, Summary, Timings,
+};
+use self::runs::{diff, log_run, previous_run, LoggedRun};
+#[cfg(feature = "sqlite")]
+use self::sessions::{sessions, SessionsArgs};
+use self::sheets::{append, check_key, sheet_url};
+use self::systemd::{install_systemd, strip_install_options, Schedule};
+use self::template::Messages;
+use self::videos::{videos, VideosArgs};
+use self::watch::watch;
+use self::webhook::{announce, Announced, WebhookFormat};
+
+mod backup;
+mod broadcaster;
+mod cache;
+mod channels;
+mod clips;
+mod config;
+mod digest;
+mod doctor;
+mod eventsub;
+mod favorites;
+mod games;
+mod history;
+mod ignore;
+mod play;
+mod quiet;
+mod repl;
+mod report;
+mod runs;
+#[cfg(feature = "sqlite")]
+mod sessions;
+mod sheets;
+mod systemd;
+mod template;
+mod videos;
+mod watch;
+mod webhook;
+
+// -----------------------------------------------------------------------------
+//     - Command line arguments -
+// -----------------------------------------------------------------------------
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Without a subcommand, live streams are searched
+    #[clap(flatten)]
+    streams: StreamsArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Search the titles of live streams (the default)
+    Streams(Box<StreamsArgs>),
+    /// Search channels by name or title, live or not
+    Channels(ChannelsArgs),
+    /// Search categories by name, or list the most watched ones
+    Games(GamesArgs),
+    /// Search the titles of past broadcasts and other videos
+    Videos(VideosArgs),
+    /// Search the titles of clips from a time window
+    Clips(ClipsArgs),
+    /// Clip the last seconds of a live channel and print where to edit it
+    /// (needs `login`)
+    Clip(ClipArgs),
+    /// Show the top cheerers of your channel (needs `login`)
+    Bits(BitsArgs),
+    /// Show the subscriber count and points of your channel, or list its
+    /// subscribers (needs `login`)
+    Subs(SubsArgs),
+    /// Show where to download the analytics reports of your games, or
+    /// download them (needs `login`)
+    Analytics(AnalyticsArgs),
+    /// Watch a live channel, or the top live match of a search, in a player
+    Play(PlayArgs),
+    /// Look through the searches run before (see also --last)
+    History(HistoryArgs),
+    /// List past stream sessions recorded with --db
+    #[cfg(feature = "sqlite")]
+    Sessions(SessionsArgs),
+    /// Sign in as a Twitch user, for --followed
+    Login,
+    /// Forget and revoke the token stored by login
+    Logout,
+    /// Keep channels blocked on Twitch out of every search
+    Ignore(IgnoreArgs),
+    /// Block a channel on Twitch and leave it out of every search
+    Block(BlockArgs),
+    /// Keep a list of favorite channels, to share with `fav export`/`fav import`
+    Fav(FavArgs),
+    /// Check credentials, network access and clock for common problems
+    Doctor,
+    /// Check the config file
+    Config(ConfigArgs),
+    /// Inspect or clear the cached access token and user metadata
+    Cache(CacheArgs),
+    /// Save the config, ignore list, favorites, history and cached channel
+    /// metadata (not tokens) to one file, for moving to another machine
+    Backup(BackupArgs),
+    /// Put back the files saved by backup
+    Restore(RestoreArgs),
+    /// Hear about channels going live from Twitch over EventSub
+    Eventsub(EventSubArgs),
+    /// Run a search like `streams`, or install it as a user systemd service
+    /// with --install-systemd
+    Daemon(Box<DaemonArgs>),
+}
+
+// Options shared by the subcommands that talk to Helix
+#[derive(clap::Args, Debug)]
+struct CommonArgs {
+    /// Only show these languages (en,fr,... or English,French,...; can be repeated)
+    #[clap(
+        short = 'l',
+        long,
+        multiple_occurrences = true,
+        use_value_delimiter = true
+    )]
+    lang: Vec<String>,
+
+    /// How to print the results [default: table]
+    #[clap(short, long, value_enum)]
+    output: Option<OutputFormat>,
+
+    /// Read defaults from this file instead of ~/.config/twitch-search/config.toml
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Send at most this many API requests per second, or any number with 0
+    #[clap(long, value_name = "REQ/S", value_parser = parse_throttle)]
+    throttle: Option<f64>,
+
+    /// Always request a new access token and user metadata instead of reusing cached ones
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Send a request again this many times after a network error or 5xx response
+    #[clap(long, value_name = "N", default_value_t = 3)]
+    retries: u32,
+
+    /// Seconds to wait before the first retry, doubled for each one after up to a minute
+    #[clap(long, value_name = "SECONDS", default_value_t = 0.5, value_parser = parse_retry_delay)]
+    retry_delay: f64,
+
+    /// Fit tables to this many columns instead of the terminal width
+    #[clap(long, value_name = "N")]
+    width: Option<usize>,
+
+    /// How to fit a last column that is too wide for the terminal
+    #[clap(long, value_enum, default_value = "truncate", value_name = "MODE")]
+    wrap: Wrap,
+
+    /// Write the results to this file instead of stdout
+    #[clap(long, value_name = "FILE")]
+    out_file: Option<PathBuf>,
+}
+
+// Search terms of the subcommands that match titles
+#[derive(clap::Args, Debug)]
+struct TermArgs {
+    /// Term to search for
+    #[clap(default_value = "")]
+    term: Vec<String>,
+
+    /// Require matching all words, instead of just any
+    #[clap(short, long)]
+    all: bool,
+
+    /// Only match whole words, so "rust" skips "rusty" while "f1" and "c++" still match
+    #[clap(short, long)]
+    word: bool,
+
+    /// Skip results whose title contains this (can be repeated)
+    #[clap(
+        long,
+        value_name = "TERM",
+        multiple_occurrences = true,
+        number_of_values = 1
+    )]
+    not: Vec<String>,
+
+    /// Match the terms as case-insensitive regular expressions
+    #[clap(long)]
+    regex: bool,
+
+    /// The `[blocklist]` of the config file
+    #[clap(skip)]
+    blocklist: Blocklist,
+
+    /// Also match misspelled, abbreviated or split terms, best matches first
+    #[clap(long, conflicts_with_all = &["regex", "word"])]
+    fuzzy: bool,
+
+    /// Ignore accents and compatibility forms, so "Rúst" and "ＲＵＳＴ" match "rust"
+    #[clap(
+        long,
+        value_name = "BOOL",
+        action = clap::ArgAction::Set,
+        default_value_t = true,
+        min_values = 0,
+        max_values = 1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    normalize: bool,
+}
+
+// Options of the `streams` subcommand
+#[derive(clap::Args, Debug)]
+struct StreamsArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    #[clap(flatten)]
+    terms: TermArgs,
+
+    /// Game or category to search, by name or id (can be repeated)
+    #[clap(short, long, multiple_occurrences = true, number_of_values = 1)]
+    game: Vec<String>,
+
+    /// Search the live channels you follow instead of a category (needs
+    /// `twitch-search login` or a user access token in TWITCH_USER_TOKEN)
+    #[clap(long, conflicts_with_all = &["game", "from-file"])]
+    followed: bool,
+
+    /// Streamers to exclude
+    #[clap(short = 'x', long)]
+    exclude: Option<Vec<String>>,
+
+    /// Only show streams with this tag (can be repeated, all must match)
+    #[clap(long, multiple_occurrences = true, number_of_values = 1)]
+    tag: Vec<String>,
+
+    /// Add a column with the stream tags
+    #[clap(long)]
+    show_tags: bool,
+
+    /// Add a column with the search terms each stream matched
+    #[clap(long)]
+    show_matched: bool,
+
+    /// Show full language names instead of codes
+    #[clap(long)]
+    lang_names: bool,
+
+    /// Highlight matches, link channels and dim small streams
+    #[clap(long, value_enum, default_value = "auto", value_name = "WHEN")]
+    color: ColorChoice,
+
+    /// Where to look for the terms
+    #[clap(
+        long = "in",
+        value_enum,
+        value_name = "FIELDS",
+        use_value_delimiter = true,
+        default_value = "title"
+    )]
+    fields: Vec<MatchField>,
+
+    /// Keep running, searching again every this many seconds and printing the
+    /// streams that started or stopped matching
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        conflicts_with_all = &["repl", "tui", "output", "out-file"]
+    )]
+    watch: Option<u64>,
+
+    /// Raise a desktop notification for every stream that starts matching
+    #[clap(long, requires = "watch")]
+    notify: bool,
+
+    /// Gather the streams that start matching for this many minutes, then
+    /// tell about them in one notification and webhook message
+    #[clap(long, value_name = "MINUTES", requires = "watch")]
+    digest: Option<u64>,
+
+    /// Post the matching streams to this webhook, each broadcast once, also
+    /// across runs. With --diff, only the NEW ones
+    #[clap(long, value_name = "URL", conflicts_with_all = &["tui", "repl"])]
+    webhook_url: Option<String>,
+
+    /// Message format of --webhook-url, by default guessed from its address
+    #[clap(long, value_enum, value_name = "FORMAT", requires = "webhook-url")]
+    webhook_format: Option<WebhookFormat>,
+
+    /// Append the matching streams to the Google Sheet of the config's
+    /// [sheets] table, each broadcast once, also across runs
+    #[clap(long, conflicts_with_all = &["tui", "repl"])]
+    sheet: bool,
+
+    #[clap(skip)]
+    sheets: Option<Sheets>,
+
+    #[clap(skip)]
+    messages: Messages,
+
+    #[clap(skip)]
+    quiet_hours: Option<QuietHours>,
+
+    /// Browse the results in a full-screen terminal UI
+    #[clap(long, conflicts_with_all = &["repl", "output", "out-file"])]
+    tui: bool,
+
+    /// Refresh --tui every this many seconds, marking new streams and
+    /// changes in viewers
+    #[clap(long, value_name = "SECONDS", requires = "tui")]
+    refresh: Option<u64>,
+
+    /// The `[theme]` of the config file, for --tui
+    #[clap(skip)]
+    theme: Theme,
+
+    /// Fetch once, then refine the filters interactively
+    #[clap(long, conflicts_with = "out-file")]
+    repl: bool,
+
+    /// Save the unfiltered API responses to this file
+    #[clap(long, value_name = "FILE")]
+    save_raw: Option<PathBuf>,
+
+    /// Search responses saved with --save-raw instead of fetching
+    #[clap(long, value_name = "FILE", conflicts_with = "save-raw")]
+    from_file: Option<PathBuf>,
+
+    /// Collapse or drop streams whose title (or one nearly like it) is shared
+    /// by other channels
+    #[clap(long, value_enum, value_name = "MODE")]
+    dedupe_titles: Option<DedupeTitles>,
+
+    /// Sort the results by this key
+    #[clap(long, value_enum, value_name = "KEY")]
+    sort: Option<SortKey>,
+
+    /// Sort largest first (the default for viewers and uptime)
+    #[clap(long, requires = "sort", conflicts_with = "asc")]
+    desc: bool,
+
+    /// Sort smallest first (the default for name and lang)
+    #[clap(long, requires = "sort")]
+    asc: bool,
+
+    /// Group the results under headings with their stream and viewer counts,
+    /// the most watched group first
+    #[clap(
+        long,
+        value_enum,
+        value_name = "KEY",
+        conflicts_with_all = &["watch", "tui", "repl"]
+    )]
+    group_by: Option<GroupBy>,
+
+    /// Run the search saved as [search.<NAME>] in the config file, adding
+    /// the terms and filters given here
+    #[clap(short, long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// List the searches saved in the config file
+    #[clap(long)]
+    list_profiles: bool,
+
+    /// Print the JSON Schema of `--output json` and exit
+    #[clap(long)]
+    schema: bool,
+
+    /// Extra columns to show (fetched for matching channels only)
+    #[clap(
+        long,
+        value_enum,
+        multiple_occurrences = true,
+        use_value_delimiter = true
+    )]
+    extra: Vec<ExtraColumn>,
+
+    /// Columns to show and their order, replacing the default layout
+    #[clap(
+        long,
+        value_enum,
+        use_value_delimiter = true,
+        conflicts_with = "extra",
+        value_name = "COLUMN,..."
+    )]
+    columns: Option<Vec<Column>>,
+
+    /// Hide streams with fewer viewers than this (also stops fetching early)
+    #[clap(long, value_name = "N")]
+    min_viewers: Option<i64>,
+
+    /// Hide streams with more viewers than this
+    #[clap(long, value_name = "N")]
+    max_viewers: Option<i64>,
+
+    /// Skip streams whose title is longer than this many characters
+    #[clap(long, value_name = "N")]
+    max_title_len: Option<usize>,
+
+    /// Cut titles longer than this many columns, ending them with …
+    #[clap(long, value_name = "N")]
+    max_title_width: Option<usize>,
+
+    /// Open the first matching stream, or the Nth as listed (--open=N), in the browser
+    #[clap(
+        long,
+        value_name = "N",
+        min_values = 0,
+        max_values = 1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = parse_from_one,
+        conflicts_with_all = &["play", "watch", "tui", "repl"]
+    )]
+    open: Option<usize>,
+
+    /// Watch the first matching stream, or the Nth as listed (--play=N), with --player
+    #[clap(
+        long,
+        value_name = "N",
+        min_values = 0,
+        max_values = 1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = parse_from_one,
+        conflicts_with_all = &["watch", "tui", "repl"]
+    )]
+    play: Option<usize>,
+
+    /// Player command for --play, where {url} is the stream [default: streamlink {url} {quality}]
+    #[clap(long, value_name = "COMMAND")]
+    player: Option<String>,
+
+    /// Hide channels whose account is younger than this (e.g. 30d, 12h, 2w)
+    #[clap(long, value_name = "AGE", value_parser = parse_age)]
+    min_account_age: Option<chrono::Duration>,
+
+    /// Append the matches with a timestamp to this CSV file
+    #[clap(long, value_name = "FILE")]
+    log_csv: Option<PathBuf>,
+
+    /// Log the matches of this run in the data directory, for --diff
+    #[clap(long)]
+    log_history: bool,
+
+    /// Record every stream seen in this SQLite database, for the sessions subcommand
+    #[cfg(feature = "sqlite")]
+    #[clap(long, value_name = "PATH", conflicts_with_all = &["watch", "tui", "repl"])]
+    db: Option<PathBuf>,
+
+    /// Compare with the last logged run of the same search: mark streams NEW
+    /// or with their change in viewers, and list the ones GONE
+    #[clap(long, conflicts_with_all = &["watch", "tui", "repl"])]
+    diff: bool,
+
+    /// Summarize the matches on stderr: viewers, languages, longest live, top
+    /// tags and viewer and uptime percentiles
+    #[clap(long)]
+    stats: bool,
+
+    /// Only print the --stats summary, without the results, even with --quiet
+    #[clap(long, conflicts_with_all = &["watch", "tui", "repl"])]
+    stats_only: bool,
+
+    /// Show how the viewer counts of the matches are distributed, on stderr
+    #[clap(long)]
+    histogram: bool,
+
+    /// Hide channels with more than this many viewers per follower. Follower
+    /// counts need a user token from `login`
+    #[clap(long, value_name = "RATIO")]
+    max_viewer_ratio: Option<f64>,
+
+    /// Write a JSON report about the run (counts, rate limit, errors) to this file
+    #[clap(long, value_name = "PATH")]
+    report_file: Option<PathBuf>,
+
+    /// Report how long each phase of the run took
+    #[clap(long)]
+    timings: bool,
+
+    /// Don't print progress and summaries to stderr
+    #[clap(short, long)]
+    quiet: bool,
+
+    /// How to show progress while fetching
+    #[clap(long, value_enum, default_value = "dots", value_name = "STYLE")]
+    progress: Progress,
+
+    /// Stop after this many pages per game, e.g. when only the biggest
+    /// streams matter
+    #[clap(long, value_name = "N", default_value_t = 500, value_parser = parse_from_one)]
+    max_pages: usize,
+
+    /// Stop after this many matches, most watched first
+    #[clap(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Show the results fetched so far if a later page fails
+    #[clap(long)]
+    keep_partial: bool,
+
+    /// Scan up to this many games at the same time
+    #[clap(long, value_name = "N", default_value_t = 1)]
+    concurrency: usize,
+
+    /// Re-run the previous search
+    #[clap(long)]
+    last: bool,
+}
+
+// Options of the `daemon` subcommand
+#[derive(clap::Args, Debug)]
+struct DaemonArgs {
+    /// Write user systemd units running this search as twitch-search-NAME
+    /// instead of running it, kept going with --watch or run by a timer
+    /// with --systemd-timer
+    #[clap(long, value_name = "NAME", conflicts_with_all = &["tui", "repl", "last"])]
+    install_systemd: Option<String>,
+
+    /// With --install-systemd, run the search every this many minutes
+    #[clap(
+        long,
+        value_name = "MINUTES",
+        requires = "install-systemd",
+        conflicts_with = "watch",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    systemd_timer: Option<u64>,
+
+    #[clap(flatten)]
+    search: StreamsArgs,
+}
+
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+enum OutputFormat {
+    /// Aligned columns with progress and a summary
+    Table,
+    /// JSON array of the matched streams, nothing else
+    Json,
+    /// Comma separated values with a header row
+    Csv,
+    /// Tab separated values with a header row
+    Tsv,
+    /// Standalone HTML page with links, thumbnails and a sortable table
+    Html,
+    /// RSS feed of the matched streams, for feed readers (stream searches only)
+    Rss,
+}
+
+/// A `--throttle` rate, see [`check_throttle`]
+fn parse_throttle(s: &str) -> Result<f64, String> {
+    let rate = s.parse::<f64>().map_err(|e| e.to_string())?;
+    check_throttle(rate).map(|()| rate)
+}
+
+/// Requests per second are 0 for no limit, or a number from [`MIN_THROTTLE`]
+/// up. Slower ones would wait for days between requests.
+fn check_throttle(rate: f64) -> Result<(), String> {
+    match rate == 0.0 || (rate.is_finite() && rate >= MIN_THROTTLE) {
+        true => Ok(()),
+        false => Err(format!(
+            "expected 0 for no limit or a number from {MIN_THROTTLE} up"
+        )),
+    }
+}
+
+/// A `--retry-delay` from 0 up to [`MAX_BACKOFF`]
+fn parse_retry_delay(s: &str) -> Result<f64, String> {
+    let max = MAX_BACKOFF.as_secs_f64();
+    match s.parse::<f64>() {
+        Ok(delay) if (0.0..=max).contains(&delay) => Ok(delay),
+        _ => Err(format!("expected a number of seconds from 0 to {max}")),
+    }
+}
+
+/// Parses an age like `30d`: a number followed by `h`, `d`, `w` or `y`
+fn parse_age(s: &str) -> Result<chrono::Duration, String> {
+    let (count, unit) = s.split_at(s.trim_end_matches(char::is_alphabetic).len());
+    let count = count
+        .parse::<i64>()
+        .ok()
+        .filter(|&count| count >= 0)
+        .ok_or_else(|| format!("expected a number followed by h, d, w or y, got {s:?}"))?;
+
+    let age = match unit {
+        "h" => chrono::Duration::try_hours(count),
+        "d" | "" => chrono::Duration::try_days(count),
+        "w" => chrono::Duration::try_weeks(count),
+        "y" => count.checked_mul(365).and_then(chrono::Duration::try_days),
+        _ => return Err(format!("unknown unit {unit:?}, expected h, d, w or y")),
+    };
+    age.ok_or_else(|| format!("{s} is too long ago"))
+}
+
+/// Keeps the entries with at most `max_ratio` viewers per follower, and
+/// those without a follower count, returning how many of them there were
+fn retain_viewer_ratios(entries: &mut Vec<StreamEntry>, max_ratio: f64) -> usize {
+    let mut unknown = 0;
+    entries.retain(|entry| {
+        let ratio = entry
+            .user
+            .as_ref()
+            .and_then(|u| u.viewer_ratio(entry.viewer_count));
+        unknown += usize::from(ratio.is_none());
+        ratio.is_none_or(|ratio| ratio <= max_ratio)
+    });
+    unknown
+}
+
+/// Keeps the entries of accounts at least `min_age` old at `now`, and those
+/// whose age is unknown
+fn retain_old_accounts(
+    entries: &mut Vec<StreamEntry>,
+    min_age: chrono::Duration,
+    now: DateTime<Utc>,
+) {
+    // An age from before the calendar leaves no account old enough
+    let cutoff = now
+        .checked_sub_signed(min_age)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    entries.retain(|entry| {
+        let created_at = entry.user.as_ref().and_then(|u| u.created_at);
+        created_at.is_none_or(|created_at| created_at <= cutoff)
+    });
+}
+
+/// A number from 1 up, like a 1-based position in the list of matches or
+/// `--max-pages`
+pub(super) fn parse_from_one(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("expected a number from 1 up, got {s:?}")),
+    }
+}
+
+/// The URL of the `n`th of `matched`, counting from 1, for `--open` and `--play`
+fn pick(matched: &[StreamEntry], n: usize) -> Result<String, AppError> {
+    matched
+        .get(n - 1)
+        .map(StreamEntry::url)
+        .ok_or(AppError::NoSuchMatch(n, matched.len()))
+}
+
+/// The matches worth posting or adding: all of them, less those that only
+/// changed in viewers since the run compared with (`--diff`)
+fn fresh_matches(matched: &[StreamEntry]) -> Vec<StreamEntry> {
+    matched
+        .iter()
+        .filter(|entry| !matches!(entry.change, Some(Change::Viewers(_))))
+        .cloned()
+        .collect()
+}
+
+/// Loads the config file at `path` (or the default one) and the credentials it names
+fn load_config(path: Option<&Path>) -> Result<Config, AppError> {
+    let config = match path {
+        Some(path) => Config::load(path)?,
+        None => Config::load_default()?,
+    };
+    if let Some(credentials) = &config.credentials {
+        load_credentials(credentials)?;
+    }
+    Ok(config)
+}
+
+/// What is wrong in `config` beyond its syntax and keys, which
+/// [`Config::load`] already checks: values the options they stand in for
+/// would reject, by the key they are under
+fn config_problems(config: &Config) -> Vec<(String, String)> {
+    let mut problems = Vec::new();
+    if let Some(output) = &config.output {
+        if let Err(e) = OutputFormat::from_str(output, true) {
+            problems.push(("output".to_string(), e));
+        }
+    }
+    if config
+        .player
+        .as_deref()
+        .is_some_and(|p| p.trim().is_empty())
+    {
+        problems.push(("player".to_string(), "the command is empty".to_string()));
+    }
+    if let Some(Err(e)) = config.throttle.map(check_throttle) {
+        problems.push(("throttle".to_string(), e));
+    }
+    if let Err(AppError::InvalidStyle(key, e)) = Styles::new(&config.theme) {
+        problems.push((key, e));
+    }
+    if let Err(AppError::InvalidTemplate(key, e)) = Messages::new(&config.templates) {
+        problems.push((key, e));
+    }
+    if let Some(Err(AppError::InvalidQuiet(key, e))) = config.quiet.as_ref().map(QuietHours::new) {
+        problems.push((key, e));
+    }
+    if let Some(Err(e)) = config.sheets.as_ref().map(check_key) {
+        problems.push(("sheets.key".to_string(), e.to_string()));
+    }
+    if let Err(e) = Terms::regex(&config.blocklist.patterns, true) {
+        problems.push(("blocklist.patterns".to_string(), e.to_string()));
+    }
+
+    let mut langs = config
+        .lang
+        .iter()
+        .map(|lang| ("lang".to_string(), lang))
+        .collect::<Vec<_>>();
+    for (name, profile) in &config.search {
+        langs.extend(
+            profile
+                .lang
+                .iter()
+                .map(|lang| (format!("search.{name}.lang"), lang)),
+        );
+    }
+    for (key, lang) in langs
+        .into_iter()
+        .filter(|(_, lang)| !lang.trim().is_empty())
+    {
+        if let Err(e) = resolve_language(lang) {
+            problems.push((key, e.to_string()));
+        }
+    }
+    problems
+}
+
+impl CommonArgs {
+    /// Fills in the options not given on the command line from the config
+    /// file at `path`, and applies `--throttle` (or its `throttle` key)
+    fn setup(&mut self, config: &Config, path: Option<&Path>) -> Result<(), AppError> {
+        if self.lang.is_empty() {
+            self.lang = config.lang.clone();
+        }
+        if self.output.is_none() {
+            let output = config
+                .output
+                .as_deref()
+                .map(|output| OutputFormat::from_str(output, true))
+                .transpose()
+                .map_err(|e| {
+                    let path = path.map(|p| p.display().to_string()).unwrap_or_default();
+                    AppError::ReadConfig(path, format!("output: {e}"))
+                })?;
+            self.output = Some(output.unwrap_or(OutputFormat::Table));
+        }
+        if self.throttle.is_none() {
+            self.throttle = config.throttle;
+        }
+
+        if let Some(rate) = self.throttle.filter(|rate| *rate != 0.0) {
+            set_throttle(rate)?;
+        }
+        set_retry_policy(RetryPolicy {
+            retries: self.retries,
+            delay: Duration::from_secs_f64(self.retry_delay),
+        });
+        Ok(())
+    }
+
+    fn output(&self) -> OutputFormat {
+        self.output.unwrap_or(OutputFormat::Table)
+    }
+
+    /// Language codes of `--lang`, which also takes names
+    fn languages(&self) -> Result<Vec<String>, AppError> {
+        self.lang
+            .iter()
+            .map(|lang| resolve_language(lang))
+            .collect()
+    }
+
+    fn client(&self) -> Result<TwitchClient, AppError> {
+        Ok(TwitchClient::from_env(!self.no_cache)?)
+    }
+
+    /// Whether the results end up on the terminal
+    fn to_terminal(&self) -> bool {
+        self.out_file.is_none() && io::stdout().is_terminal()
+    }
+
+    /// `--width`, or that of the terminal when printing to one
+    fn screen_width(&self) -> Option<usize> {
+        self.width.or_else(|| {
+            self.to_terminal()
+                .then(terminal::size)
+                .and_then(Result::ok)
+                .map(|(columns, _)| columns as usize)
+        })
+    }
+
+    fn print(&self, table: &Table) {
+        table.print_to_width(self.screen_width(), self.wrap);
+    }
+
+    /// Whether to style the results, which don't go to the terminal with
+    /// `--out-file` unless asked to
+    fn color(&self, color: ColorChoice) -> bool {
+        match self.out_file {
+            Some(_) => color == ColorChoice::Always,
+            None => color.enabled(),
+        }
+    }
+
+    /// Runs `write` on `--out-file`, or on stdout without one
+    fn write_output(
+        &self,
+        write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> Result<(), AppError> {
+        match &self.out_file {
+            Some(path) => {
+                let error = |e| AppError::WriteOutput(path.display().to_string(), e);
+                let mut file = BufWriter::new(File::create(path).map_err(error)?);
+                write(&mut file).and_then(|()| file.flush()).map_err(error)
+            }
+            None => write(&mut io::stdout().lock())
+                .map_err(|e| AppError::WriteOutput("stdout".to_string(), e)),
+        }
+    }
+}
+
+impl TermArgs {
+    fn terms(&self) -> Result<Terms, AppError> {
+        let terms = match (self.regex, self.fuzzy) {
+            (true, _) => Terms::regex(&self.term, self.normalize)?,
+            (_, true) => Terms::fuzzy(&self.term, self.normalize),
+            _ => Terms::plain(&self.term, self.normalize),
+        };
+        Ok(Terms {
+            whole_word: self.word,
+            ..terms
+        })
+    }
+
+    fn not(&self) -> Vec<String> {
+        self.not.iter().map(|t| fold(t, self.normalize)).collect()
+    }
+
+    fn blocklist(&self) -> Result<TitleBlocklist, AppError> {
+        let Blocklist { titles, patterns } = &self.blocklist;
+        TitleBlocklist::new(titles, patterns, self.normalize)
+    }
+
+    fn title_filter(&self) -> Result<TitleFilter, AppError> {
+        Ok(TitleFilter {
+            terms: self.terms()?,
+            not: self.not(),
+            blocklist: self.blocklist()?,
+            all: self.all,
+        })
+    }
+}
+
+/// Fills in the terms each entry matched, if there is a column for them
+fn note_matched_terms(entries: &mut [StreamEntry], filter: &SearchFilter, columns: &[Column]) {
+    if columns.contains(&Column::Matched) {
+        for entry in entries {
+            entry.matched_terms = filter.matched_terms(entry);
+        }
+    }
+}
+
+/// Prints `table` in any `--output` format but JSON, which the caller
+/// handles with [`print_json`], and RSS, which only searches have
+fn print_table(table: &Table, common: &CommonArgs) -> Result<(), AppError> {
+    if common.output() == OutputFormat::Rss {
+        return Err(AppError::NoFeed);
+    }
+    common.write_output(|out| match common.output() {
+        OutputFormat::Table => table.write_to_width(out, common.screen_width(), common.wrap),
+        OutputFormat::Csv => table.write_csv(out),
+        OutputFormat::Tsv => table.write_tsv(out),
+        OutputFormat::Html => table.write_html(out),
+        OutputFormat::Json | OutputFormat::Rss => unreachable!("handled by the caller"),
+    })
+}
+
+fn print_json(json: Vec<Value>, common: &CommonArgs) -> Result<(), AppError> {
+    common.write_output(|out| writeln!(out, "{:#}", Value::Array(json)))
+}
+
+/// Fills in the stream options not given on the command line from the
+/// saved search of `--profile` and the config file
+fn apply_config(
+    args: &mut StreamsArgs,
+    mut config: Config,
+    path: Option<&Path>,
+) -> Result<(), AppError> {
+    if let Some(name) = &args.profile {
+        let profile = config
+            .search
+            .remove(name)
+            .ok_or_else(|| AppError::UnknownProfile(name.clone()))?;
+        apply_profile(args, profile);
+    }
+
+    args.common.setup(&config, path)?;
+    args.sheets = config.sheets;
+    args.messages = Messages::new(&config.templates)?;
+    args.quiet_hours = config.quiet.as_ref().map(QuietHours::new).transpose()?;
+    args.theme = config.theme;
+    args.terms.blocklist = config.blocklist;
+    if args.exclude.is_none() && !config.exclude.is_empty() {
+        args.exclude = Some(config.exclude);
+    }
+    if args.game.is_empty() {
+        args.game = config.games;
+    }
+    if args.player.is_none() {
+        args.player = config.player;
+    }
+    Ok(())
+}
+
+fn apply_profile(args: &mut StreamsArgs, profile: Profile) {
+    // Without terms of its own, the command line holds just the empty default
+    let terms = &mut args.terms.term;
+    terms.retain(|term| !term.is_empty());
+    terms.splice(0..0, profile.terms);
+    if terms.is_empty() {
+        terms.push(String::new());
+    }
+
+    args.tag.extend(profile.tags);
+    args.terms.not.extend(profile.not);
+    if !profile.exclude.is_empty() {
+        args.exclude
+            .get_or_insert_with(Vec::new)
+            .extend(profile.exclude);
+    }
+    if args.common.lang.is_empty() {
+        args.common.lang = profile.lang;
+    }
+    if args.game.is_empty() {
+        args.game = profile.games;
+    }
+}
+
+fn list_profiles(config: &Config) {
+    if config.search.is_empty() {
+        println!("No saved searches. Add [search.<name>] tables to the config file.");
+    }
+    for (name, profile) in &config.search {
+        let mut details = vec![format!("terms {:?}", profile.terms)];
+        for (label, values) in [
+            ("tags", &profile.tags),
+            ("not", &profile.not),
+            ("exclude", &profile.exclude),
+            ("lang", &profile.lang),
+            ("games", &profile.games),
+        ] {
+            if !values.is_empty() {
+                details.push(format!("{label} {}", values.join(",")));
+            }
+        }
+        println!("{name}: {}", details.join(", "));
+    }
+}
+
+/// Sort direction from `--desc`/`--asc`, falling back to the key's default
+fn sort_descending(key: SortKey, desc: bool, asc: bool) -> bool {
+    desc || (!asc && key.descending_by_default())
+}
+
+// -----------------------------------------------------------------------------
+//     - Main -
+// -----------------------------------------------------------------------------
+
+/// Entry point of the `twitch-search` binary. Exits with
+/// [`EXIT_NO_MATCHES`] when a search found nothing, see [`AppError::exit_code`]
+/// for failures.
+pub fn run() -> Result<ExitCode, AppError> {
+    let cli = Cli::parse();
+    // Whether anything was found. Commands that don't search always find.
+    let found = match cli.command {
+        None => streams(cli.streams)?,
+        Some(Command::Streams(args)) => streams(*args)?,
+        Some(Command::Daemon(args)) => daemon(*args)?,
+        Some(Command::Channels(args)) => channels(args)?,
+        Some(Command::Games(args)) => games(args)?,
+        Some(Command::Videos(args)) => videos(args)?,
+        Some(Command::Clips(args)) => clips(args)?,
+        Some(Command::Clip(args)) => {
+            clip(args)?;
+            true
+        }
+        Some(Command::Bits(args)) => bits(args)?,
+        Some(Command::Subs(args)) => subs(args)?,
+        Some(Command::Analytics(args)) => analytics(args)?,
+        Some(Command::Play(args)) => {
+            play(args)?;
+            true
+        }
+        Some(Command::History(args)) => {
+            history(args)?;
+            true
+        }
+        #[cfg(feature = "sqlite")]
+        Some(Command::Sessions(args)) => sessions(args)?,
+        Some(Command::Login) => {
+            load_config(None)?;
+            println!("Logged in as {}.", login(&configure_agent())?);
+            true
+        }
+        Some(Command::Logout) => {
+            load_config(None)?;
+            match logout(&configure_agent())? {
+                true => println!("Logged out."),
+                false => println!("Not logged in."),
+            }
+            true
+        }
+        Some(Command::Ignore(args)) => {
+            ignore(args)?;
+            true
+        }
+        Some(Command::Block(args)) => {
+            block(args)?;
+            true
+        }
+        Some(Command::Fav(args)) => {
+            fav(args)?;
+            true
+        }
+        Some(Command::Doctor) => {
+            // Checks the config file itself
+            doctor()?;
+            true
+        }
+        Some(Command::Config(args)) => {
+            config(args)?;
+            true
+        }
+        Some(Command::Cache(args)) => {
+            cache(args)?;
+            true
+        }
+        Some(Command::Backup(args)) => {
+            backup(args)?;
+            true
+        }
+        Some(Command::Restore(args)) => {
+            restore(args)?;
+            true
+        }
+        Some(Command::Eventsub(args)) => {
+            eventsub(args)?;
+            true
+        }
+    };
+    Ok(match found {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::from(EXIT_NO_MATCHES),
+    })
+}
+
+/// Runs the `daemon` subcommand: installs the systemd units running the
+/// search, or runs it as [`streams`] does
+fn daemon(args: DaemonArgs) -> Result<bool, AppError> {
+    let Some(name) = &args.install_systemd else {
+        return streams(args.search);
+    };
+    let schedule = match (args.search.watch, args.systemd_timer) {
+        (Some(_), _) => Schedule::Watch,
+        (None, Some(minutes)) => Schedule::Timer(minutes),
+        (None, None) => {
+            return Err(AppError::Systemd(
+                "--install-systemd needs --watch or --systemd-timer".to_string(),
+            ))
+        }
+    };
+    let config_path = args
+        .search
+        .common
+        .config
+        .clone()
+        .or_else(Config::default_path);
+    let search = strip_install_options(&env::args().skip(1).collect::<Vec<_>>());
+    install_systemd(name, schedule, &search, config_path.as_deref())?;
+    Ok(true)
+}
+
+/// Runs the `streams` subcommand, recording it in the search history.
+/// Returns whether any stream matched.
+fn streams(mut args: StreamsArgs) -> Result<bool, AppError> {
+    if args.schema {
+        println!("{:#}", json_schema());
+        return Ok(true);
+    }
+
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+
+    if args.list_profiles {
+        list_profiles(&config);
+        return Ok(true);
+    }
+
+    let mut command = env::args().skip(1).collect::<Vec<_>>();
+    if args.last {
+        let previous = read_history()?.pop().ok_or(AppError::EmptyHistory)?;
+        eprintln!("Repeating: {}", previous.command_line());
+        args = repeat(args, &previous.args);
+        command = previous.args;
+    } else if let Err(e) = record_history(&command) {
+        eprintln!("Warning: failed to record search history: {e}");
+    }
+
+    apply_config(&mut args, config, config_path.as_deref())?;
+
+    let report_file = args.report_file.clone();
+    let mut report = RunReport::new(command, args.terms.term.clone());
+
+    let result = search(args, &mut report);
+
+    if let Some(path) = report_file {
+        if let Err(e) = report.write(&path, &result) {
+            eprintln!("Warning: failed to write report to {}: {e}", path.display());
+        }
+    }
+
+    result
+}
+
+/// The arguments of the search `previous` for `--last`, keeping the
+/// `--report-file` of the command line that asked for it
+fn repeat(current: StreamsArgs, previous: &[String]) -> StreamsArgs {
+    let program = env::args().next().unwrap_or_default();
+    let cli = Cli::parse_from(std::iter::once(program).chain(previous.iter().cloned()));
+    let mut args = match cli.command {
+        Some(Command::Streams(args)) => *args,
+        Some(Command::Daemon(args)) => args.search,
+        _ => cli.streams,
+    };
+    if current.report_file.is_some() {
+        args.report_file = current.report_file;
+    }
+    args
+}
+
+// -----------------------------------------------------------------------------
+//     - Search -
+// -----------------------------------------------------------------------------
+
+/// Where the streams are read from
+enum Source {
+    /// The Helix API, searching these games
+    Api {
+        client: TwitchClient,
+        game_ids: Vec<String>,
+        /// Languages filtered on by Helix itself
+        languages: Vec<String>,
+    },
+    /// The Helix API, searching the channels this user follows
+    Followed {
+        client: TwitchClient,
+        user_id: String,
+    },
+    /// Pages saved with `--save-raw`
+    File(PathBuf),
+}
+
+impl Source {
+    fn new(
+        args: &StreamsArgs,
+        filter: &SearchFilter,
+        timings: &mut Timings,
+    ) -> Result<Self, AppError> {
+        if let Some(path) = &args.from_file {
+            return Ok(Source::File(path.clone()));
+        }
+
+        if args.followed {
+            let client = timings.time("token", TwitchClient::for_user)?;
+            let user_id = token_user_id(client.agent(), &client.access_token())?;
+            return Ok(Source::Followed { client, user_id });
+        }
+
+        let client = timings.time("token", || args.common.client())?;
+        let game_ids = match args.game.is_empty() {
+            true => DEFAULT_GAME_IDS.map(str::to_string).to_vec(),
+            false => args
+                .game
+                .iter()
+                .map(|game| client.resolve_game(game))
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(Source::Api {
+            client,
+            game_ids,
+            languages: filter.langs.clone(),
+        })
+    }
+
+    fn client(&self) -> Option<&TwitchClient> {
+        match self {
+            Source::Api { client, .. } | Source::Followed { client, .. } => Some(client),
+            Source::File(_) => None,
+        }
+    }
+
+    /// Fetches (or loads) every stream, writing the raw pages to `save_raw` if given
+    fn fetch(
+        &self,
+        args: &StreamsArgs,
+        filter: &SearchFilter,
+        progress: Progress,
+        save_raw: Option<&Path>,
+    ) -> Result<(Vec<StreamEntry>, FetchStats), AppError> {
+        self.fetch_games(args, filter, progress, save_raw, None)
+    }
+
+    /// Like `fetch`, searching `games` (ids) instead when they are given and
+    /// the source searches games
+    fn fetch_games(
+        &self,
+        args: &StreamsArgs,
+        filter: &SearchFilter,
+        progress: Progress,
+        save_raw: Option<&Path>,
+        games: Option<&[String]>,
+    ) -> Result<(Vec<StreamEntry>, FetchStats), AppError> {
+        let options = |languages: &[String]| FetchOptions {
+            max_pages: args.max_pages,
+            keep_partial: args.keep_partial,
+            progress,
+            min_viewers: args.min_viewers,
+            languages: languages.to_vec(),
+            concurrency: args.concurrency,
+            limit: args.limit,
+            filter: Some(filter.clone()),
+        };
+        match self {
+            Source::Api {
+                client,
+                game_ids,
+                languages,
+            } => client.fetch_games(games.unwrap_or(game_ids), &options(languages), save_raw),
+            Source::Followed { client, user_id } => {
+                client.fetch_followed(user_id, &options(&[]), save_raw)
+            }
+            Source::File(path) => load_streams(path),
+        }
+    }
+}
+
+fn search(args: StreamsArgs, report: &mut RunReport) -> Result<bool, AppError> {
+    if args.sheet && args.sheets.is_none() {
+        return Err(AppError::NoSheet);
+    }
+    let mut exclude = exclusions(args.exclude.clone());
+    match IgnoreList::load() {
+        Ok(ignored) => exclude.extend(ignored.channels().map(str::to_string)),
+        Err(e) => report.warn(e.to_string()),
+    }
+    let filter = SearchFilter {
+        terms: args.terms.terms()?,
+        fields: args.fields.clone(),
+        not: args.terms.not(),
+        blocklist: args.terms.blocklist()?,
+        exclude,
+        langs: args.common.languages()?,
+        tags: args.tag.iter().map(|t| t.to_lowercase()).collect(),
+        all: args.terms.all,
+        viewers: ViewerLimits {
+            min: args.min_viewers,
+            max: args.max_viewers,
+        },
+        max_title_len: args.max_title_len,
+    };
+    let sort = args
+        .sort
+        .map(|key| (key, sort_descending(key, args.desc, args.asc)));
+
+    let output = args.common.output();
+    let human = output == OutputFormat::Table && !args.tui;
+    // Progress and summaries go to stderr, out of the way of piped results
+    let chatty = !args.quiet && !args.tui;
+
+    if chatty {
+        eprintln!("Searching for {:?}", args.terms.term);
+    }
+
+    let mut timings = Timings::default();
+
+    let source = Source::new(&args, &filter, &mut timings)?;
+
+    let mut column_list = args.columns.clone().unwrap_or_else(|| {
+        let mut layout = Column::layout(&args.extra);
+        if output == OutputFormat::Html {
+            layout.insert(0, Column::Thumbnail);
+        }
+        layout
+    });
+    if args.show_tags {
+        Column::insert(&mut column_list, Column::Tags);
+    }
+    if args.show_matched {
+        Column::insert(&mut column_list, Column::Matched);
+    }
+
+    let color = args.common.color(args.color);
+    // Matches still stand out on a terminal without colors
+    let brackets = human && !color && args.common.to_terminal();
+
+    // Modes that don't fetch channel details only get the columns that need none
+    let cheap_columns = column_list
+        .iter()
+        .copied()
+        .filter(|c| !c.needs_hydration())
+        .collect::<Vec<_>>();
+
+    if let Some(seconds) = args.watch {
+        let columns = Columns {
+            list: cheap_columns,
+            lang_names: args.lang_names,
+            plain: false,
+            color,
+            highlight: Some(filter.terms.clone()),
+            brackets,
+            max_title_width: args.max_title_width,
+        };
+        let interval = Duration::from_secs(seconds.max(1));
+        return watch(&args, &source, &filter, &columns, interval).map(|()| true);
+    }
+
+    let (entries, stats) = timings.time(
+        match source {
+            Source::File(_) => "load",
+            _ => "fetch",
+        },
+        || {
+            let progress = match chatty {
+                true => args.progress,
+                false => Progress::None,
+            };
+            source.fetch(&args, &filter, progress, args.save_raw.as_deref())
+        },
+    )?;
+
+    report.pages = stats.pages;
+    report.duplicates = stats.duplicates;
+    let capped = stats.capped;
+    if let Some(incomplete) = stats.incomplete {
+        report.warn(incomplete);
+    }
+
+    if args.tui {
+        let select = |entries: Vec<StreamEntry>| {
+            let mut matched = entries
+                .into_iter()
+                .filter(|e| filter.matches(e))
+                .collect::<Vec<_>>();
+            detect_duplicate_titles(&mut matched, args.dedupe_titles);
+            note_matched_terms(&mut matched, &filter, &column_list);
+            matched
+        };
+        let mut list = cheap_columns;
+        Column::insert(&mut list, Column::Category);
+        let columns = Columns {
+            list,
+            lang_names: args.lang_names,
+            plain: true,
+            color: false,
+            highlight: Some(filter.terms.clone()),
+            brackets: false,
+            max_title_width: args.max_title_width,
+        };
+        let styles = Styles::new(&args.theme)?;
+        let refresh = |game: Option<&str>| {
+            let games = game.map(|id| vec![id.to_string()]);
+            let (entries, _) =
+                source.fetch_games(&args, &filter, Progress::None, None, games.as_deref())?;
+            Ok(select(entries))
+        };
+        // Only the games of a search can be switched
+        let search_games = |query: &str| match &source {
+            Source::Api { client, .. } => client.search_games(query, 1),
+            _ => Ok(Vec::new()),
+        };
+        let categories = match &source {
+            Source::Api { .. } => Some(&search_games as &dyn Fn(&str) -> _),
+            _ => None,
+        };
+        // Blocking and clipping take the user token of `login`, not the app one
+        let block = |entry: &StreamEntry| {
+            let client = TwitchClient::for_user()?;
+            block_channel(&client, &entry.user_id, &entry.user_login)
+        };
+        let clip = |entry: &StreamEntry| {
+            let client = TwitchClient::for_user()?;
+            clip_channel(&client, &entry.user_id)
+        };
+        let actions = ui::Actions {
+            categories,
+            block: Some(&block),
+            clip: Some(&clip),
+        };
+        let interval = args
+            .refresh
+            .map(|seconds| Duration::from_secs(seconds.max(1)));
+        return ui::browse(
+            select(entries),
+            sort,
+            columns,
+            styles,
+            interval,
+            refresh,
+            actions,
+        )
+        .map(|()| true);
+    }
+
+    if args.repl {
+        let state = ReplState {
+            filter,
+            sort,
+            columns: Columns {
+                list: cheap_columns,
+                lang_names: args.lang_names,
+                plain: false,
+                color,
+                highlight: None,
+                brackets,
+                max_title_width: args.max_title_width,
+            },
+            width: args.common.screen_width(),
+            wrap: args.common.wrap,
+        };
+        return repl(entries, state).map(|()| true);
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &args.db {
+        let result = SessionDb::open(path).and_then(|mut db| db.record(&entries));
+        if let Err(e) = result {
+            report.warn(format!(
+                "failed to record sessions in {}: {e}",
+                path.display()
+            ));
+        }
+    }
+
+    let total = entries.len();
+    let mut matched = timings.time("filter", || {
+        entries
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .collect::<Vec<_>>()
+    });
+
+    if let Some(limit) = args.limit {
+        // Games were fetched one after the other, so put the biggest first again
+        sort_entries(&mut matched, SortKey::Viewers, true);
+        matched.truncate(limit);
+    }
+
+    let needs_users = column_list.iter().any(|c| c.needs_hydration());
+    if needs_users || args.min_account_age.is_some() || args.max_viewer_ratio.is_some() {
+        let options = HydrateOptions {
+            channels: column_list.contains(&Column::Ccls),
+            followers: args.max_viewer_ratio.is_some()
+                || column_list.contains(&Column::Followers)
+                || column_list.contains(&Column::ViewerRatio),
+        };
+        let result = timings.time("hydrate", || match source.client() {
+            Some(client) => client.hydrate(&mut matched, options),
+            None => args.common.client()?.hydrate(&mut matched, options),
+        });
+        if let Err(e) = result {
+            report.warn(format!("failed to fetch channel details: {e}"));
+        }
+    }
+
+    if let Some(max_ratio) = args.max_viewer_ratio {
+        let unknown = retain_viewer_ratios(&mut matched, max_ratio);
+        if unknown > 0 {
+            report.warn(format!(
+                "no follower count for {unknown} stream(s), kept without checking \
+                 --max-viewer-ratio (follower counts need `twitch-search login`)"
+            ));
+        }
+    }
+
+    if let Some(min_age) = args.min_account_age {
+        retain_old_accounts(&mut matched, min_age, Utc::now());
+    }
+
+    detect_duplicate_titles(&mut matched, args.dedupe_titles);
+
+    note_matched_terms(&mut matched, &filter, &column_list);
+
+    match sort {
+        Some((key, descending)) => sort_entries(&mut matched, key, descending),
+        None if args.terms.fuzzy => {
+            matched.sort_by(|a, b| filter.score(b).total_cmp(&filter.score(a)));
+        }
+        None => {}
+    }
+    let groups = match args.group_by {
+        Some(key) => group_entries(&mut matched, key),
+        None => vec![],
+    };
+
+    // What makes runs comparable for --diff
+    let query = serde_json::json!({
+        "terms": args.terms.term,
+        "games": args.game,
+        "langs": filter.langs,
+        "followed": args.followed,
+    });
+    let gone = match args.diff {
+        true => {
+            Column::insert(&mut column_list, Column::Change);
+            let previous = previous_run(&query)?.unwrap_or_else(|| {
+                report.warn("no earlier logged run of this search to compare with".to_string());
+                LoggedRun::default()
+            });
+            diff(&mut matched, &previous)
+        }
+        false => vec![],
+    };
+    if args.log_history {
+        if let Err(e) = log_run(&query, &matched) {
+            report.warn(format!("failed to log this run: {e}"));
+        }
+    }
+
+    if let Some(url) = &args.webhook_url {
+        let fresh = fresh_matches(&matched);
+        let mut announced = Announced::load_or_warn(url, "posted", |e| report.warn(e));
+        match &args.quiet_hours {
+            // Matches still live after the quiet hours are posted by the
+            // next run, or never when they are dropped
+            Some(quiet) if quiet.contains(Utc::now()) => {
+                if !quiet.queue {
+                    let dropped = announced.fresh(&fresh);
+                    announced.add(&dropped);
+                }
+            }
+            _ => {
+                if let Err(e) =
+                    announce(args.webhook_format, &args.messages, &fresh, &mut announced)
+                {
+                    report.warn(e.to_string());
+                }
+            }
+        }
+        if let Err(e) = announced.store() {
+            report.warn(format!("failed to remember the posted streams: {e}"));
+        }
+    }
+
+    if let Some(sheets) = args.sheets.as_ref().filter(|_| args.sheet) {
+        let fresh = fresh_matches(&matched);
+        let mut added = Announced::load_or_warn(&sheet_url(sheets), "added", |e| report.warn(e));
+        if let Err(e) = append(sheets, &fresh, &mut added) {
+            report.warn(e.to_string());
+        }
+        if let Err(e) = added.store() {
+            report.warn(format!("failed to remember the added streams: {e}"));
+        }
+    }
+
+    if let Some(path) = &args.log_csv {
+        if let Err(e) = append_csv_log(path, &matched) {
+            report.warn(format!("failed to append to {}: {e}", path.display()));
+        }
+    }
+
+    let viewers = matched.iter().map(|e| e.viewer_count).collect::<Vec<_>>();
+    let uptimes = matched
+        .iter()
+        .filter_map(|e| e.started_at)
+        .map(|started_at| (Utc::now() - started_at).num_minutes())
+        .collect::<Vec<_>>();
+
+    let summary = (args.stats || args.stats_only).then(|| Summary::new(&matched));
+
+    let matched_count = matched.len();
+    let picked = args
+        .open
+        .or(args.play)
+        .map(|n| pick(&matched, n))
+        .transpose()?;
+
+    timings.time("render", || {
+        if args.stats_only {
+            return Ok(());
+        }
+        if output == OutputFormat::Json {
+            let json = matched.iter().map(StreamEntry::to_json);
+            let gone = gone.iter().map(|stream| stream.to_json());
+            return print_json(json.chain(gone).collect(), &args.common);
+        }
+        if output == OutputFormat::Rss {
+            let title = format!("Twitch streams: {}", args.terms.term.join(" "));
+            return args
+                .common
+                .write_output(|out| write_rss(out, title.trim_end(), &matched));
+        }
+
+        let columns = Columns {
+            list: column_list,
+            lang_names: args.lang_names,
+            plain: !human,
+            color: color && output == OutputFormat::Table,
+            highlight: Some(filter.terms.clone()),
+            brackets,
+            max_title_width: args.max_title_width,
+        };
+        let mut table = columns.table();
+        let mut rows = matched.into_iter().map(|entry| entry.format_row(&columns));
+        for group in &groups {
+            table.push_heading(group.to_string());
+            for row in rows.by_ref().take(group.len) {
+                table.push(row);
+            }
+        }
+        for row in rows {
+            table.push(row);
+        }
+        if !groups.is_empty() && !gone.is_empty() {
+            table.push_heading(format!("Gone since the last run ({})", gone.len()));
+        }
+        for stream in &gone {
+            table.push(stream.gone_row(&columns));
+        }
+
+        print_table(&table, &args.common)
+    })?;
+
+    report.total = total;
+    report.matched = matched_count;
+
+    if chatty {
+        eprintln!("Done ({matched_count}/{total})");
+        if report.duplicates > 0 {
+            eprintln!(
+                "Skipped {} streams seen on an earlier page",
+                report.duplicates
+            );
+        }
+        if capped {
+            eprintln!("Stopped early after {} pages", report.pages);
+        }
+    }
+
+    if let Some(summary) = summary.filter(|_| chatty || args.stats_only) {
+        summary.print();
+        print_percentiles(&viewers, &uptimes);
+    }
+
+    if chatty && args.histogram {
+        print_histogram(&viewers);
+    }
+
+    if args.timings {
+        timings.print();
+    }
+
+    if let Some(url) = picked {
+        match args.play {
+            Some(_) => {
+                let template = args.player.as_deref().unwrap_or(DEFAULT_PLAYER);
+                player::play(template, &url, DEFAULT_QUALITY)?
+            }
+            None => ui::open_in_browser(&url).map_err(|e| AppError::OpenBrowser(url, e))?,
+        }
+    }
+
+    Ok(matched_count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::api::UserInfo;
+    use crate::entry::test_entry;
+    use crate::filter::test_filter;
+
+    /// The `streams` arguments of a command line, with or without the subcommand
+    fn streams_args(args: &[&str]) -> StreamsArgs {
+        let cli = Cli::parse_from(std::iter::once("twitch-search").chain(args.iter().copied()));
+        match cli.command {
+            Some(Command::Streams(args)) => *args,
+            _ => cli.streams,
+        }
+    }
+
+    #[test]
+    fn throttle_rates() {
+        assert_eq!(parse_throttle("2.5"), Ok(2.5));
+        assert_eq!(parse_throttle("0"), Ok(0.0));
+        for rate in ["1e-300", "-1", "inf", "NaN", "fast"] {
+            assert!(parse_throttle(rate).is_err(), "{rate}");
+        }
+
+        let config = Config {
+            throttle: Some(1e-300),
+            ..Config::default()
+        };
+        assert_eq!(config_problems(&config)[0].0, "throttle");
+    }
+
+    #[test]
+    fn account_ages() {
+        assert_eq!(parse_age("12h"), Ok(chrono::Duration::hours(12)));
+        assert_eq!(parse_age("30"), Ok(chrono::Duration::days(30)));
+        assert_eq!(parse_age("2w"), Ok(chrono::Duration::weeks(2)));
+        assert_eq!(parse_age("1y"), Ok(chrono::Duration::days(365)));
+        for age in [
+            "-3d",
+            "3m",
+            "old",
+            "99999999999999999w",
+            "9223372036854775807y",
+        ] {
+            assert!(parse_age(age).is_err(), "{age}");
+        }
+
+        let now = "2026-10-16T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let created = |name: &str, created_at: Option<DateTime<Utc>>| StreamEntry {
+            user: Some(UserInfo {
+                created_at,
+                ..UserInfo::default()
+            }),
+            ..test_entry(name, 10)
+        };
+        let mut entries = vec![
+            created("Old", Some(now - chrono::Duration::days(400))),
+            created("New", Some(now - chrono::Duration::days(3))),
+            created("Unknown", None),
+        ];
+        retain_old_accounts(&mut entries, chrono::Duration::days(30), now);
+        let names = entries.iter().map(|e| &e.display_name).collect::<Vec<_>>();
+        assert_eq!(names, ["Old", "Unknown"]);
+
+        retain_old_accounts(&mut entries, chrono::Duration::MAX, now);
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn viewer_ratios() {
+        let followed = |name: &str, viewers: i64, followers: Option<i64>| StreamEntry {
+            user: Some(UserInfo {
+                followers,
+                ..UserInfo::default()
+            }),
+            ..test_entry(name, viewers)
+        };
+        let mut entries = vec![
+            followed("Botted", 500, Some(20)),
+            followed("Fine", 50, Some(1000)),
+            followed("Unknown", 500, None),
+            followed("New", 2, Some(0)),
+        ];
+        assert_eq!(retain_viewer_ratios(&mut entries, 1.5), 1);
+        let names = entries.iter().map(|e| &e.display_name).collect::<Vec<_>>();
+        assert_eq!(names, ["Fine", "Unknown"]);
+    }
+
+    #[test]
+    fn last_keeps_the_report_file() {
+        let previous = ["rust", "--report-file", "old.json"].map(str::to_string);
+
+        let repeated = repeat(
+            streams_args(&["--last", "--report-file", "new.json"]),
+            &previous,
+        );
+        assert_eq!(repeated.terms.term, ["rust"]);
+        assert_eq!(repeated.report_file, Some(PathBuf::from("new.json")));
+
+        let repeated = repeat(streams_args(&["--last"]), &previous);
+        assert_eq!(repeated.report_file, Some(PathBuf::from("old.json")));
+    }
+
+    #[test]
+    fn game_flag() {
+        assert!(streams_args(&["rust"]).game.is_empty());
+        assert_eq!(
+            streams_args(&["--game", "Just Chatting", "rust"]).game,
+            ["Just Chatting"]
+        );
+        assert_eq!(
+            streams_args(&["-g", "509658", "-g", "1469308723"]).game,
+            ["509658", "1469308723"]
+        );
+    }
+
+    #[test]
+    fn output_formats() {
+        assert_eq!(streams_args(&["rust"]).common.output, None);
+        assert_eq!(
+            streams_args(&["--output", "json", "rust"]).common.output,
+            Some(OutputFormat::Json)
+        );
+    }
+
+    #[test]
+    fn max_pages_from_one() {
+        assert_eq!(streams_args(&["--max-pages", "1"]).max_pages, 1);
+        assert!(Cli::try_parse_from(["twitch-search", "--max-pages", "0"]).is_err());
+        assert!(Cli::try_parse_from(["twitch-search", "games", "--max-pages", "0"]).is_err());
+    }
+
+    #[test]
+    fn daemon_installs() {
+        let daemon = |args: &[&str]| {
+            let args = ["twitch-search", "daemon"]
+                .into_iter()
+                .chain(args.iter().copied());
+            match Cli::try_parse_from(args).map(|cli| cli.command) {
+                Ok(Some(Command::Daemon(args))) => Ok(args),
+                Ok(command) => panic!("{command:?}"),
+                Err(e) => Err(e),
+            }
+        };
+        let args = daemon(&["--install-systemd", "rust", "--systemd-timer", "15", "rust"]).unwrap();
+        assert_eq!(args.install_systemd.as_deref(), Some("rust"));
+        assert_eq!(args.systemd_timer, Some(15));
+        assert_eq!(args.search.terms.term, ["rust"]);
+        assert!(daemon(&["--install-systemd", "rust", "--systemd-timer", "0"]).is_err());
+        assert!(daemon(&["--systemd-timer", "15"]).is_err());
+        let timed = [
+            "--install-systemd",
+            "rust",
+            "--systemd-timer",
+            "15",
+            "--watch",
+            "60",
+        ];
+        assert!(daemon(&timed).is_err());
+        assert!(Cli::try_parse_from(["twitch-search", "--install-systemd", "rust"]).is_err());
+    }
+
+    #[test]
+    fn notify_needs_watch() {
+        assert!(Cli::try_parse_from(["twitch-search", "--notify"]).is_err());
+        assert!(streams_args(&["--watch", "300", "--notify"]).notify);
+    }
+
+    #[test]
+    fn saved_searches() {
+        let profile = || Profile {
+            terms: vec!["zig".to_string()],
+            not: vec!["giveaway".to_string()],
+            games: vec!["Software and Game Development".to_string()],
+            lang: vec!["sv".to_string()],
+            ..Profile::default()
+        };
+
+        let mut args = streams_args(&["rust", "--lang", "en"]);
+        apply_profile(&mut args, profile());
+        assert_eq!(args.terms.term, ["zig", "rust"]);
+        assert_eq!(args.terms.not, ["giveaway"]);
+        assert_eq!(args.common.lang, ["en"]);
+        assert_eq!(args.game, ["Software and Game Development"]);
+    }
+
+    #[test]
+    fn saved_search_without_terms() {
+        let profile = Profile {
+            terms: vec!["zig".to_string()],
+            lang: vec!["sv".to_string()],
+            ..Profile::default()
+        };
+        let mut args = streams_args(&["--game", "Minecraft"]);
+        apply_profile(&mut args, profile);
+        assert_eq!(args.terms.term, ["zig"]);
+        assert_eq!(args.common.lang, ["sv"]);
+        assert_eq!(args.game, ["Minecraft"]);
+    }
+
+    #[test]
+    fn subcommands() {
+        let command = |args: &[&str]| {
+            let args = std::iter::once("twitch-search").chain(args.iter().copied());
+            Cli::try_parse_from(args).map(|cli| cli.command)
+        };
+        assert!(matches!(command(&["rust"]), Ok(None)));
+        assert!(matches!(
+            command(&["streams", "rust"]),
+            Ok(Some(Command::Streams(_)))
+        ));
+        assert!(matches!(
+            command(&["channels", "rust"]),
+            Ok(Some(Command::Channels(_)))
+        ));
+        assert!(matches!(
+            command(&["games", "mine"]),
+            Ok(Some(Command::Games(_)))
+        ));
+        assert!(matches!(
+            command(&["videos", "rust"]),
+            Ok(Some(Command::Videos(_)))
+        ));
+        assert!(matches!(
+            command(&["clips", "rust"]),
+            Ok(Some(Command::Clips(_)))
+        ));
+        assert_eq!(streams_args(&["streams", "rust"]).terms.term, ["rust"]);
+    }
+
+    #[test]
+    fn retry_delays() {
+        assert_eq!(parse_retry_delay("0.5"), Ok(0.5));
+        assert_eq!(parse_retry_delay("0"), Ok(0.0));
+        for delay in ["inf", "1e300", "61", "-1", "NaN"] {
+            assert!(parse_retry_delay(delay).is_err(), "{delay}");
+        }
+    }
+
+    #[test]
+    fn open_and_play_positions() {
+        assert_eq!(streams_args(&["--open", "rust"]).open, Some(1));
+        assert_eq!(streams_args(&["--play=3", "rust"]).play, Some(3));
+        assert_eq!(streams_args(&["rust"]).open, None);
+        for args in [&["--open=0", "rust"][..], &["--open", "--play", "rust"]] {
+            let args = std::iter::once("twitch-search").chain(args.iter().copied());
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+
+        let matched = [test_entry("Crab", 30), test_entry("Ferris", 20)];
+        assert_eq!(pick(&matched, 2).unwrap(), "https://twitch.tv/ferris");
+        assert!(matches!(
+            pick(&matched, 3),
+            Err(AppError::NoSuchMatch(3, 2))
+        ));
+    }
+
+    #[test]
+    fn out_file() {
+        let path = env::temp_dir().join(format!("twitch-search-out-{}.csv", std::process::id()));
+        let out_file = path.to_str().unwrap();
+        let common = streams_args(&["--output", "csv", "--out-file", out_file, "rust"]).common;
+        assert!(!common.color(ColorChoice::Auto));
+        assert!(common.color(ColorChoice::Always));
+
+        let mut table = Table::new(2);
+        table.set_headers(vec!["channel".to_string(), "viewers".to_string()]);
+        table.push(vec!["Crab".to_string(), "10".to_string()]);
+        print_table(&table, &common).unwrap();
+        let written = fs::read_to_string(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(written.unwrap(), "channel,viewers\nCrab,10\n");
+
+        let missing = Path::new("no-such-dir").join("out.json");
+        let common = streams_args(&["--out-file", missing.to_str().unwrap(), "rust"]).common;
+        assert!(matches!(
+            print_json(vec![], &common),
+            Err(AppError::WriteOutput(..))
+        ));
+        let args = [
+            "twitch-search",
+            "--watch",
+            "60",
+            "--out-file",
+            "out.csv",
+            "rust",
+        ];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn matched_column() {
+        let filter = test_filter(&["rust", "zig", "café"]);
+        let mut entries = vec![
+            StreamEntry {
+                title: "Rust and Zig at the Cafe".to_string(),
+                ..test_entry("Crab", 10)
+            },
+            test_entry("Ferris", 20),
+        ];
+        note_matched_terms(&mut entries, &filter, &[Column::Title]);
+        assert!(entries[0].matched_terms.is_empty());
+
+        note_matched_terms(&mut entries, &filter, &[Column::Title, Column::Matched]);
+        assert_eq!(entries[0].matched_terms, ["rust", "zig", "cafe"]);
+        assert_eq!(entries[1].matched_terms, ["rust"]);
+        assert!(streams_args(&["--show-matched", "rust"]).show_matched);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::config::Config;
+use crate::player::{self, DEFAULT_PLAYER, DEFAULT_QUALITY};
+use crate::AppError;
+
+use super::{load_config, CommonArgs};
+
+/// Options of the `play` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct PlayArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    /// Channel name, or terms to search live channels for
+    #[clap(required = true)]
+    target: Vec<String>,
+
+    /// Stream quality, like 720p, 480p or best, for {quality} in the player command
+    #[clap(short, long, default_value = DEFAULT_QUALITY)]
+    quality: String,
+
+    /// Player command, where {url} is the stream [default: streamlink {url} {quality}]
+    #[clap(long, value_name = "COMMAND")]
+    player: Option<String>,
+}
+
+/// Plays the channel named by the target if there is one, or else the best
+/// live match of searching channels for it
+pub(super) fn play(mut args: PlayArgs) -> Result<(), AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+    let template = args.player.or(config.player);
+
+    let client = args.common.client()?;
+    let target = args.target.join(" ");
+    let is_login = target
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    let live = match is_login {
+        true => client.fetch_live(&target)?,
+        false => None,
+    };
+    let (login, title) = match live {
+        Some(stream) => (stream.user_login, stream.title),
+        None => {
+            if is_login {
+                match client.resolve_user(&target) {
+                    Ok(_) => return Err(AppError::NotLive(target)),
+                    Err(AppError::UnknownChannel(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let languages = args.common.languages()?;
+            client
+                .search_channels(&target, true, 1)?
+                .into_iter()
+                .find(|c| languages.is_empty() || languages.contains(&c.broadcaster_language))
+                .map(|c| (c.broadcaster_login, c.title))
+                .ok_or(AppError::NoLiveMatch(target))?
+        }
+    };
+
+    let url = format!("https://twitch.tv/{login}");
+    eprintln!("Playing {url}: {title}");
+    player::play(
+        template.as_deref().unwrap_or(DEFAULT_PLAYER),
+        &url,
+        &args.quality,
+    )
+}
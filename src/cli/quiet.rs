@@ -0,0 +1,111 @@
+use chrono::prelude::*;
+use chrono_tz::Tz;
+
+use crate::config::Quiet;
+use crate::AppError;
+
+/// The `[quiet]` table, checked
+#[derive(Debug, Clone)]
+pub(super) struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+    /// The system's time zone when `None`
+    timezone: Option<Tz>,
+    /// Whether what matched is sent once the hours are over
+    pub(super) queue: bool,
+}
+
+impl QuietHours {
+    pub(super) fn new(quiet: &Quiet) -> Result<Self, AppError> {
+        let time = |text: &str| NaiveTime::parse_from_str(text.trim(), "%H:%M").ok();
+        let (start, end) = quiet
+            .hours
+            .split_once('-')
+            .and_then(|(start, end)| Some((time(start)?, time(end)?)))
+            .ok_or_else(|| {
+                AppError::InvalidQuiet(
+                    "quiet.hours".to_string(),
+                    format!("expected like 22:00-07:00, got {:?}", quiet.hours),
+                )
+            })?;
+        let timezone = quiet
+            .timezone
+            .as_deref()
+            .map(|name| {
+                name.parse::<Tz>().map_err(|_| {
+                    AppError::InvalidQuiet(
+                        "quiet.timezone".to_string(),
+                        format!("unknown time zone {name:?}, expected like Europe/Stockholm"),
+                    )
+                })
+            })
+            .transpose()?;
+        Ok(QuietHours {
+            start,
+            end,
+            timezone,
+            queue: quiet.queue,
+        })
+    }
+
+    /// Whether `now` falls within the hours
+    pub(super) fn contains(&self, now: DateTime<Utc>) -> bool {
+        let time = match self.timezone {
+            Some(timezone) => now.with_timezone(&timezone).time(),
+            None => now.with_timezone(&Local).time(),
+        };
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet(hours: &str, timezone: Option<&str>) -> Result<QuietHours, AppError> {
+        QuietHours::new(&Quiet {
+            hours: hours.to_string(),
+            timezone: timezone.map(str::to_string),
+            queue: true,
+        })
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        format!("2026-10-16T{time}:00Z").parse().unwrap()
+    }
+
+    #[test]
+    fn hours_past_midnight() {
+        let night = quiet("22:00-07:00", Some("UTC")).unwrap();
+        assert!(night.contains(at("23:30")));
+        assert!(night.contains(at("03:00")));
+        assert!(!night.contains(at("07:00")));
+        assert!(!night.contains(at("12:00")));
+
+        let lunch = quiet(" 12:00 - 13:00 ", Some("UTC")).unwrap();
+        assert!(lunch.contains(at("12:30")));
+        assert!(!lunch.contains(at("13:30")));
+    }
+
+    #[test]
+    fn in_the_time_zone() {
+        // 22:30 UTC is 00:30 in Stockholm in October (summer time)
+        let night = quiet("00:00-06:00", Some("Europe/Stockholm")).unwrap();
+        assert!(night.contains(at("22:30")));
+        assert!(!night.contains(at("05:00")));
+    }
+
+    #[test]
+    fn rejects_bad_tables() {
+        assert!(quiet("22-07", None).is_err());
+        assert!(quiet("22:00", None).is_err());
+        assert!(matches!(
+            quiet("22:00-07:00", Some("Mars/Olympus")),
+            Err(AppError::InvalidQuiet(key, _)) if key == "quiet.timezone"
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+
+use crate::filter::SearchFilter;
+use crate::languages::resolve_language;
+use crate::matcher::fold;
+use crate::table::{sort_entries, Columns, SortKey, Wrap};
+use crate::{AppError, StreamEntry};
+
+use super::note_matched_terms;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum ReplAction {
+    Print,
+    Nothing,
+    Quit,
+}
+
+#[derive(Debug)]
+pub(super) struct ReplState {
+    pub(super) filter: SearchFilter,
+    /// Key and whether to sort descending
+    pub(super) sort: Option<(SortKey, bool)>,
+    pub(super) columns: Columns,
+    /// What to fit the table into, see [`crate::table::Table::print_to_width`]
+    pub(super) width: Option<usize>,
+    pub(super) wrap: Wrap,
+}
+
+const REPL_HELP: &str = "\
+Commands:
+  terms <word>...      replace the search terms (no words matches everything)
+  not <word>...        replace the terms that rule out a title
+  exclude <name>...    replace the excluded streamers
+  lang <lang>,...|off  only show these languages (codes or names)
+  all on|off           require all terms to match
+  word on|off          match on word boundaries
+  min <n>|off          minimum viewer count
+  max <n>|off          maximum viewer count
+  sort <key> [asc|desc] sort by viewers, uptime, name or lang (or off)
+  show                 print the table again
+  help                 show this help
+  quit                 leave";
+
+impl ReplState {
+    fn print(&self, entries: &[StreamEntry]) {
+        let mut matched = entries
+            .iter()
+            .filter(|e| self.filter.matches(e))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if let Some((key, descending)) = self.sort {
+            sort_entries(&mut matched, key, descending);
+        }
+        note_matched_terms(&mut matched, &self.filter, &self.columns.list);
+
+        // The terms may have changed since the last search
+        let columns = Columns {
+            highlight: Some(self.filter.terms.clone()),
+            ..self.columns.clone()
+        };
+        let mut table = columns.table();
+        for entry in matched {
+            table.push(entry.format_row(&columns));
+        }
+        table.print_to_width(self.width, self.wrap);
+
+        println!("Matched {}/{}", table.len(), entries.len());
+    }
+
+    fn apply(&mut self, line: &str) -> Result<ReplAction, String> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(ReplAction::Nothing);
+        };
+        let rest = words.collect::<Vec<_>>();
+
+        fn switch(rest: &[&str]) -> Result<bool, String> {
+            match rest {
+                ["on"] => Ok(true),
+                ["off"] => Ok(false),
+                _ => Err("expected on or off".to_string()),
+            }
+        }
+
+        fn limit(rest: &[&str]) -> Result<Option<i64>, String> {
+            match rest {
+                ["off"] => Ok(None),
+                [n] => n
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("not a number: {n}")),
+                _ => Err("expected a number or off".to_string()),
+            }
+        }
+
+        match command {
+            "terms" => {
+                let terms = rest.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+                self.filter.terms = self
+                    .filter
+                    .terms
+                    .replaced(&terms)
+                    .map_err(|e| e.to_string())?;
+            }
+            "not" => {
+                let normalize = self.filter.terms.normalize;
+                self.filter.not = rest.iter().map(|t| fold(t, normalize)).collect()
+            }
+            "exclude" => self.filter.exclude = rest.iter().map(|t| t.to_lowercase()).collect(),
+            "lang" => {
+                self.filter.langs = match rest[..] {
+                    ["off"] | [] => vec![],
+                    _ => rest
+                        .join(" ")
+                        .split(',')
+                        .map(|lang| resolve_language(lang.trim()))
+                        .collect::<Result<_, _>>()
+                        .map_err(|e| e.to_string())?,
+                }
+            }
+            "all" => self.filter.all = switch(&rest)?,
+            "word" => self.filter.terms.whole_word = switch(&rest)?,
+            "min" => self.filter.viewers.min = limit(&rest)?,
+            "max" => self.filter.viewers.max = limit(&rest)?,
+            "sort" => {
+                self.sort = match rest[..] {
+                    ["off"] | [] => None,
+                    [key, ref direction @ ..] => {
+                        let key = SortKey::from_str(key, true).map_err(|_| {
+                            "expected viewers, uptime, name, lang or off".to_string()
+                        })?;
+                        let descending = match direction {
+                            [] => key.descending_by_default(),
+                            ["desc"] => true,
+                            ["asc"] => false,
+                            _ => return Err("expected asc or desc".to_string()),
+                        };
+                        Some((key, descending))
+                    }
+                }
+            }
+            "show" => {}
+            "help" | "?" => {
+                println!("{REPL_HELP}");
+                return Ok(ReplAction::Nothing);
+            }
+            "quit" | "exit" | "q" => return Ok(ReplAction::Quit),
+            other => return Err(format!("unknown command: {other} (try help)")),
+        }
+
+        Ok(ReplAction::Print)
+    }
+}
+
+pub(super) fn repl(entries: Vec<StreamEntry>, mut state: ReplState) -> Result<(), AppError> {
+    state.print(&entries);
+
+    let stdin = std::io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            break;
+        }
+
+        match state.apply(&line) {
+            Ok(ReplAction::Print) => state.print(&entries),
+            Ok(ReplAction::Nothing) => {}
+            Ok(ReplAction::Quit) => break,
+            Err(e) => println!("{e}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+    use crate::filter::test_filter;
+
+    fn repl_state() -> ReplState {
+        ReplState {
+            filter: test_filter(&["rust"]),
+            sort: None,
+            columns: Columns::default(),
+            width: None,
+            wrap: Wrap::Off,
+        }
+    }
+
+    #[test]
+    fn repl_commands_change_the_search() {
+        let mut state = repl_state();
+        let zig = StreamEntry {
+            title: "Zig live".to_string(),
+            ..test_entry("Ziggy", 50)
+        };
+        assert!(!state.filter.matches(&zig));
+
+        assert_eq!(state.apply("terms Zig Crab"), Ok(ReplAction::Print));
+        assert!(state.filter.matches(&zig));
+        assert_eq!(state.apply("min 100"), Ok(ReplAction::Print));
+        assert!(!state.filter.matches(&zig));
+        assert_eq!(state.apply("min off"), Ok(ReplAction::Print));
+        assert_eq!(state.apply("exclude ZIGGY"), Ok(ReplAction::Print));
+        assert!(!state.filter.matches(&zig));
+
+        assert_eq!(state.apply("lang English, sv"), Ok(ReplAction::Print));
+        assert_eq!(state.filter.langs, ["en", "sv"]);
+        assert_eq!(state.apply("lang off"), Ok(ReplAction::Print));
+        assert!(state.filter.langs.is_empty());
+        assert_eq!(state.apply("all on"), Ok(ReplAction::Print));
+        assert!(state.filter.all);
+        assert_eq!(state.apply("word on"), Ok(ReplAction::Print));
+        assert!(state.filter.terms.whole_word);
+
+        assert_eq!(state.apply("sort viewers"), Ok(ReplAction::Print));
+        assert_eq!(state.sort, Some((SortKey::Viewers, true)));
+        assert_eq!(state.apply("sort name desc"), Ok(ReplAction::Print));
+        assert_eq!(state.sort, Some((SortKey::Name, true)));
+        assert_eq!(state.apply("sort off"), Ok(ReplAction::Print));
+        assert_eq!(state.sort, None);
+    }
+
+    #[test]
+    fn repl_other_lines() {
+        let mut state = repl_state();
+        assert_eq!(state.apply("  "), Ok(ReplAction::Nothing));
+        assert_eq!(state.apply("show"), Ok(ReplAction::Print));
+        assert_eq!(state.apply("q"), Ok(ReplAction::Quit));
+        assert!(state.apply("all maybe").is_err());
+        assert!(state.apply("min lots").is_err());
+        assert!(state.apply("sort size").is_err());
+        assert!(state.apply("sort name up").is_err());
+        assert!(state.apply("lang klingon").is_err());
+        assert!(state.apply("search rust").is_err());
+        assert!(!state.filter.all);
+    }
+}
@@ -0,0 +1,436 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use serde_json::Value;
+
+use crate::api::{last_rate_limit, RateLimit};
+use crate::table::csv_field;
+use crate::{AppError, StreamEntry};
+
+// -----------------------------------------------------------------------------
+//     - Timings -
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Default)]
+pub(super) struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub(super) fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.phases.push((phase, start.elapsed()));
+        result
+    }
+
+    pub(super) fn print(&self) {
+        let total = self.phases.iter().map(|(_, d)| *d).sum::<Duration>();
+        for (phase, duration) in self.phases.iter().chain([&("total", total)]) {
+            eprintln!("{phase:>8}: {:>9.3} s", duration.as_secs_f64());
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Run report -
+// -----------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub(super) struct RunReport {
+    pub(super) started_at: DateTime<Utc>,
+    pub(super) start: Instant,
+    pub(super) args: Vec<String>,
+    pub(super) terms: Vec<String>,
+    pub(super) pages: usize,
+    pub(super) total: usize,
+    pub(super) matched: usize,
+    /// Streams skipped for turning up on more than one page
+    pub(super) duplicates: usize,
+    pub(super) errors: Vec<String>,
+}
+
+impl RunReport {
+    pub(super) fn new(args: Vec<String>, terms: Vec<String>) -> Self {
+        RunReport {
+            started_at: Utc::now(),
+            start: Instant::now(),
+            args,
+            terms,
+            pages: 0,
+            total: 0,
+            matched: 0,
+            duplicates: 0,
+            errors: vec![],
+        }
+    }
+
+    /// Prints a warning and remembers it for the report
+    pub(super) fn warn(&mut self, message: String) {
+        eprintln!("Warning: {message}");
+        self.errors.push(message);
+    }
+
+    pub(super) fn write<T>(
+        &self,
+        path: &Path,
+        result: &Result<T, AppError>,
+    ) -> std::io::Result<()> {
+        let report = self.to_json(result, last_rate_limit());
+        fs::write(path, format!("{report:#}\n"))
+    }
+
+    fn to_json<T>(&self, result: &Result<T, AppError>, rate_limit: Option<RateLimit>) -> Value {
+        let mut errors = self.errors.clone();
+        if let Err(e) = result {
+            errors.push(e.to_string());
+        }
+
+        serde_json::json!({
+            "query": {
+                "terms": self.terms,
+                "args": self.args,
+            },
+            "started_at": self.started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "duration_secs": self.start.elapsed().as_secs_f64(),
+            "success": result.is_ok(),
+            "pages": self.pages,
+            "total": self.total,
+            "matched": self.matched,
+            "duplicates": self.duplicates,
+            "rate_limit": rate_limit.map(|r| serde_json::json!({
+                "limit": r.limit,
+                "remaining": r.remaining,
+                "reset": r.reset.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            })),
+            "errors": errors,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - CSV logging -
+// -----------------------------------------------------------------------------
+
+const CSV_LOG_HEADER: &str = "sampled_at,user_id,channel,lang,viewer_count,started_at,title";
+
+/// Appends one row per entry, stamped with the current time, writing the
+/// header first if the file is new. The file is locked while writing, so
+/// runs sampling at the same time (say, from cron) neither both write the
+/// header nor mix their rows.
+pub(super) fn append_csv_log(
+    path: &std::path::Path,
+    entries: &[StreamEntry],
+) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.lock()?;
+    let header = file.metadata()?.len() == 0;
+
+    let sampled_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let mut rows = Vec::new();
+    write_csv_log(&mut rows, entries, &sampled_at, header)?;
+    file.write_all(&rows)
+}
+
+fn write_csv_log(
+    out: &mut impl Write,
+    entries: &[StreamEntry],
+    sampled_at: &str,
+    header: bool,
+) -> std::io::Result<()> {
+    if header {
+        writeln!(out, "{CSV_LOG_HEADER}")?;
+    }
+    for entry in entries {
+        let started_at = entry
+            .started_at
+            .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .unwrap_or_default();
+        let row = [
+            sampled_at,
+            &entry.user_id,
+            &entry.display_name,
+            &entry.lang,
+            &entry.viewer_count.to_string(),
+            &started_at,
+            &entry.title,
+        ];
+        let row = row.map(csv_field).join(",");
+        writeln!(out, "{row}")?;
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+//     - Summary -
+// -----------------------------------------------------------------------------
+
+const PERCENTILES: [usize; 3] = [50, 90, 99];
+
+/// Tags listed by the summary at most
+const TOP_TAGS: usize = 5;
+
+/// Totals of the matches for `--stats`, gathered before they are printed
+#[derive(Debug, Default)]
+pub(super) struct Summary {
+    streams: usize,
+    viewers: i64,
+    median_viewers: i64,
+    max_viewers: i64,
+    /// Streams per language, most first
+    langs: Vec<(String, usize)>,
+    /// Channel that has been live the longest, and for how many minutes
+    longest: Option<(String, i64)>,
+    /// The most used tags and how many streams carry them
+    tags: Vec<(String, usize)>,
+}
+
+impl Summary {
+    pub(super) fn new(entries: &[StreamEntry]) -> Self {
+        let mut viewers = entries.iter().map(|e| e.viewer_count).collect::<Vec<_>>();
+        viewers.sort_unstable();
+
+        let longest = entries
+            .iter()
+            .filter_map(|e| Some((e, e.started_at?)))
+            .min_by_key(|&(_, started_at)| started_at)
+            .map(|(e, started_at)| {
+                let minutes = (Utc::now() - started_at).num_minutes();
+                (e.display_name.clone(), minutes)
+            });
+
+        Summary {
+            streams: entries.len(),
+            viewers: viewers.iter().sum(),
+            median_viewers: match viewers.is_empty() {
+                true => 0,
+                false => percentile(&viewers, 50),
+            },
+            max_viewers: viewers.last().copied().unwrap_or_default(),
+            langs: most_common(entries.iter().map(|e| e.lang.clone())),
+            longest,
+            tags: most_common(entries.iter().flat_map(|e| e.tags.iter().cloned())),
+        }
+    }
+
+    /// Prints the summary to stderr, like the progress, so it stays out of
+    /// piped results
+    pub(super) fn print(&self) {
+        eprintln!(
+            "Streams: {}, {} viewers (median {}, max {})",
+            self.streams, self.viewers, self.median_viewers, self.max_viewers
+        );
+        if !self.langs.is_empty() {
+            let langs = self.langs.iter().map(|(lang, n)| format!("{lang} {n}"));
+            eprintln!("Languages: {}", langs.collect::<Vec<_>>().join(", "));
+        }
+        if let Some((channel, minutes)) = &self.longest {
+            eprintln!("Longest live: {channel} ({})", hours_minutes(*minutes));
+        }
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().take(TOP_TAGS);
+            let tags = tags.map(|(tag, n)| format!("{tag} {n}"));
+            eprintln!("Top tags: {}", tags.collect::<Vec<_>>().join(", "));
+        }
+    }
+}
+
+/// Counts each distinct value, most common first, ties in order of appearance
+fn most_common(values: impl Iterator<Item = String>) -> Vec<(String, usize)> {
+    let mut counts = Vec::<(String, usize)>::new();
+    for value in values {
+        match counts.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+    counts.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+    counts
+}
+
+fn hours_minutes(minutes: i64) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Nearest-rank percentile of already sorted values
+fn percentile(sorted: &[i64], p: usize) -> i64 {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+pub(super) fn print_percentiles(viewers: &[i64], uptimes: &[i64]) {
+    if viewers.is_empty() {
+        return;
+    }
+
+    let mut viewers = viewers.to_vec();
+    viewers.sort_unstable();
+    let mut uptimes = uptimes.to_vec();
+    uptimes.sort_unstable();
+
+    let viewer_stats = PERCENTILES
+        .iter()
+        .map(|&p| format!("p{p} {}", percentile(&viewers, p)))
+        .collect::<Vec<_>>();
+    eprintln!("Viewers: {}", viewer_stats.join(", "));
+
+    if !uptimes.is_empty() {
+        let uptime_stats = PERCENTILES
+            .iter()
+            .map(|&p| format!("p{p} {}", hours_minutes(percentile(&uptimes, p))))
+            .collect::<Vec<_>>();
+        eprintln!("Uptime:  {}", uptime_stats.join(", "));
+    }
+}
+
+/// Width of the longest bar in the histogram
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Labels of the histogram buckets, and the viewer counts they stop short of
+const HISTOGRAM_BUCKETS: [(&str, i64); 4] = [
+    ("0-10", 10),
+    ("10-100", 100),
+    ("100-1k", 1000),
+    ("1k+", i64::MAX),
+];
+
+/// How many of `viewers` fall in each of [`HISTOGRAM_BUCKETS`]
+fn histogram_counts(viewers: &[i64]) -> [usize; HISTOGRAM_BUCKETS.len()] {
+    let mut counts = [0; HISTOGRAM_BUCKETS.len()];
+    for &v in viewers {
+        let bucket = HISTOGRAM_BUCKETS
+            .iter()
+            .position(|&(_, upper)| v < upper)
+            .unwrap_or(HISTOGRAM_BUCKETS.len() - 1);
+        counts[bucket] += 1;
+    }
+    counts
+}
+
+pub(super) fn print_histogram(viewers: &[i64]) {
+    let counts = histogram_counts(viewers);
+    let max = counts.iter().copied().max().unwrap_or_default().max(1);
+    for ((label, _), count) in HISTOGRAM_BUCKETS.iter().zip(counts) {
+        let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(max));
+        eprintln!("{label:>7} | {bar:<HISTOGRAM_WIDTH$} {count}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+
+    #[test]
+    fn run_report() {
+        let mut report = RunReport::new(
+            ["twitch-search", "rust"].map(str::to_string).to_vec(),
+            vec!["rust".to_string()],
+        );
+        report.pages = 2;
+        report.total = 150;
+        report.matched = 3;
+        report.errors.push("page 3 failed".to_string());
+
+        let json = report.to_json(&Ok(()), None);
+        assert_eq!(json["query"]["terms"], serde_json::json!(["rust"]));
+        assert_eq!(json["success"], true);
+        assert_eq!(json["pages"], 2);
+        assert_eq!(json["matched"], 3);
+        assert!(json["rate_limit"].is_null());
+        assert_eq!(json["errors"], serde_json::json!(["page 3 failed"]));
+
+        let failed: Result<(), _> = Err(AppError::EmptyHistory);
+        let json = report.to_json(&failed, None);
+        assert_eq!(json["success"], false);
+        assert_eq!(json["errors"].as_array().unwrap().len(), 2);
+    }
+
+    fn csv_log(entries: &[StreamEntry], header: bool) -> String {
+        let mut out = Vec::new();
+        write_csv_log(&mut out, entries, "2026-10-16T13:00:00Z", header).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_log_rows() {
+        let entry = StreamEntry {
+            title: "Rust, \"live\"".to_string(),
+            ..test_entry("Crab", 10)
+        };
+        assert_eq!(
+            csv_log(&[entry], true),
+            "sampled_at,user_id,channel,lang,viewer_count,started_at,title\n\
+             2026-10-16T13:00:00Z,crab,Crab,en,10,2026-10-16T12:00:00Z,\"Rust, \"\"live\"\"\"\n"
+        );
+        assert_eq!(csv_log(&[], false), "");
+    }
+
+    #[test]
+    fn timings_record_each_phase() {
+        let mut timings = Timings::default();
+        assert_eq!(timings.time("fetch", || 42), 42);
+        timings.time("print", || std::thread::sleep(Duration::from_millis(5)));
+
+        let phases = timings.phases.iter().map(|&(p, _)| p).collect::<Vec<_>>();
+        assert_eq!(phases, ["fetch", "print"]);
+        assert!(timings.phases[1].1 >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let hundred = (1..=100).collect::<Vec<_>>();
+        let ranks = PERCENTILES.map(|p| percentile(&hundred, p));
+        assert_eq!(ranks, [50, 90, 99]);
+
+        let ten = (1..=10).map(|v| v * 10).collect::<Vec<_>>();
+        assert_eq!(PERCENTILES.map(|p| percentile(&ten, p)), [50, 90, 100]);
+        assert_eq!(PERCENTILES.map(|p| percentile(&[7], p)), [7, 7, 7]);
+        assert_eq!(percentile(&[1, 2], 50), 1);
+        assert_eq!(percentile(&[1, 2, 3], 50), 2);
+        assert_eq!(percentile(&[1, 2, 3], 0), 1);
+        assert_eq!(percentile(&[1, 2, 3], 100), 3);
+    }
+
+    #[test]
+    fn histogram_bucket_boundaries() {
+        assert_eq!(histogram_counts(&[]), [0; 4]);
+        assert_eq!(histogram_counts(&[0, 9, 10, 99]), [2, 2, 0, 0]);
+        assert_eq!(histogram_counts(&[100, 999, 1000, i64::MAX]), [0, 0, 2, 2]);
+    }
+
+    #[test]
+    fn summary() {
+        let stream = |name: &str, viewers, lang: &str, hours: i64, tags: &[&str]| StreamEntry {
+            lang: lang.to_string(),
+            started_at: Some(Utc::now() - chrono::Duration::hours(hours)),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..test_entry(name, viewers)
+        };
+        let summary = Summary::new(&[
+            stream("Crab", 30, "sv", 2, &["Rust"]),
+            stream("Ferris", 10, "en", 5, &["Rust", "English"]),
+            stream("Corro", 20, "sv", 1, &["Zig"]),
+        ]);
+        assert_eq!(summary.streams, 3);
+        assert_eq!(
+            (summary.viewers, summary.median_viewers, summary.max_viewers),
+            (60, 20, 30)
+        );
+        assert_eq!(
+            summary.langs,
+            [("sv".to_string(), 2), ("en".to_string(), 1)]
+        );
+        let (channel, minutes) = summary.longest.unwrap();
+        assert_eq!(channel, "Ferris");
+        assert_eq!(hours_minutes(minutes), "05:00");
+        assert_eq!(summary.tags[0], ("Rust".to_string(), 2));
+
+        let empty = Summary::new(&[]);
+        assert_eq!((empty.median_viewers, empty.max_viewers), (0, 0));
+        assert!(empty.longest.is_none());
+    }
+}
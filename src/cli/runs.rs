@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::entry::{blank_json, Change};
+use crate::table::{Column, Columns};
+use crate::{AppError, StreamEntry};
+
+use super::history::data_dir;
+
+// -----------------------------------------------------------------------------
+//     - Logged runs -
+// -----------------------------------------------------------------------------
+
+/// One line of the run log: the matches of a search at some point in time
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct LoggedRun {
+    pub(super) time: String,
+    /// What was searched for, so runs of other searches aren't compared
+    pub(super) query: Value,
+    pub(super) streams: Vec<LoggedStream>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct LoggedStream {
+    pub(super) id: String,
+    pub(super) user_id: String,
+    pub(super) user_name: String,
+    pub(super) viewer_count: i64,
+    pub(super) title: String,
+}
+
+impl From<&StreamEntry> for LoggedStream {
+    fn from(entry: &StreamEntry) -> Self {
+        LoggedStream {
+            id: entry.id.clone(),
+            user_id: entry.user_id.clone(),
+            user_name: entry.display_name.clone(),
+            viewer_count: entry.viewer_count,
+            title: entry.title.clone(),
+        }
+    }
+}
+
+pub(super) fn runs_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("runs.jsonl"))
+}
+
+/// Appends the matches of this run to the run log. The file is locked while
+/// writing, so runs finishing at the same time (say, from cron) don't mix.
+pub(super) fn log_run(query: &Value, entries: &[StreamEntry]) -> io::Result<()> {
+    let Some(path) = runs_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let run = LoggedRun {
+        time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        query: query.clone(),
+        streams: entries.iter().map(LoggedStream::from).collect(),
+    };
+    let line = serde_json::to_string(&run)?;
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.lock()?;
+    writeln!(file, "{line}")
+}
+
+/// The last logged run of `query`, if any
+pub(super) fn previous_run(query: &Value) -> Result<Option<LoggedRun>, AppError> {
+    let Some(path) = runs_path() else {
+        return Ok(None);
+    };
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AppError::ReadRuns(e)),
+    };
+    file.lock_shared().map_err(AppError::ReadRuns)?;
+    last_run_of(BufReader::new(file), query)
+}
+
+/// The last run of `query` in a run log
+fn last_run_of(log: impl BufRead, query: &Value) -> Result<Option<LoggedRun>, AppError> {
+    let mut previous = None;
+    for line in log.lines() {
+        let line = line.map_err(AppError::ReadRuns)?;
+        // Lines from other versions or cut short are skipped, like in the search history
+        match serde_json::from_str::<LoggedRun>(&line) {
+            Ok(run) if run.query == *query => previous = Some(run),
+            _ => continue,
+        }
+    }
+    Ok(previous)
+}
+
+// -----------------------------------------------------------------------------
+//     - Diff -
+// -----------------------------------------------------------------------------
+
+/// Marks each entry as new or by how much its viewer count moved since
+/// `previous`, and returns the channels that matched then but no longer do
+pub(super) fn diff(entries: &mut [StreamEntry], previous: &LoggedRun) -> Vec<LoggedStream> {
+    let mut before = previous
+        .streams
+        .iter()
+        .map(|stream| (stream.user_id.as_str(), stream))
+        .collect::<HashMap<_, _>>();
+
+    for entry in entries.iter_mut() {
+        entry.change = Some(match before.remove(entry.user_id.as_str()) {
+            Some(stream) => Change::Viewers(entry.viewer_count - stream.viewer_count),
+            None => Change::New,
+        });
+    }
+
+    // Keep the order of the previous run
+    previous
+        .streams
+        .iter()
+        .filter(|stream| before.contains_key(stream.user_id.as_str()))
+        .cloned()
+        .collect()
+}
+
+impl LoggedStream {
+    /// A row for a channel that no longer matches, with what is known of it
+    pub(super) fn gone_row(&self, columns: &Columns) -> Vec<String> {
+        let cell = |column: Column| match column {
+            Column::Change => "GONE".to_string(),
+            Column::Name => format!("https://twitch.tv/{}", self.user_name),
+            Column::Viewers => match columns.plain {
+                true => self.viewer_count.to_string(),
+                false => format!("{} viewers", self.viewer_count),
+            },
+            Column::Title => self.title.clone(),
+            _ => String::new(),
+        };
+        columns.list.iter().map(|&column| cell(column)).collect()
+    }
+
+    /// What is known of the stream in `--output json`, the rest null
+    pub(super) fn to_json(&self) -> Value {
+        let mut json = blank_json();
+        json["id"] = self.id.clone().into();
+        json["user_id"] = self.user_id.clone().into();
+        json["user_name"] = self.user_name.clone().into();
+        json["title"] = self.title.clone().into();
+        json["viewer_count"] = self.viewer_count.into();
+        json["change"] = "gone".into();
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::entry::test_entry;
+
+    fn logged(name: &str, viewer_count: i64) -> LoggedStream {
+        LoggedStream::from(&test_entry(name, viewer_count))
+    }
+
+    #[test]
+    fn runs_of_the_same_query() {
+        let run = |query: Value, names: &[&str]| {
+            let run = LoggedRun {
+                query,
+                streams: names.iter().map(|name| logged(name, 10)).collect(),
+                ..LoggedRun::default()
+            };
+            serde_json::to_string(&run).unwrap()
+        };
+        let log = [
+            run(json!({ "terms": ["rust"] }), &["First"]),
+            run(json!({ "terms": ["rust"] }), &["Second"]),
+            "{\"time\": \"cut short".to_string(),
+            run(json!({ "terms": ["zig"] }), &["Other"]),
+        ]
+        .join("\n");
+
+        let previous = last_run_of(log.as_bytes(), &json!({ "terms": ["rust"] })).unwrap();
+        assert_eq!(previous.unwrap().streams[0].user_name, "Second");
+        let previous = last_run_of(log.as_bytes(), &json!({ "terms": ["c++"] })).unwrap();
+        assert!(previous.is_none());
+    }
+
+    #[test]
+    fn differences_from_the_previous_run() {
+        let previous = LoggedRun {
+            streams: vec![
+                logged("Gone", 5),
+                logged("Crab", 30),
+                logged("Same", 10),
+                logged("Left", 1),
+            ],
+            ..LoggedRun::default()
+        };
+        let mut entries = vec![
+            test_entry("Ferris", 20),
+            test_entry("Crab", 25),
+            test_entry("Same", 10),
+        ];
+        let gone = diff(&mut entries, &previous);
+
+        let changes = entries.iter().map(|e| e.change.unwrap().to_string());
+        assert_eq!(changes.collect::<Vec<_>>(), ["NEW", "-5", "="]);
+
+        // In the order of the previous run
+        let gone_names = gone.iter().map(|s| s.user_name.as_str());
+        assert_eq!(gone_names.collect::<Vec<_>>(), ["Gone", "Left"]);
+
+        let columns = Columns {
+            list: vec![Column::Change, Column::Name, Column::Viewers, Column::Lang],
+            plain: true,
+            ..Columns::default()
+        };
+        assert_eq!(
+            gone[0].gone_row(&columns),
+            ["GONE", "https://twitch.tv/Gone", "5", ""]
+        );
+        assert_eq!(gone[0].to_json()["change"], "gone");
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use serde_json::json;
+
+use crate::sessions::SessionDb;
+use crate::table::{Align, Table};
+use crate::AppError;
+
+use super::{print_json, print_table, CommonArgs, OutputFormat};
+
+/// Options of the `sessions` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct SessionsArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    /// Only sessions whose title contains all of these
+    term: Vec<String>,
+
+    /// Database written by `--db`
+    #[clap(long, value_name = "PATH")]
+    db: PathBuf,
+
+    /// Only sessions of this channel
+    #[clap(long, value_name = "NAME")]
+    channel: Option<String>,
+
+    /// Show at most this many sessions, most recent first (0 for all)
+    #[clap(long, value_name = "N", default_value_t = 50)]
+    limit: usize,
+}
+
+/// Prints the stream sessions recorded with `--db` that match the query
+pub(super) fn sessions(args: SessionsArgs) -> Result<bool, AppError> {
+    let db = SessionDb::open(&args.db)?;
+    let langs = args.common.languages()?;
+    let sessions = db.find(&args.term, args.channel.as_deref(), &langs, args.limit)?;
+
+    let found = !sessions.is_empty();
+    let time = |t: DateTime<Utc>| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string();
+    if args.common.output() == OutputFormat::Json {
+        let json = sessions
+            .iter()
+            .map(|session| {
+                json!({
+                    "stream_id": session.stream_id,
+                    "channel": session.channel,
+                    "language": session.lang,
+                    "game_name": session.game,
+                    "title": session.title,
+                    "max_viewers": session.max_viewers,
+                    "started_at": session.started_at.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                    "last_seen": session.last_seen.to_rfc3339_opts(SecondsFormat::Secs, true),
+                })
+            })
+            .collect();
+        print_json(json, &args.common)?;
+        return Ok(found);
+    }
+
+    let headers = [
+        "started",
+        "duration",
+        "max_viewers",
+        "lang",
+        "channel",
+        "title",
+    ];
+    let mut table = Table::new(headers.len());
+    table.set_headers(headers.map(str::to_string).to_vec());
+    table.set_align(1, Align::Right);
+    table.set_align(2, Align::Right);
+    for session in sessions {
+        table.push(vec![
+            session.started_at.map(time).unwrap_or_default(),
+            session.duration(),
+            session.max_viewers.to_string(),
+            session.lang,
+            session.channel,
+            session.title,
+        ]);
+    }
+    print_table(&table, &args.common)?;
+
+    Ok(found)
+}
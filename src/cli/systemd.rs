@@ -0,0 +1,244 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::AppError;
+
+/// Where user units go, as systemd looks for them
+fn unit_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("systemd/user"))
+}
+
+/// How the installed search runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Schedule {
+    /// Kept running with `--watch`, restarted when it fails
+    Watch,
+    /// Run once every this many minutes by a timer
+    Timer(u64),
+}
+
+/// Writes the user units running this search as `twitch-search-<name>`,
+/// `args` being its command line less the `--install-systemd` options. The
+/// config file in use is passed on, and relative paths start where this
+/// runs, as they did here. The units are readable by the current user only,
+/// as the command line may hold webhook tokens.
+pub(super) fn install_systemd(
+    name: &str,
+    schedule: Schedule,
+    args: &[String],
+    config: Option<&Path>,
+) -> Result<(), AppError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AppError::Systemd(format!(
+            "unit names are letters, digits, - and _, got {name:?}"
+        )));
+    }
+    let dir = unit_dir().ok_or_else(|| AppError::Systemd("no home directory".to_string()))?;
+    let program = env::current_exe().map_err(|e| AppError::Systemd(e.to_string()))?;
+    let here = env::current_dir().map_err(|e| AppError::Systemd(e.to_string()))?;
+
+    let mut command = vec![program.display().to_string()];
+    command.extend(with_config(args, config));
+    let unit = format!("twitch-search-{name}");
+    let mut units = vec![(
+        format!("{unit}.service"),
+        service(name, schedule, &command, &here),
+    )];
+    if let Schedule::Timer(minutes) = schedule {
+        units.push((format!("{unit}.timer"), timer(name, minutes)));
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| AppError::Systemd(e.to_string()))?;
+    for (file, text) in &units {
+        let path = dir.join(file);
+        write_private(&path, text)
+            .map_err(|e| AppError::Systemd(format!("{}: {e}", path.display())))?;
+        println!("Wrote {}", path.display());
+    }
+    let started = match schedule {
+        Schedule::Watch => format!("{unit}.service"),
+        Schedule::Timer(_) => format!("{unit}.timer"),
+    };
+    println!("Start it, also after reboots, with:");
+    println!("  systemctl --user daemon-reload");
+    println!("  systemctl --user enable --now {started}");
+    println!("Run `loginctl enable-linger` to keep it running while logged out.");
+    Ok(())
+}
+
+/// Writes `text` to `path`, readable by the current user only
+fn write_private(path: &Path, text: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)?.write_all(text.as_bytes())
+}
+
+/// The command line without the options installing the units
+pub(super) fn strip_install_options(args: &[String]) -> Vec<String> {
+    const OPTIONS: [&str; 2] = ["--install-systemd", "--systemd-timer"];
+    let mut kept = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            kept.push(arg.clone());
+            kept.extend(args.cloned());
+            break;
+        }
+        if OPTIONS.contains(&arg.as_str()) {
+            args.next();
+        } else if !OPTIONS
+            .iter()
+            .any(|option| arg.starts_with(&format!("{option}=")))
+        {
+            kept.push(arg.clone());
+        }
+    }
+    kept
+}
+
+/// `args` with `--config` added, ahead of any `--`, unless they have it
+fn with_config(args: &[String], config: Option<&Path>) -> Vec<String> {
+    let mut args = args.to_vec();
+    let given = args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--config" || arg.starts_with("--config="));
+    if let (false, Some(config)) = (given, config.filter(|path| path.exists())) {
+        let at = args
+            .iter()
+            .position(|arg| arg == "--")
+            .unwrap_or(args.len());
+        let config = config
+            .canonicalize()
+            .unwrap_or_else(|_| config.to_path_buf());
+        args.splice(
+            at..at,
+            ["--config".to_string(), config.display().to_string()],
+        );
+    }
+    args
+}
+
+fn service(name: &str, schedule: Schedule, command: &[String], dir: &Path) -> String {
+    let exec = command
+        .iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut unit = format!(
+        "[Unit]\n\
+         Description=twitch-search {name}\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         WorkingDirectory={}\n",
+        dir.display()
+    );
+    match schedule {
+        Schedule::Watch => unit.push_str(&format!(
+            "Type=simple\n\
+             ExecStart={exec}\n\
+             Restart=on-failure\n\
+             RestartSec=30\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n"
+        )),
+        Schedule::Timer(_) => unit.push_str(&format!(
+            "Type=oneshot\n\
+             ExecStart={exec}\n"
+        )),
+    }
+    unit
+}
+
+fn timer(name: &str, minutes: u64) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Run twitch-search {name} every {minutes} minutes\n\
+         \n\
+         [Timer]\n\
+         OnActiveSec=1min\n\
+         OnUnitActiveSec={minutes}min\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n"
+    )
+}
+
+/// An argument of `ExecStart=`, quoted when systemd would split or expand it
+fn quote(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%").replace('$', "$$");
+    if !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';'))
+    {
+        return escaped;
+    }
+    format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn drops_the_install_options() {
+        let args = strings(&[
+            "daemon",
+            "--install-systemd",
+            "rust",
+            "--watch",
+            "60",
+            "--systemd-timer=15",
+            "--",
+            "--install-systemd",
+        ]);
+        assert_eq!(
+            strip_install_options(&args),
+            ["daemon", "--watch", "60", "--", "--install-systemd"]
+        );
+    }
+
+    #[test]
+    fn quotes_for_systemd() {
+        assert_eq!(quote("rust"), "rust");
+        assert_eq!(quote("100%"), "100%%");
+        assert_eq!(quote("game dev"), "\"game dev\"");
+        assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote(""), "\"\"");
+    }
+
+    #[test]
+    fn writes_units() {
+        let command = strings(&["/usr/bin/twitch-search", "--watch", "60", "game dev"]);
+        let unit = service("dev", Schedule::Watch, &command, Path::new("/srv"));
+        assert!(unit.contains("ExecStart=/usr/bin/twitch-search --watch 60 \"game dev\"\n"));
+        assert!(unit.contains("WorkingDirectory=/srv\n"));
+        assert!(unit.contains("WantedBy=default.target"));
+
+        let unit = service("dev", Schedule::Timer(15), &command, Path::new("/srv"));
+        assert!(unit.contains("Type=oneshot"));
+        assert!(!unit.contains("[Install]"));
+        assert!(timer("dev", 15).contains("OnUnitActiveSec=15min"));
+    }
+}
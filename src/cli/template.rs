@@ -0,0 +1,235 @@
+use crate::config::{Template as TemplateConfig, Templates};
+use crate::{AppError, StreamEntry};
+
+/// Stream details a template can name, as `{name}`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Field {
+    Name,
+    Login,
+    Title,
+    Url,
+    Game,
+    Viewers,
+    Lang,
+    Uptime,
+    Tags,
+}
+
+const FIELDS: [(&str, Field); 9] = [
+    ("name", Field::Name),
+    ("login", Field::Login),
+    ("title", Field::Title),
+    ("url", Field::Url),
+    ("game", Field::Game),
+    ("viewers", Field::Viewers),
+    ("lang", Field::Lang),
+    ("uptime", Field::Uptime),
+    ("tags", Field::Tags),
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+/// Text with placeholders like `{name} is live`, filled in per stream. `{{`
+/// and `}}` stand for braces.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(super) struct Template(Vec<Piece>);
+
+impl Template {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| "unclosed {".to_string())?;
+                    let name = &rest[..end];
+                    let field = FIELDS
+                        .iter()
+                        .find(|(known, _)| *known == name)
+                        .map(|&(_, field)| field)
+                        .ok_or_else(|| {
+                            let known = FIELDS.map(|(known, _)| known).join(", ");
+                            format!("unknown placeholder {{{name}}}, expected one of {known}")
+                        })?;
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Field(field));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err("unmatched }, write }} for a brace".to_string()),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+        Ok(Template(pieces))
+    }
+
+    /// The text for `entry`, with the values of the placeholders passed
+    /// through `escape`
+    pub(super) fn render(&self, entry: &StreamEntry, escape: impl Fn(&str) -> String) -> String {
+        let mut text = String::new();
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(literal) => text.push_str(literal),
+                Piece::Field(field) => {
+                    let value = match field {
+                        Field::Name => entry.display_name.clone(),
+                        Field::Login => entry.user_login.clone(),
+                        Field::Title => entry.title.clone(),
+                        Field::Url => entry.url(),
+                        Field::Game => entry.game_name.clone(),
+                        Field::Viewers => entry.viewer_count.to_string(),
+                        Field::Lang => entry.lang.clone(),
+                        Field::Uptime => entry.live_duration.clone(),
+                        Field::Tags => entry.tags.join(", "),
+                    };
+                    text.push_str(&escape(&value));
+                }
+            }
+        }
+        text
+    }
+}
+
+/// A message about a stream going live
+#[derive(Clone, Debug)]
+pub(super) struct Message {
+    pub(super) title: Template,
+    pub(super) body: Template,
+}
+
+/// The messages of each way of telling about streams, from the
+/// `[templates]` table or the built-in ones
+#[derive(Clone, Debug)]
+pub(super) struct Messages {
+    /// Desktop notifications
+    pub(super) notify: Message,
+    /// Discord embeds, the body being their description
+    pub(super) discord: Message,
+    /// Slack lines, which have only a body
+    pub(super) slack: Template,
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Messages::new(&Templates::default()).unwrap()
+    }
+}
+
+impl Messages {
+    pub(super) fn new(templates: &Templates) -> Result<Self, AppError> {
+        let template = |key: &str, text: &Option<String>, default: &str| {
+            Template::parse(text.as_deref().unwrap_or(default))
+                .map_err(|e| AppError::InvalidTemplate(format!("templates.{key}"), e))
+        };
+        let message = |key: &str, config: &TemplateConfig, title: &str, body: &str| {
+            Ok::<_, AppError>(Message {
+                title: template(&format!("{key}.title"), &config.title, title)?,
+                body: template(&format!("{key}.body"), &config.body, body)?,
+            })
+        };
+        if templates.slack.title.is_some() {
+            return Err(AppError::InvalidTemplate(
+                "templates.slack.title".to_string(),
+                "Slack messages have no title, only a body".to_string(),
+            ));
+        }
+        Ok(Messages {
+            notify: message(
+                "notify",
+                &templates.notify,
+                "{name} is live",
+                "{title}\n{viewers} viewers",
+            )?,
+            discord: message("discord", &templates.discord, "{name} is live", "{title}")?,
+            slack: template(
+                "slack.body",
+                &templates.slack.body,
+                "<{url}|{name}> is live: {title} ({viewers} viewers)",
+            )?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+
+    fn entry() -> StreamEntry {
+        StreamEntry {
+            title: "Rust <3".to_string(),
+            tags: vec!["Rust".to_string(), "English".to_string()],
+            ..test_entry("Crab", 42)
+        }
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        let template = Template::parse("{name} ({login}): {title} {{{viewers}}} [{tags}]").unwrap();
+        assert_eq!(
+            template.render(&entry(), str::to_string),
+            "Crab (crab): Rust <3 {42} [Rust, English]"
+        );
+        let escaped = template.render(&entry(), |value| value.replace('<', "&lt;"));
+        assert_eq!(escaped, "Crab (crab): Rust &lt;3 {42} [Rust, English]");
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        assert!(Template::parse("{nmae}").unwrap_err().contains("{nmae}"));
+        assert!(Template::parse("{name").is_err());
+        assert!(Template::parse("name}").is_err());
+    }
+
+    #[test]
+    fn default_notification() {
+        let notify = Messages::default().notify;
+        assert_eq!(
+            notify.title.render(&entry(), str::to_string),
+            "Crab is live"
+        );
+        assert_eq!(
+            notify.body.render(&entry(), str::to_string),
+            "Rust <3\n42 viewers"
+        );
+    }
+
+    #[test]
+    fn messages_from_the_config() {
+        let mut templates = Templates::default();
+        templates.discord.body = Some("{title} in {game}".to_string());
+        let messages = Messages::new(&templates).unwrap();
+        assert_eq!(
+            messages.discord.body.render(&entry(), str::to_string),
+            "Rust <3 in Software and Game Development"
+        );
+        assert_eq!(
+            messages.notify.title.render(&entry(), str::to_string),
+            "Crab is live"
+        );
+
+        templates.slack.title = Some("{name}".to_string());
+        assert!(matches!(
+            Messages::new(&templates),
+            Err(AppError::InvalidTemplate(key, _)) if key == "templates.slack.title"
+        ));
+    }
+}
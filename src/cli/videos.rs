@@ -0,0 +1,194 @@
+use chrono::prelude::*;
+use clap::ValueEnum;
+use serde_json::json;
+
+use crate::api::DEFAULT_GAME_IDS;
+use crate::config::Config;
+use crate::table::{Align, Table};
+use crate::AppError;
+
+use super::{
+    load_config, parse_from_one, print_json, print_table, CommonArgs, OutputFormat, TermArgs,
+};
+
+// Options of the `videos` subcommand
+#[derive(clap::Args, Debug)]
+pub(super) struct VideosArgs {
+    #[clap(flatten)]
+    common: CommonArgs,
+
+    #[clap(flatten)]
+    terms: TermArgs,
+
+    /// Game or category to search, by name or id (can be repeated)
+    #[clap(short, long, multiple_occurrences = true, number_of_values = 1)]
+    game: Vec<String>,
+
+    /// Only videos published within this period
+    #[clap(long, value_enum, default_value = "all")]
+    period: Period,
+
+    /// Kind of video to search
+    #[clap(
+        long = "type",
+        value_enum,
+        value_name = "TYPE",
+        default_value = "archive"
+    )]
+    kind: VideoType,
+
+    /// Fetch at most this many pages of 100 videos per game
+    #[clap(long, default_value_t = 10, value_parser = parse_from_one)]
+    max_pages: usize,
+}
+
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+enum Period {
+    All,
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+enum VideoType {
+    All,
+    /// Past broadcasts
+    Archive,
+    Highlight,
+    Upload,
+}
+
+/// Name of a value in the Helix query, which matches the argument value
+fn query_value(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Searches the titles of past videos, newest first, for when nobody is live
+pub(super) fn videos(mut args: VideosArgs) -> Result<bool, AppError> {
+    let config_path = args.common.config.clone().or_else(Config::default_path);
+    let config = load_config(args.common.config.as_deref())?;
+    args.common.setup(&config, config_path.as_deref())?;
+    args.terms.blocklist = config.blocklist;
+    if args.game.is_empty() {
+        args.game = config.games;
+    }
+
+    let filter = args.terms.title_filter()?;
+    let languages = args.common.languages()?;
+    let client = args.common.client()?;
+    let game_ids = match args.game.is_empty() {
+        true => DEFAULT_GAME_IDS.map(str::to_string).to_vec(),
+        false => args
+            .game
+            .iter()
+            .map(|game| client.resolve_game(game))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let (period, kind) = (query_value(args.period), query_value(args.kind));
+    let mut videos = Vec::new();
+    for game_id in &game_ids {
+        videos.extend(
+            client
+                .fetch_videos(game_id, &period, &kind, args.max_pages)?
+                .into_iter()
+                .filter(|v| languages.is_empty() || languages.contains(&v.language))
+                .filter(|v| filter.matches(&v.title)),
+        );
+    }
+    videos.sort_by(|a, b| b.published_at.cmp(&a.published_at));
+
+    let found = !videos.is_empty();
+    let output = args.common.output();
+    if output == OutputFormat::Json {
+        let json = videos
+            .iter()
+            .map(|v| {
+                json!({
+                    "id": v.id,
+                    "user_login": v.user_login,
+                    "user_name": v.user_name,
+                    "title": v.title,
+                    "language": v.language,
+                    "published_at": v.published_at,
+                    "duration": v.duration,
+                    "view_count": v.view_count,
+                    "url": v.url,
+                })
+            })
+            .collect();
+        print_json(json, &args.common)?;
+        return Ok(found);
+    }
+
+    let mut table = Table::new(7);
+    table.set_align(2, Align::Right);
+    table.set_align(3, Align::Right);
+    table.set_headers(
+        [
+            "lang",
+            "channel",
+            "duration",
+            "views",
+            "published",
+            "url",
+            "title",
+        ]
+        .map(str::to_string)
+        .to_vec(),
+    );
+    for video in videos {
+        let published = video
+            .published_at
+            .parse::<DateTime<Utc>>()
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d").to_string())
+            .unwrap_or(video.published_at);
+        table.push(vec![
+            video.language,
+            video.user_name,
+            video.duration,
+            video.view_count.to_string(),
+            published,
+            video.url,
+            video.title,
+        ]);
+    }
+    print_table(&table, &args.common)?;
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::{Cli, Command};
+
+    fn videos_args(args: &[&str]) -> VideosArgs {
+        let args = ["twitch-search", "videos"]
+            .into_iter()
+            .chain(args.iter().copied());
+        match Cli::parse_from(args).command {
+            Some(Command::Videos(args)) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn query_values() {
+        let args = videos_args(&["rust"]);
+        assert_eq!(query_value(args.period), "all");
+        assert_eq!(query_value(args.kind), "archive");
+        assert_eq!(args.max_pages, 10);
+
+        let args = videos_args(&["--period", "week", "--type", "highlight", "rust"]);
+        assert_eq!(query_value(args.period), "week");
+        assert_eq!(query_value(args.kind), "highlight");
+        assert_eq!(query_value(VideoType::Upload), "upload");
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use chrono::prelude::*;
+use notify_rust::Notification;
+
+use crate::api::Progress;
+use crate::filter::SearchFilter;
+use crate::table::Columns;
+use crate::{AppError, StreamEntry};
+
+use super::digest::Digest;
+use super::sheets::{append, sheet_url};
+use super::template::Message;
+use super::webhook::{announce, announce_digest, Announced};
+use super::{note_matched_terms, Source, StreamsArgs};
+
+/// Re-runs the search every `interval` until interrupted. The first poll prints
+/// every match, later ones only the streams that started or stopped matching.
+/// Every match is posted to `--webhook-url` and added to the `--sheet` once,
+/// starting with the first poll.
+pub(super) fn watch(
+    args: &StreamsArgs,
+    source: &Source,
+    filter: &SearchFilter,
+    columns: &Columns,
+    interval: Duration,
+) -> Result<(), AppError> {
+    // Matches of the previous poll, in API order
+    let mut previous: Option<Vec<StreamEntry>> = None;
+    // Streams posted to the webhook, kept for the whole broadcast so one
+    // briefly not matching (a changed title) isn't posted again
+    let mut announced = args
+        .webhook_url
+        .as_deref()
+        .map(|url| Announced::load_or_warn(url, "posted", |e| eprintln!("Warning: {e}")));
+    // New matches waiting for the end of --digest's window, or of the quiet
+    // hours when they are queued
+    let holds = args.digest.is_some() || args.quiet_hours.as_ref().is_some_and(|quiet| quiet.queue);
+    let window = args.digest.unwrap_or(0);
+    let mut notify_digest = (holds && args.notify).then(|| Digest::new(window));
+    let mut webhook_digest = (holds && announced.is_some()).then(|| Digest::new(window));
+    let mut added = args.sheets.as_ref().filter(|_| args.sheet).map(|sheets| {
+        Announced::load_or_warn(&sheet_url(sheets), "added", |e| eprintln!("Warning: {e}"))
+    });
+    loop {
+        let entries = match source.fetch(args, filter, Progress::None, None) {
+            Ok((entries, _)) => entries,
+            // Keep going through network hiccups once we know the search works
+            Err(e) if previous.is_some() => {
+                eprintln!("{} Warning: poll failed: {e}", timestamp());
+                thread::sleep(interval);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut current = entries
+            .into_iter()
+            .filter(|e| filter.matches(e))
+            .collect::<Vec<_>>();
+        note_matched_terms(&mut current, filter, &columns.list);
+
+        let now = Utc::now();
+        let quiet = args
+            .quiet_hours
+            .as_ref()
+            .filter(|quiet| quiet.contains(now));
+        let dropping = quiet.is_some_and(|quiet| !quiet.queue);
+        let gathering = quiet.is_some() || args.digest.is_some();
+        if let Some(announced) = &mut announced {
+            let result = match &mut webhook_digest {
+                _ if dropping => {
+                    let dropped = announced.fresh(&current);
+                    announced.add(&dropped);
+                    Ok(())
+                }
+                Some(digest) => {
+                    if gathering {
+                        digest.add(announced.fresh(&current), now);
+                    }
+                    let held = quiet.is_none().then(|| digest.take(now)).flatten();
+                    let result = match held {
+                        Some(entries) => announce_digest(
+                            args.webhook_format,
+                            &args.messages,
+                            &entries,
+                            announced,
+                        ),
+                        None => Ok(()),
+                    };
+                    match gathering {
+                        true => result,
+                        false => result.and_then(|()| {
+                            announce(args.webhook_format, &args.messages, &current, announced)
+                        }),
+                    }
+                }
+                None => announce(args.webhook_format, &args.messages, &current, announced),
+            };
+            if let Err(e) = result {
+                eprintln!("{} Warning: {e}", timestamp());
+            }
+            if let Err(e) = announced.store() {
+                eprintln!(
+                    "{} Warning: failed to remember the posted streams: {e}",
+                    timestamp()
+                );
+            }
+        }
+
+        if let (Some(added), Some(sheets)) = (&mut added, &args.sheets) {
+            if let Err(e) = append(sheets, &current, added) {
+                eprintln!("{} Warning: {e}", timestamp());
+            }
+            if let Err(e) = added.store() {
+                eprintln!(
+                    "{} Warning: failed to remember the added streams: {e}",
+                    timestamp()
+                );
+            }
+        }
+
+        match &previous {
+            None => {
+                let mut table = columns.table();
+                for entry in &current {
+                    table.push(entry.clone().format_row(columns));
+                }
+                args.common.print(&table);
+                eprintln!("{} Watching {} streams", timestamp(), current.len());
+            }
+            Some(previous) => {
+                let (started, stopped) = changes(previous, &current);
+                for entry in started {
+                    let row = entry.clone().format_row(columns).join(" | ");
+                    println!("{} + {row}", timestamp());
+                    match &mut notify_digest {
+                        _ if dropping => {}
+                        Some(digest) if gathering => digest.add([entry], now),
+                        _ if args.notify => notify(entry, &args.messages.notify),
+                        _ => {}
+                    }
+                }
+                for entry in stopped {
+                    println!(
+                        "{} - {} (offline) {}",
+                        timestamp(),
+                        entry.url(),
+                        entry.title
+                    );
+                }
+            }
+        }
+
+        let held = notify_digest.as_mut().filter(|_| quiet.is_none());
+        if let Some(entries) = held.and_then(|digest| digest.take(now)) {
+            notify_all(&entries, &args.messages.notify);
+        }
+
+        previous = Some(current);
+        thread::sleep(interval);
+    }
+}
+
+/// Raises a desktop notification about a newly matching stream
+pub(super) fn notify(entry: &StreamEntry, message: &Message) {
+    let result = Notification::new()
+        .appname("twitch-search")
+        .summary(&message.title.render(entry, str::to_string))
+        .body(&message.body.render(entry, str::to_string))
+        .show();
+    if let Err(e) = result {
+        eprintln!("Warning: failed to show notification: {e}");
+    }
+}
+
+/// The streams of `current` that didn't match in `previous`, and those of
+/// `previous` that no longer do
+fn changes<'a>(
+    previous: &'a [StreamEntry],
+    current: &'a [StreamEntry],
+) -> (Vec<&'a StreamEntry>, Vec<&'a StreamEntry>) {
+    let previous_ids = ids(previous);
+    let current_ids = ids(current);
+    let started = current
+        .iter()
+        .filter(|e| !previous_ids.contains(e.id.as_str()))
+        .collect();
+    let stopped = previous
+        .iter()
+        .filter(|e| !current_ids.contains(e.id.as_str()))
+        .collect();
+    (started, stopped)
+}
+
+/// Raises one desktop notification about all of `entries`, a line each
+fn notify_all(entries: &[StreamEntry], message: &Message) {
+    let lines = entries
+        .iter()
+        .map(|entry| message.title.render(entry, str::to_string))
+        .collect::<Vec<_>>();
+    let result = Notification::new()
+        .appname("twitch-search")
+        .summary(&format!("{} streams went live", entries.len()))
+        .body(&lines.join("\n"))
+        .show();
+    if let Err(e) = result {
+        eprintln!("Warning: failed to show notification: {e}");
+    }
+}
+
+fn ids(entries: &[StreamEntry]) -> HashSet<&str> {
+    entries.iter().map(|e| e.id.as_str()).collect()
+}
+
+pub(super) fn timestamp() -> String {
+    Local::now().format("%H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+
+    #[test]
+    fn streams_starting_and_stopping() {
+        let previous = [test_entry("a", 10), test_entry("b", 20)];
+        let current = [test_entry("c", 5), test_entry("b", 25)];
+        let (started, stopped) = changes(&previous, &current);
+        let names = |entries: Vec<&StreamEntry>| {
+            let names = entries.into_iter().map(|e| e.display_name.clone());
+            names.collect::<Vec<_>>()
+        };
+        assert_eq!(names(started), ["c"]);
+        assert_eq!(names(stopped), ["a"]);
+
+        let (started, stopped) = changes(&current, &current);
+        assert!(started.is_empty() && stopped.is_empty());
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::api::configure_agent;
+use crate::{AppError, StreamEntry};
+
+use super::history::data_dir;
+use super::template::Messages;
+
+/// Shape of the messages posted to `--webhook-url`
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub(super) enum WebhookFormat {
+    /// Discord message with an embed per stream
+    Discord,
+    /// Slack message with a line per stream
+    Slack,
+    /// `{"streams": [...]}`, the streams as in `--output json`
+    Json,
+}
+
+impl WebhookFormat {
+    /// The format `url` takes, going by its host, or generic JSON
+    fn guess(url: &str) -> Self {
+        let host = url.split("://").nth(1).unwrap_or(url);
+        if ["discord.com/", "discordapp.com/"]
+            .iter()
+            .any(|domain| host.starts_with(domain))
+        {
+            WebhookFormat::Discord
+        } else if host.starts_with("hooks.slack.com/") {
+            WebhookFormat::Slack
+        } else {
+            WebhookFormat::Json
+        }
+    }
+}
+
+/// Discord takes at most this many embeds per message
+const BATCH_SIZE: usize = 10;
+
+/// Posts the streams among `entries` not in `announced` to its webhook, in
+/// `format` or the one guessed from the address with the text of `messages`,
+/// and adds them to it. A stream id only lasts one broadcast, so each is
+/// announced once per broadcast; on failure, the rest are left for the next
+/// call.
+pub(super) fn announce(
+    format: Option<WebhookFormat>,
+    messages: &Messages,
+    entries: &[StreamEntry],
+    announced: &mut Announced,
+) -> Result<(), AppError> {
+    let url = announced.url.clone();
+    let format = format.unwrap_or_else(|| WebhookFormat::guess(&url));
+    let fresh = announced.fresh(entries);
+    let agent = configure_agent();
+    for batch in fresh.chunks(BATCH_SIZE) {
+        agent
+            .post(&url)
+            .send_json(payload(format, messages, batch))
+            .map_err(|e| AppError::Webhook(Box::new(e)))?;
+        announced.add(batch);
+    }
+    Ok(())
+}
+
+/// Posts the streams among `entries` not in `announced` to its webhook as a
+/// single message, and adds them to it
+pub(super) fn announce_digest(
+    format: Option<WebhookFormat>,
+    messages: &Messages,
+    entries: &[StreamEntry],
+    announced: &mut Announced,
+) -> Result<(), AppError> {
+    let url = announced.url.clone();
+    let format = format.unwrap_or_else(|| WebhookFormat::guess(&url));
+    let fresh = announced.fresh(entries);
+    if fresh.is_empty() {
+        return Ok(());
+    }
+    configure_agent()
+        .post(&url)
+        .send_json(digest_payload(format, messages, &fresh))
+        .map_err(|e| AppError::Webhook(Box::new(e)))?;
+    announced.add(&fresh);
+    Ok(())
+}
+
+fn payload(format: WebhookFormat, messages: &Messages, entries: &[&StreamEntry]) -> Value {
+    match format {
+        WebhookFormat::Discord => {
+            let embeds = entries.iter().map(|entry| {
+                let mut fields = vec![json!({
+                    "name": "Viewers",
+                    "value": entry.viewer_count.to_string(),
+                    "inline": true,
+                })];
+                // Discord rejects empty field values
+                if !entry.game_name.is_empty() {
+                    fields.push(json!({
+                        "name": "Category",
+                        "value": entry.game_name,
+                        "inline": true,
+                    }));
+                }
+                json!({
+                    "title": messages.discord.title.render(entry, str::to_string),
+                    "url": entry.url(),
+                    "description": messages.discord.body.render(entry, str::to_string),
+                    "fields": fields,
+                })
+            });
+            json!({
+                "username": "twitch-search",
+                "embeds": embeds.collect::<Vec<_>>(),
+            })
+        }
+        WebhookFormat::Slack => {
+            let lines = entries
+                .iter()
+                .map(|entry| messages.slack.render(entry, slack_escape));
+            json!({ "text": lines.collect::<Vec<_>>().join("\n") })
+        }
+        WebhookFormat::Json => json!({
+            "streams": entries.iter().map(|entry| entry.to_json()).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// Discord cuts embed descriptions longer than this
+const DISCORD_DESCRIPTION: usize = 4096;
+
+fn digest_payload(format: WebhookFormat, messages: &Messages, entries: &[&StreamEntry]) -> Value {
+    let heading = format!("{} streams went live", entries.len());
+    match format {
+        WebhookFormat::Discord => {
+            let mut description = String::new();
+            for (i, entry) in entries.iter().enumerate() {
+                let line = format!(
+                    "[{}]({}): {}\n",
+                    messages.discord.title.render(entry, str::to_string),
+                    entry.url(),
+                    messages
+                        .discord
+                        .body
+                        .render(entry, str::to_string)
+                        .replace('\n', " ")
+                );
+                // Room for the line and the note about the rest
+                if description.chars().count() + line.chars().count() + 20 > DISCORD_DESCRIPTION {
+                    description.push_str(&format!("and {} more", entries.len() - i));
+                    break;
+                }
+                description.push_str(&line);
+            }
+            json!({
+                "username": "twitch-search",
+                "embeds": [{ "title": heading, "description": description.trim_end() }],
+            })
+        }
+        WebhookFormat::Slack => {
+            let lines = entries
+                .iter()
+                .map(|entry| messages.slack.render(entry, slack_escape));
+            let text = std::iter::once(heading).chain(lines).collect::<Vec<_>>();
+            json!({ "text": text.join("\n") })
+        }
+        WebhookFormat::Json => payload(format, messages, entries),
+    }
+}
+
+/// Escapes the characters Slack reads as markup in message text
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// -----------------------------------------------------------------------------
+//     - Posted streams -
+// -----------------------------------------------------------------------------
+
+/// Posted streams are forgotten after this long, when their broadcasts have
+/// long ended
+const FORGET_AFTER_HOURS: i64 = 48;
+
+/// Posted streams by webhook address, then by stream id, with when they were
+/// posted
+type AnnouncedFile = HashMap<String, HashMap<String, String>>;
+
+pub(super) fn announced_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("announced.json"))
+}
+
+/// The streams posted to one webhook (or added to a sheet). Kept in the
+/// data directory, so runs from cron and restarted watches don't post them
+/// again.
+#[derive(Debug)]
+pub(super) struct Announced {
+    url: String,
+    streams: HashMap<String, String>,
+}
+
+impl Announced {
+    /// Nothing posted to `url` yet
+    pub(super) fn new(url: &str) -> Self {
+        Announced {
+            url: url.to_string(),
+            streams: HashMap::new(),
+        }
+    }
+
+    /// The streams posted to `url` by earlier runs
+    pub(super) fn load(url: &str) -> io::Result<Self> {
+        let mut announced = Announced::new(url);
+        let Some(path) = announced_path() else {
+            return Ok(announced);
+        };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(announced),
+            Err(e) => return Err(e),
+        };
+        file.lock_shared()?;
+        if let Some(streams) = read_announced(&file)?.remove(url) {
+            announced.streams = streams;
+        }
+        Ok(announced)
+    }
+
+    /// Like [`load`](Self::load), but starts afresh if that fails, telling
+    /// `warn` why the streams `done` before (posted, added) are forgotten
+    pub(super) fn load_or_warn(url: &str, done: &str, warn: impl FnOnce(String)) -> Self {
+        Announced::load(url).unwrap_or_else(|e| {
+            warn(format!("failed to read the streams {done} before: {e}"));
+            Announced::new(url)
+        })
+    }
+
+    /// The streams among `entries` not posted yet
+    pub(super) fn fresh<'a>(&self, entries: &'a [StreamEntry]) -> Vec<&'a StreamEntry> {
+        entries
+            .iter()
+            .filter(|entry| !self.streams.contains_key(&entry.id))
+            .collect()
+    }
+
+    /// Remembers that `entries` were posted just now
+    pub(super) fn add(&mut self, entries: &[&StreamEntry]) {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        for entry in entries {
+            self.streams.insert(entry.id.clone(), now.clone());
+        }
+    }
+
+    /// Saves the streams posted to this webhook, along with those other runs
+    /// posted in the meantime, and forgets the ones posted long ago. The file
+    /// is locked meanwhile, like the run log.
+    pub(super) fn store(&self) -> io::Result<()> {
+        let Some(path) = announced_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        file.lock()?;
+
+        let mut all = read_announced(&file)?;
+        self.merge_into(&mut all, Utc::now());
+
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", serde_json::to_string(&all)?)
+    }
+
+    /// Adds the streams posted to this webhook to `all`, and forgets those
+    /// posted to any webhook long before `now`
+    fn merge_into(&self, all: &mut AnnouncedFile, now: DateTime<Utc>) {
+        all.entry(self.url.clone())
+            .or_default()
+            .extend(self.streams.clone());
+        let cutoff = now - chrono::Duration::hours(FORGET_AFTER_HOURS);
+        for streams in all.values_mut() {
+            streams.retain(|_, time| {
+                DateTime::parse_from_rfc3339(time).is_ok_and(|time| time > cutoff)
+            });
+        }
+        all.retain(|_, streams| !streams.is_empty());
+    }
+}
+
+/// What `file` holds, or nothing if it is empty or can't be made sense of
+fn read_announced(mut file: &File) -> io::Result<AnnouncedFile> {
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    Ok(serde_json::from_str(&text).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+
+    #[test]
+    fn formats_from_the_address() {
+        let guess = WebhookFormat::guess;
+        assert_eq!(
+            guess("https://discord.com/api/webhooks/1/abc"),
+            WebhookFormat::Discord
+        );
+        assert_eq!(
+            guess("https://discordapp.com/api/webhooks/1/abc"),
+            WebhookFormat::Discord
+        );
+        assert_eq!(
+            guess("https://hooks.slack.com/services/T0/B0/x"),
+            WebhookFormat::Slack
+        );
+        assert_eq!(
+            guess("https://example.com/discord.com/"),
+            WebhookFormat::Json
+        );
+        assert_eq!(guess("hooks.slack.com/services/x"), WebhookFormat::Slack);
+    }
+
+    #[test]
+    fn announced_streams() {
+        let url = "https://example.com/hook";
+        let entries = [test_entry("Crab", 10), test_entry("Ferris", 20)];
+        let mut announced = Announced::new(url);
+        announced.add(&[&entries[0]]);
+        let fresh = announced.fresh(&entries);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].display_name, "Ferris");
+
+        let now = Utc::now();
+        let ago = |hours| {
+            let time = now - chrono::Duration::hours(hours);
+            time.to_rfc3339_opts(SecondsFormat::Secs, true)
+        };
+        let streams = |posted: &[(&str, String)]| {
+            let posted = posted
+                .iter()
+                .map(|(id, time)| (id.to_string(), time.clone()));
+            posted.collect::<HashMap<_, _>>()
+        };
+        let mut all = AnnouncedFile::from([
+            (
+                url.to_string(),
+                streams(&[("old", ago(49)), ("recent", ago(47))]),
+            ),
+            (
+                "https://example.com/other".to_string(),
+                streams(&[("x", ago(50))]),
+            ),
+            (
+                "https://example.com/odd".to_string(),
+                streams(&[("y", "never".into())]),
+            ),
+        ]);
+        announced.merge_into(&mut all, now);
+
+        // Webhooks with nothing left are dropped
+        assert_eq!(all.len(), 1);
+        let mut ids = all[url].keys().map(String::as_str).collect::<Vec<_>>();
+        ids.sort_unstable();
+        assert_eq!(ids, ["crab-stream", "recent"]);
+    }
+}
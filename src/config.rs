@@ -0,0 +1,237 @@
+//! User preferences from `~/.config/twitch-search/config.toml`
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::AppError;
+
+/// Defaults for the options not given on the command line
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Languages to show, like `--lang`
+    pub lang: Vec<String>,
+    /// Channels to leave out, like `--exclude`
+    pub exclude: Vec<String>,
+    /// Games or categories to search, by name or id, like `--game`
+    pub games: Vec<String>,
+    /// `table`, `json`, `csv`, `tsv`, `html` or `rss`, like `--output`
+    pub output: Option<String>,
+    /// Command to watch streams with, like `--player`
+    pub player: Option<String>,
+    /// Most Helix requests per second, like `--throttle`
+    pub throttle: Option<f64>,
+    /// TOML file with `client_id` and `client_secret`, for when the
+    /// environment doesn't set them. Relative paths start at the config file.
+    pub credentials: Option<PathBuf>,
+    /// Named searches from `[search.<name>]` tables, run with `--profile`
+    pub search: BTreeMap<String, Profile>,
+    /// Google Sheet that `--sheet` appends matches to, from the `[sheets]` table
+    pub sheets: Option<Sheets>,
+    /// Messages of `--notify` and `--webhook-url`, from the `[templates]` table
+    pub templates: Templates,
+    /// Hours without notifications or webhook posts, from the `[quiet]` table
+    pub quiet: Option<Quiet>,
+    /// Colors of `--tui`, from the `[theme]` table
+    pub theme: Theme,
+    /// Titles to leave out of every search, from the `[blocklist]` table
+    pub blocklist: Blocklist,
+}
+
+/// Messages about streams going live, by where they go. Placeholders in
+/// braces are filled in per stream: `{name}`, `{login}`, `{title}`, `{url}`,
+/// `{game}`, `{viewers}`, `{lang}`, `{uptime}` and `{tags}`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Templates {
+    /// Desktop notifications of `--notify`
+    pub notify: Template,
+    /// Discord embeds of `--webhook-url`, the body being their description
+    pub discord: Template,
+    /// Slack lines of `--webhook-url`, which only have a body
+    pub slack: Template,
+}
+
+/// A message, the built-in one where a part is left out
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Template {
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Hours to keep quiet in, like the night
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Quiet {
+    /// `22:00-07:00`, past midnight when the end is before the start
+    pub hours: String,
+    /// Time zone of `hours`, like `Europe/Stockholm`. The system's by default.
+    pub timezone: Option<String>,
+    /// Send what matched during the hours once they are over, in one message,
+    /// rather than never
+    #[serde(default = "Quiet::default_queue")]
+    pub queue: bool,
+}
+
+impl Quiet {
+    fn default_queue() -> bool {
+        true
+    }
+}
+
+/// A Google Sheet shared with a service account
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sheets {
+    /// JSON key file of the service account. Relative paths start at the
+    /// config file.
+    pub key: PathBuf,
+    /// The id in the sheet's address, between `/d/` and `/edit`
+    pub spreadsheet: String,
+    /// Sheet (tab) or range to append the rows to
+    #[serde(default = "Sheets::default_range")]
+    pub range: String,
+}
+
+impl Sheets {
+    fn default_range() -> String {
+        "Sheet1".to_string()
+    }
+}
+
+/// Colors of `--tui`. Styles are words like `bold yellow on blue`: the
+/// modifiers bold, dim, italic, underlined and reversed, then a color name
+/// (`light-red`), number (`208`) or `#rrggbb`, and a background after `on`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// `dark` or `light`, the colors the other keys change
+    pub preset: Option<String>,
+    /// Column headers
+    pub header: Option<String>,
+    /// The selected row
+    pub selection: Option<String>,
+    /// Search terms found in titles
+    pub highlight: Option<String>,
+    /// Badges of streams new since the last refresh
+    pub new: Option<String>,
+    /// Viewer counts that went up since the last refresh
+    pub up: Option<String>,
+    /// Viewer counts that went down since the last refresh
+    pub down: Option<String>,
+    /// Cells of each column, by column name like `--columns`
+    pub columns: BTreeMap<String, String>,
+}
+
+/// Titles always left out, on top of `--not`
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Blocklist {
+    /// Text a title must not contain, compared like `--not`
+    pub titles: Vec<String>,
+    /// Case-insensitive regular expressions a title must not match
+    pub patterns: Vec<String>,
+}
+
+/// A saved search. Lists add to the ones given on the command line, except
+/// `lang` and `games`, which are only used when the command line has none.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub terms: Vec<String>,
+    pub tags: Vec<String>,
+    pub not: Vec<String>,
+    pub exclude: Vec<String>,
+    pub lang: Vec<String>,
+    pub games: Vec<String>,
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_home.join("twitch-search").join("config.toml"))
+    }
+
+    /// Reads the config file at `path`
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let error = |e: String| AppError::ReadConfig(path.display().to_string(), e);
+        let text = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let mut config = toml::from_str::<Config>(&text).map_err(|e| error(e.to_string()))?;
+
+        let relative = |file: &Path| match path.parent() {
+            Some(dir) => dir.join(expand_home(file)),
+            None => expand_home(file),
+        };
+        if let Some(credentials) = &config.credentials {
+            config.credentials = Some(relative(credentials));
+        }
+        if let Some(sheets) = &mut config.sheets {
+            sheets.key = relative(&sheets.key);
+        }
+
+        Ok(config)
+    }
+
+    /// Reads the config file at the default location, if there is one
+    pub fn load_default() -> Result<Self, AppError> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+}
+
+/// Replaces a leading `~` with the home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `text` as a config file in a directory of its own
+    fn load(name: &str, text: &str) -> (PathBuf, Result<Config, AppError>) {
+        let dir = env::temp_dir().join(format!("twitch-search-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, text).unwrap();
+        let config = Config::load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        (dir, config)
+    }
+
+    #[test]
+    fn config_file() {
+        let (dir, config) = load(
+            "config",
+            r#"
+                lang = ["en", "sv"]
+                games = ["Software and Game Development"]
+                output = "json"
+                credentials = "twitch.toml"
+            "#,
+        );
+        let config = config.unwrap();
+        assert_eq!(config.lang, ["en", "sv"]);
+        assert_eq!(config.games, ["Software and Game Development"]);
+        assert_eq!(config.output.as_deref(), Some("json"));
+        assert_eq!(config.credentials, Some(dir.join("twitch.toml")));
+        assert!(config.exclude.is_empty());
+
+        let (_, config) = load("typo", "langs = [\"en\"]\n");
+        let error = config.unwrap_err().to_string();
+        assert!(error.contains("unknown field `langs`"), "{error}");
+    }
+}
@@ -0,0 +1,395 @@
+use std::fmt;
+
+use chrono::prelude::*;
+use serde_json::{json, Value};
+
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::helix::{Page, Stream};
+
+#[derive(Debug, Clone)]
+pub struct StreamEntry {
+    pub id: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub lang: String,
+    pub game_id: String,
+    pub game_name: String,
+    pub display_name: String,
+    pub title: String,
+    pub thumbnail_url: String,
+    pub tags: Vec<String>,
+    pub viewer_count: i64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub live_duration: String,
+    pub user: Option<UserInfo>,
+    /// Number of channels sharing (nearly) this title, including this one
+    pub same_title: usize,
+    /// Search terms found in the stream, when asked for
+    pub matched_terms: Vec<String>,
+    /// How the stream changed since the previous run, with --diff
+    pub change: Option<Change>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UserInfo {
+    pub broadcaster_type: String,
+    pub description: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub content_labels: Vec<String>,
+    pub followers: Option<i64>,
+}
+
+impl UserInfo {
+    /// Viewers per follower. Channels with far more viewers than followers
+    /// are often viewbotted.
+    pub fn viewer_ratio(&self, viewer_count: i64) -> Option<f64> {
+        self.followers
+            .map(|followers| viewer_count as f64 / followers.max(1) as f64)
+    }
+}
+
+/// The streams on a page of Helix `streams` results, and the cursor of the
+/// next page. This is all of the HTTP layer that filtering needs, so callers
+/// with their own (e.g. a browser's `fetch`) can pass the response body here.
+pub fn parse_streams(json: &Value) -> Result<(Vec<StreamEntry>, Option<String>), AppError> {
+    let page = Page::<Stream>::deserialize(json)
+        .map_err(|e| AppError::UnexpectedResponse("streams".to_string(), e))?;
+    let entries = page.data.into_iter().map(Into::into).collect();
+
+    Ok((entries, page.pagination.cursor))
+}
+
+/// How a matching stream differs from the previous run of the same search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// It didn't match then
+    New,
+    /// It matched then too, and now has this many more viewers (or fewer)
+    Viewers(i64),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::New => write!(f, "NEW"),
+            Change::Viewers(0) => write!(f, "="),
+            Change::Viewers(delta) => write!(f, "{delta:+}"),
+        }
+    }
+}
+
+impl StreamEntry {
+    pub fn url(&self) -> String {
+        format!("https://twitch.tv/{}", self.user_login)
+    }
+
+    /// The stream as in `--output json`, see [`json_schema`]
+    pub fn to_json(&self) -> Value {
+        let time =
+            |t: Option<DateTime<Utc>>| t.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true));
+        let user = self.user.as_ref();
+        json!({
+            "schema": JSON_SCHEMA_VERSION,
+            "id": self.id,
+            "user_id": self.user_id,
+            "user_login": self.user_login,
+            "user_name": self.display_name,
+            "game_id": self.game_id,
+            "game_name": self.game_name,
+            "title": self.title,
+            "language": self.lang,
+            "viewer_count": self.viewer_count,
+            "started_at": time(self.started_at),
+            "thumbnail_url": self.thumbnail_url,
+            "tags": self.tags,
+            "url": self.url(),
+            "broadcaster_type": user.map(|u| &u.broadcaster_type),
+            "description": user.map(|u| &u.description),
+            "created_at": time(user.and_then(|u| u.created_at)),
+            "content_classification_labels": user.map(|u| &u.content_labels),
+            "followers": user.and_then(|u| u.followers),
+            "same_title": self.same_title.max(1),
+            "change": self.change.map(|change| match change {
+                Change::New => json!("new"),
+                Change::Viewers(delta) => json!(delta),
+            }),
+            "matched_terms": self.matched_terms,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - JSON schema -
+// -----------------------------------------------------------------------------
+
+/// Version of the stream objects in `--output json`. Raised when a key is
+/// renamed, removed or changes type; new keys may appear without it.
+pub const JSON_SCHEMA_VERSION: u64 = 1;
+
+/// JSON Schema of the stream objects in `--output json`, printed by
+/// `--schema`. Every key is always present, null when unknown.
+pub fn json_schema() -> Value {
+    let string = json!({ "type": "string" });
+    let nullable = |kind: &str| json!({ "type": [kind, "null"] });
+    let time = json!({ "type": ["string", "null"], "format": "date-time" });
+    let properties = json!({
+        "schema": { "const": JSON_SCHEMA_VERSION },
+        "id": string,
+        "user_id": string,
+        "user_login": nullable("string"),
+        "user_name": string,
+        "game_id": nullable("string"),
+        "game_name": nullable("string"),
+        "title": string,
+        "language": nullable("string"),
+        "viewer_count": { "type": "integer" },
+        "started_at": time,
+        "thumbnail_url": nullable("string"),
+        "tags": { "type": ["array", "null"], "items": { "type": "string" } },
+        "url": nullable("string"),
+        "broadcaster_type": {
+            "description": "With channel columns in --extra or --columns, else null",
+            "type": ["string", "null"],
+        },
+        "description": nullable("string"),
+        "created_at": time,
+        "content_classification_labels": {
+            "type": ["array", "null"],
+            "items": { "type": "string" },
+        },
+        "followers": nullable("integer"),
+        "same_title": {
+            "description": "Channels sharing (nearly) this title, this one included",
+            "type": ["integer", "null"],
+            "minimum": 1,
+        },
+        "change": {
+            "description": "With --diff: \"new\", the change in viewers, or \"gone\" for \
+                            channels that no longer match; else null",
+            "oneOf": [
+                { "enum": ["new", "gone", null] },
+                { "type": "integer" },
+            ],
+        },
+        "matched_terms": {
+            "description": "With the matched column, else empty",
+            "type": "array",
+            "items": { "type": "string" },
+        },
+    });
+    let required = properties
+        .as_object()
+        .map(|keys| keys.keys().cloned().collect::<Vec<_>>());
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "twitch-search streams",
+        "description": "Output of `twitch-search --output json`",
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        },
+    })
+}
+
+/// A stream object with every key of the schema, all null but `schema`
+#[cfg(any(feature = "client", test))]
+pub fn blank_json() -> Value {
+    let schema = json_schema();
+    let mut json = schema["items"]["properties"]
+        .as_object()
+        .map(|keys| {
+            keys.keys()
+                .map(|key| (key.clone(), Value::Null))
+                .collect::<serde_json::Map<_, _>>()
+        })
+        .unwrap_or_default();
+    json.insert("schema".to_string(), JSON_SCHEMA_VERSION.into());
+    Value::Object(json)
+}
+
+pub(crate) fn to_instant(started_at: Option<DateTime<Utc>>) -> String {
+    match started_at {
+        Some(val) => {
+            let dur = Utc::now() - val;
+            format!("{:02}:{:02}", dur.num_hours(), dur.num_minutes() % 60)
+        }
+        None => "".to_string(),
+    }
+}
+
+/// A live stream as Helix lists it, for tests: of the channel `name`, which
+/// is also its id and (lowercased) login, titled "Rust" in English and live
+/// since noon on 2026-10-16
+#[cfg(test)]
+pub(crate) fn test_stream(name: &str, viewer_count: i64) -> Value {
+    let login = name.to_lowercase();
+    serde_json::json!({
+        "id": format!("{login}-stream"),
+        "user_id": login,
+        "user_login": login,
+        "user_name": name,
+        "game_id": "1469308723",
+        "game_name": "Software and Game Development",
+        "type": "live",
+        "title": "Rust",
+        "language": "en",
+        "viewer_count": viewer_count,
+        "started_at": "2026-10-16T12:00:00Z",
+        "thumbnail_url": "",
+        "tags": [],
+    })
+}
+
+/// [`test_stream`] as an entry, to change what a test is about with struct
+/// update syntax
+#[cfg(test)]
+pub(crate) fn test_entry(name: &str, viewer_count: i64) -> StreamEntry {
+    let stream = serde_json::from_value::<Stream>(test_stream(name, viewer_count));
+    StreamEntry::from(stream.expect("a valid stream"))
+}
+
+impl From<Stream> for StreamEntry {
+    fn from(stream: Stream) -> Self {
+        let started_at = stream
+            .started_at
+            .and_then(|t| t.parse::<DateTime<Utc>>().ok());
+        StreamEntry {
+            id: stream.id,
+            user_id: stream.user_id,
+            user_login: stream.user_login,
+            lang: stream.language,
+            game_id: stream.game_id,
+            game_name: stream.game_name,
+            display_name: stream.user_name,
+            title: stream.title,
+            thumbnail_url: stream.thumbnail_url,
+            tags: stream.tags,
+            viewer_count: stream.viewer_count,
+            started_at,
+            live_duration: to_instant(started_at),
+            user: None,
+            same_title: 1,
+            matched_terms: vec![],
+            change: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    fn keys(json: &Value) -> Vec<String> {
+        let mut keys = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn json_has_every_schema_key() {
+        let schema = json_schema();
+        let required = schema["items"]["required"].as_array().unwrap();
+        let mut required = required
+            .iter()
+            .map(|key| key.as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        required.sort();
+
+        let json = test_entry("Crab", 10).to_json();
+        assert_eq!(keys(&json), required);
+        assert_eq!(keys(&blank_json()), required);
+        assert_eq!(json["schema"], JSON_SCHEMA_VERSION);
+        assert_eq!(blank_json()["schema"], JSON_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn streams_from_a_page() {
+        let page = json!({
+            "data": [{
+                "id": "1",
+                "user_id": "2",
+                "user_login": "crab",
+                "user_name": "Crab",
+                "game_id": "509658",
+                "game_name": "Just Chatting",
+                "type": "live",
+                "title": "Rust",
+                "language": "en",
+                "viewer_count": 10,
+                "started_at": "2026-10-16T12:00:00Z",
+                "thumbnail_url": "",
+                "tags": ["Rust"],
+            }],
+            "pagination": {"cursor": "abc"},
+        });
+        let (entries, cursor) = parse_streams(&page).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].user_login, "crab");
+        assert_eq!(entries[0].tags, ["Rust"]);
+        assert_eq!(cursor.as_deref(), Some("abc"));
+
+        assert!(parse_streams(&json!({"data": 1})).is_err());
+    }
+
+    #[test]
+    fn json_nulls_for_unknowns() {
+        let json = test_entry("Crab", 10).to_json();
+        assert_eq!(json["followers"], Value::Null);
+        assert_eq!(json["change"], Value::Null);
+        assert_eq!(json["same_title"], 1);
+        assert_eq!(json["started_at"], "2026-10-16T12:00:00Z");
+
+        let json = StreamEntry {
+            change: Some(Change::Viewers(-3)),
+            ..test_entry("Crab", 10)
+        }
+        .to_json();
+        assert_eq!(json["change"], -3);
+    }
+
+    #[test]
+    fn stream_json() {
+        let json = test_entry("Crab", 10).to_json();
+        assert_eq!(json["user_name"], "Crab");
+        assert_eq!(json["language"], "en");
+        assert_eq!(json["viewer_count"], 10);
+        assert_eq!(json["started_at"], "2026-10-16T12:00:00Z");
+        assert_eq!(json["url"], "https://twitch.tv/crab");
+        // Left out unless the channels were hydrated
+        assert!(json["broadcaster_type"].is_null());
+        assert!(json["followers"].is_null());
+        assert_eq!(json["same_title"], 1);
+    }
+
+    #[test]
+    fn streams_missing_optional_fields() {
+        let mut stream = test_stream("Crab", 10);
+        let fields = stream.as_object_mut().unwrap();
+        fields.remove("started_at");
+        fields.remove("thumbnail_url");
+        fields.insert("tags".to_string(), Value::Null);
+        let entry = StreamEntry::from(Stream::deserialize(stream).unwrap());
+        assert_eq!(entry.started_at, None);
+        assert!(entry.tags.is_empty());
+
+        let mut starting = test_stream("Crab", 10);
+        starting["started_at"] = json!("");
+        let entry = StreamEntry::from(Stream::deserialize(starting).unwrap());
+        assert_eq!(entry.started_at, None);
+
+        let mut wrong = test_stream("Crab", 10);
+        wrong["viewer_count"] = json!("ten");
+        assert!(Stream::deserialize(wrong).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AccessTokenError {
+    #[error("Client id missing. Please set the TWITCH_CLIENT_ID environment variable or a credentials file.")]
+    MissingClientId,
+
+    #[error("Client secret missing. Please set the TWITCH_CLIENT_SECRET environment variable or a credentials file.")]
+    MissingClientSecret,
+
+    #[cfg(feature = "client")]
+    #[error("Failed to get acccess token: {0}")]
+    RequestError(Box<ureq::Error>),
+
+    #[error("Failed to read acccess token: {0}")]
+    ReadError(#[from] std::io::Error),
+
+    #[error("Failed to parse acccess token: {0}")]
+    ParseAccessTokenJson(#[from] serde_json::Error),
+
+    #[error("Not logged in. Please run `twitch-search login` or set the TWITCH_USER_TOKEN environment variable.")]
+    MissingUserToken,
+
+    #[error("The access token does not belong to a user.")]
+    NotUserToken,
+
+    #[error("Login failed: {0}")]
+    Login(String),
+
+    #[error("Failed to update the stored user token: {0}")]
+    StoreUserToken(std::io::Error),
+
+    #[error("Failed to read credentials from {0}: {1}")]
+    ReadCredentials(String, String),
+}
+
+#[cfg(feature = "client")]
+impl From<ureq::Error> for AccessTokenError {
+    fn from(e: ureq::Error) -> Self {
+        AccessTokenError::RequestError(Box::new(e))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error(transparent)]
+    AccessToken(#[from] AccessTokenError),
+
+    #[cfg(feature = "client")]
+    #[error("Failed to get streams: {0}")]
+    FetchStreams(Box<ureq::Error>),
+
+    #[cfg(feature = "async")]
+    #[error("Failed to get streams: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Failed to read streams: {0}")]
+    ReadStreams(#[from] std::io::Error),
+
+    #[error("Failed to deserialize json: {0}")]
+    DeserializeJson(#[from] serde_json::Error),
+
+    #[error("Unexpected response from {0}: {1}")]
+    UnexpectedResponse(String, serde_json::Error),
+
+    #[error("Invalid regex {0:?}: {1}")]
+    InvalidRegex(String, regex::Error),
+
+    #[error("Unknown language {0:?}.")]
+    UnknownLanguage(String),
+
+    #[error("No game or category named {0:?}.{hint}", hint = did_you_mean(.1))]
+    UnknownGame(String, Vec<String>),
+
+    #[error("No channel named {0:?}.")]
+    UnknownChannel(String),
+
+    #[error("Stopped paginating: {0}")]
+    RunawayPagination(String),
+
+    #[error("{0} doctor check(s) failed.")]
+    DoctorFailed(usize),
+
+    #[error("{0} problem(s) in the config file.")]
+    InvalidConfig(usize),
+
+    #[error("Invalid {0} in the config file: {1}")]
+    InvalidTemplate(String, String),
+
+    #[error("Invalid {0} in the config file: {1}")]
+    InvalidQuiet(String, String),
+
+    #[error("Invalid {0} in the config file: {1}")]
+    InvalidStyle(String, String),
+
+    #[error("Failed to read or write the ignore list {0}: {1}")]
+    IgnoreList(String, String),
+
+    #[error("Failed to read or write the favorites {0}: {1}")]
+    Favorites(String, String),
+
+    #[error("Failed to back up or restore {0}: {1}")]
+    Backup(String, String),
+
+    #[error("Failed to install the systemd units: {0}")]
+    Systemd(String),
+
+    #[error("Failed to download the report to {0}: {1}")]
+    DownloadReport(String, String),
+
+    #[error("Failed to delete {0}: {1}")]
+    ClearCache(String, std::io::Error),
+
+    #[error("Failed to write raw responses to {0}: {1}")]
+    WriteDump(String, std::io::Error),
+
+    #[error("Failed to read raw responses from {0}: {1}")]
+    ReadDump(String, std::io::Error),
+
+    #[error("Failed to read config {0}: {1}")]
+    ReadConfig(String, String),
+
+    #[error("No search profile named {0:?} in the config file.")]
+    UnknownProfile(String),
+
+    #[error("Failed to read search history: {0}")]
+    ReadHistory(std::io::Error),
+
+    #[error("No previous search in history.")]
+    EmptyHistory,
+
+    #[error("Invalid throttle {0}, expected a finite number of requests per second.")]
+    InvalidThrottle(f64),
+
+    #[error("Failed to read logged runs: {0}")]
+    ReadRuns(std::io::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("Session database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[cfg(feature = "client")]
+    #[error("Failed to post to the webhook: {0}")]
+    Webhook(Box<ureq::Error>),
+
+    #[error("Failed to listen on {0}: {1}")]
+    EventSubListen(String, std::io::Error),
+
+    #[error("Failed to set up HTTPS: {0}")]
+    EventSubTls(String),
+
+    #[error("TWITCH_EVENTSUB_SECRET must be 10 to 100 characters.")]
+    EventSubSecret,
+
+    #[error("EventSub WebSocket {0} failed: {1}")]
+    EventSubWebSocket(String, String),
+
+    #[error("--sheet needs a [sheets] table in the config file.")]
+    NoSheet,
+
+    #[error("Invalid service account key {0}: {1}")]
+    ServiceAccountKey(String, String),
+
+    #[error("Failed to append to the Google Sheet: {0}")]
+    Sheets(String),
+
+    #[error("--output rss is only for stream searches.")]
+    NoFeed,
+
+    #[error("Failed to write results to {0}: {1}")]
+    WriteOutput(String, std::io::Error),
+
+    #[error("Only {1} stream(s) matched, so there is no number {0} to open.")]
+    NoSuchMatch(usize, usize),
+
+    #[error("Failed to open {0} in the browser: {1}")]
+    OpenBrowser(String, std::io::Error),
+
+    #[error("{0} isn't live right now.")]
+    NotLive(String),
+
+    #[error("No live channel matches {0:?}.")]
+    NoLiveMatch(String),
+
+    #[error("The player command is empty.")]
+    EmptyPlayer,
+
+    #[error("Failed to start player {0:?}: {1}")]
+    StartPlayer(String, std::io::Error),
+
+    #[error("Player {0:?} failed with {1}.")]
+    PlayerFailed(String, std::process::ExitStatus),
+}
+
+#[cfg(feature = "client")]
+impl From<ureq::Error> for AppError {
+    fn from(e: ureq::Error) -> Self {
+        AppError::FetchStreams(Box::new(e))
+    }
+}
+
+impl AppError {
+    /// Whether Helix rejected the access token, e.g. because it expired
+    #[cfg(feature = "client")]
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self, AppError::FetchStreams(e) if matches!(**e, ureq::Error::Status(401, _)))
+    }
+
+    /// What the binary exits with after this error: [`EXIT_AUTH`] when
+    /// credentials are missing or rejected, [`EXIT_NETWORK`] when Twitch
+    /// couldn't be reached or failed, and [`EXIT_ERROR`] otherwise
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            #[cfg(feature = "client")]
+            AppError::AccessToken(AccessTokenError::RequestError(e)) => match **e {
+                ureq::Error::Status(400..=499, _) => EXIT_AUTH,
+                _ => EXIT_NETWORK,
+            },
+            AppError::AccessToken(_) => EXIT_AUTH,
+            #[cfg(feature = "client")]
+            AppError::FetchStreams(e) => match **e {
+                ureq::Error::Status(401 | 403, _) => EXIT_AUTH,
+                ureq::Error::Status(429 | 500.., _) | ureq::Error::Transport(_) => EXIT_NETWORK,
+                ureq::Error::Status(..) => EXIT_ERROR,
+            },
+            #[cfg(feature = "async")]
+            AppError::Http(e) => match e.status().map(|s| s.as_u16()) {
+                Some(401 | 403) => EXIT_AUTH,
+                Some(429 | 500..) | None => EXIT_NETWORK,
+                Some(_) => EXIT_ERROR,
+            },
+            _ => EXIT_ERROR,
+        }
+    }
+}
+
+/// " Did you mean ...?" listing `names`, or nothing without any
+fn did_you_mean(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [name] => format!(" Did you mean {name:?}?"),
+        [names @ .., last] => {
+            let names = names
+                .iter()
+                .map(|name| format!("{name:?}"))
+                .collect::<Vec<_>>();
+            format!(" Did you mean {} or {last:?}?", names.join(", "))
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Exit codes -
+// -----------------------------------------------------------------------------
+
+// 0 means something was found, or a command that doesn't search worked.
+// Invalid command lines exit with 2, like clap always has.
+
+/// Any failure without a more specific code
+pub const EXIT_ERROR: u8 = 1;
+/// The search worked, but nothing matched
+pub const EXIT_NO_MATCHES: u8 = 3;
+/// Credentials are missing, or Twitch rejected them
+pub const EXIT_AUTH: u8 = 4;
+/// Twitch couldn't be reached, or kept failing
+pub const EXIT_NETWORK: u8 = 5;
+
+// The codes that differ come from errors of the client
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        let status = |code| {
+            let resp = ureq::Response::new(code, "", "").unwrap();
+            AppError::FetchStreams(Box::new(ureq::Error::Status(code, resp)))
+        };
+        assert_eq!(status(401).exit_code(), EXIT_AUTH);
+        assert_eq!(status(403).exit_code(), EXIT_AUTH);
+        assert_eq!(status(429).exit_code(), EXIT_NETWORK);
+        assert_eq!(status(503).exit_code(), EXIT_NETWORK);
+        assert_eq!(status(404).exit_code(), EXIT_ERROR);
+
+        let missing = AppError::AccessToken(AccessTokenError::MissingUserToken);
+        assert_eq!(missing.exit_code(), EXIT_AUTH);
+        let token_request = |code| {
+            let resp = ureq::Response::new(code, "", "").unwrap();
+            let error = Box::new(ureq::Error::Status(code, resp));
+            AppError::AccessToken(AccessTokenError::RequestError(error))
+        };
+        assert_eq!(token_request(400).exit_code(), EXIT_AUTH);
+        assert_eq!(token_request(502).exit_code(), EXIT_NETWORK);
+
+        let unknown = AppError::UnknownChannel("crab".to_string());
+        assert_eq!(unknown.exit_code(), EXIT_ERROR);
+    }
+}
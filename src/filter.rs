@@ -0,0 +1,1071 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+
+use clap::ValueEnum;
+use regex::{Regex, RegexBuilder};
+
+use crate::entry::StreamEntry;
+use crate::error::AppError;
+use crate::matcher::{fold, Folded, WordBounds};
+
+// -----------------------------------------------------------------------------
+//     - Matching -
+// -----------------------------------------------------------------------------
+
+/// Inclusive viewer count bounds, either of which may be left open
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ViewerLimits {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl ViewerLimits {
+    pub fn contains(&self, viewer_count: i64) -> bool {
+        self.min.is_none_or(|min| viewer_count >= min)
+            && self.max.is_none_or(|max| viewer_count <= max)
+    }
+}
+
+/// Titles left out of every search, see [`Blocklist`](crate::config::Blocklist)
+#[derive(Debug, Default, Clone)]
+pub struct TitleBlocklist {
+    /// Folded text a title must not contain
+    pub titles: Vec<String>,
+    pub patterns: Vec<Regex>,
+}
+
+impl TitleBlocklist {
+    /// Folds `titles` and compiles `patterns` as case-insensitive regular
+    /// expressions
+    pub fn new(titles: &[String], patterns: &[String], normalize: bool) -> Result<Self, AppError> {
+        Ok(TitleBlocklist {
+            titles: titles.iter().map(|t| fold(t, normalize)).collect(),
+            patterns: Terms::regex(patterns, normalize)?.regexes(),
+        })
+    }
+
+    /// Whether `title`, which folds to `folded`, is blocked
+    pub fn blocks(&self, title: &str, folded: &str) -> bool {
+        self.titles.iter().any(|text| folded.contains(text))
+            || self.patterns.iter().any(|pattern| pattern.is_match(title))
+    }
+}
+
+/// Search terms, matched against the title
+#[derive(Debug, Clone)]
+pub struct Terms {
+    pub kind: TermKind,
+    /// Compare texts without accents and compatibility forms, see [`fold`]
+    pub normalize: bool,
+    /// Only match whole words, see [`WordBounds`]. Fuzzy terms ignore this.
+    pub whole_word: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum TermKind {
+    /// Folded substrings (or whole words)
+    Plain(Vec<String>),
+    /// Case-insensitive regular expressions
+    Regex(Vec<Regex>),
+    /// Folded terms that may be misspelled or abbreviated, see [`fuzzy_match`]
+    Fuzzy(Vec<String>),
+}
+
+impl Terms {
+    /// Terms to find as they are, apart from case (and accents with `normalize`)
+    pub fn plain(terms: &[String], normalize: bool) -> Self {
+        let terms = terms.iter().map(|t| fold(t, normalize)).collect();
+        Terms {
+            kind: TermKind::Plain(terms),
+            normalize,
+            whole_word: false,
+        }
+    }
+
+    /// Compiles every term as a case-insensitive regular expression
+    pub fn regex(patterns: &[String], normalize: bool) -> Result<Self, AppError> {
+        patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| AppError::InvalidRegex(pattern.clone(), e))
+            })
+            .collect::<Result<_, _>>()
+            .map(|patterns| Terms {
+                kind: TermKind::Regex(patterns),
+                normalize,
+                whole_word: false,
+            })
+    }
+
+    /// Terms to find even when misspelled or abbreviated
+    pub fn fuzzy(terms: &[String], normalize: bool) -> Self {
+        let terms = terms.iter().map(|t| fold(t, normalize)).collect();
+        Terms {
+            kind: TermKind::Fuzzy(terms),
+            normalize,
+            whole_word: false,
+        }
+    }
+
+    /// Other terms of the same kind, matched the same way
+    pub fn replaced(&self, terms: &[String]) -> Result<Self, AppError> {
+        let replaced = match self.kind {
+            TermKind::Plain(_) => Terms::plain(terms, self.normalize),
+            TermKind::Regex(_) => Terms::regex(terms, self.normalize)?,
+            TermKind::Fuzzy(_) => Terms::fuzzy(terms, self.normalize),
+        };
+        Ok(Terms {
+            whole_word: self.whole_word,
+            ..replaced
+        })
+    }
+}
+
+impl Terms {
+    /// The compiled patterns of regex terms, none for other kinds
+    fn regexes(self) -> Vec<Regex> {
+        match self.kind {
+            TermKind::Regex(patterns) => patterns,
+            TermKind::Plain(_) | TermKind::Fuzzy(_) => vec![],
+        }
+    }
+
+    fn len(&self) -> usize {
+        match &self.kind {
+            TermKind::Plain(terms) | TermKind::Fuzzy(terms) => terms.len(),
+            TermKind::Regex(patterns) => patterns.len(),
+        }
+    }
+
+    /// Where term number `i` occurs in `text`, which folds to `folded`, as
+    /// byte ranges of `text`. Empty terms and patterns give empty ranges.
+    fn term_spans(&self, i: usize, text: &str, folded: &Folded) -> Vec<(usize, usize)> {
+        // Ranges of the folded text, mapped back below
+        let mut folded_spans = Vec::new();
+        let mut spans = Vec::new();
+        match &self.kind {
+            TermKind::Plain(terms) => {
+                let (text, term) = (folded.text.as_str(), terms[i].as_str());
+                match self.whole_word {
+                    true => folded_spans.extend(WordBounds::new(text).find(text, term)),
+                    false => folded_spans
+                        .extend(text.match_indices(term).map(|(at, m)| (at, at + m.len()))),
+                }
+            }
+            TermKind::Regex(patterns) => {
+                let found = |text: &str| {
+                    let bounds = self.whole_word.then(|| WordBounds::new(text));
+                    patterns[i]
+                        .find_iter(text)
+                        .map(|m| (m.start(), m.end()))
+                        .filter(|&span| bounds.as_ref().is_none_or(|b| b.is_whole(span)))
+                        .collect::<Vec<_>>()
+                };
+                // Regexes see the text as it is too, in case they look for accents
+                spans.extend(found(text));
+                if self.normalize {
+                    folded_spans.extend(found(&folded.text));
+                }
+            }
+            TermKind::Fuzzy(terms) => {
+                folded_spans.extend(fuzzy_match(&terms[i], &folded.text).map(|(_, span)| span));
+            }
+        }
+        spans.extend(folded_spans.into_iter().map(|s| folded.original_range(s)));
+        spans
+    }
+
+    /// Whether term number `i` occurs in any of `texts`, which fold to `folded`
+    fn found(&self, i: usize, texts: &[&str], folded: &[Folded]) -> bool {
+        texts
+            .iter()
+            .zip(folded)
+            .any(|(text, folded)| !self.term_spans(i, text, folded).is_empty())
+    }
+
+    fn fold_all(&self, texts: &[&str]) -> Vec<Folded> {
+        texts
+            .iter()
+            .map(|t| Folded::new(t, self.normalize))
+            .collect()
+    }
+
+    /// Whether every term (with `all`) or some term occurs in any of `texts`
+    fn found_in(&self, texts: &[&str], all: bool) -> bool {
+        let folded = self.fold_all(texts);
+        let found = |i: usize| self.found(i, texts, &folded);
+
+        match all {
+            true => (0..self.len()).all(found),
+            false => (0..self.len()).any(found),
+        }
+    }
+
+    /// The terms that occur in any of `texts`, as they are matched: folded, or
+    /// as patterns
+    pub fn matched(&self, texts: &[&str]) -> Vec<String> {
+        let folded = self.fold_all(texts);
+        (0..self.len())
+            .filter(|&i| self.found(i, texts, &folded))
+            .map(|i| match &self.kind {
+                TermKind::Plain(terms) | TermKind::Fuzzy(terms) => terms[i].clone(),
+                TermKind::Regex(patterns) => patterns[i].as_str().to_string(),
+            })
+            .collect()
+    }
+
+    /// Byte ranges of `text` where some term occurs, sorted and merged
+    pub fn spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let folded = Folded::new(text, self.normalize);
+        let mut spans = (0..self.len())
+            .flat_map(|i| self.term_spans(i, text, &folded))
+            .filter(|(start, end)| start < end)
+            .collect::<Vec<_>>();
+
+        spans.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in spans {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// How well `texts` match fuzzy terms, for ranking: the sum of the best
+    /// similarity of each term, each from 0 to 1. Other terms don't rank
+    /// their matches and always score 0.
+    pub fn score(&self, texts: &[&str]) -> f64 {
+        let TermKind::Fuzzy(terms) = &self.kind else {
+            return 0.0;
+        };
+        let folded = texts
+            .iter()
+            .map(|t| fold(t, self.normalize))
+            .collect::<Vec<_>>();
+        terms
+            .iter()
+            .map(|term| {
+                folded
+                    .iter()
+                    .filter_map(|text| fuzzy_match(term, text))
+                    .map(|(score, _)| score)
+                    .fold(0.0, f64::max)
+            })
+            .sum()
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Fuzzy matching -
+// -----------------------------------------------------------------------------
+
+/// Runs of this many words are also compared as one, so "leet code" matches
+/// "leetcode"
+const FUZZY_JOINED_WORDS: usize = 3;
+
+/// Shortest term that matches abbreviations of it, like "nvim" for "neovim"
+const FUZZY_MIN_ABBREVIATION: usize = 3;
+
+/// Finds where `term` (lowercase) best matches `text`: a word or a few words
+/// run together that are a few typos away from it, or that it abbreviates or is
+/// an abbreviation of. Returns the similarity, from 0 to 1, and the byte range
+/// of the words.
+pub fn fuzzy_match(term: &str, text: &str) -> Option<(f64, (usize, usize))> {
+    let term = term
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<Vec<_>>();
+    if term.is_empty() {
+        return Some((1.0, (0, 0)));
+    }
+
+    let words = text
+        .char_indices()
+        .filter(|&(_, c)| c.is_alphanumeric())
+        .fold(Vec::<(usize, usize)>::new(), |mut words, (i, c)| {
+            match words.last_mut() {
+                Some(word) if word.1 == i => word.1 = i + c.len_utf8(),
+                _ => words.push((i, i + c.len_utf8())),
+            }
+            words
+        });
+
+    let mut best: Option<(f64, (usize, usize))> = None;
+    for first in 0..words.len() {
+        let mut candidate = Vec::new();
+        for last in first..words.len().min(first + FUZZY_JOINED_WORDS) {
+            let (start, end) = words[last];
+            candidate.extend(text[start..end].chars().flat_map(char::to_lowercase));
+            if let Some(score) = similarity(&term, &candidate) {
+                if best.is_none_or(|(best, _)| score > best) {
+                    best = Some((score, (words[first].0, end)));
+                }
+            }
+        }
+    }
+    best
+}
+
+/// How alike `term` and `word` are, if alike enough to count as a match
+fn similarity(term: &[char], word: &[char]) -> Option<f64> {
+    let longest = term.len().max(word.len()) as f64;
+
+    // About one typo per four letters, though rarely in the first one
+    let distance = edit_distance(term, word);
+    let typos =
+        (distance <= term.len() / 4 && term[0] == word[0]).then(|| 1.0 - distance as f64 / longest);
+
+    let (short, long) = match term.len() <= word.len() {
+        true => (term, word),
+        false => (word, term),
+    };
+    let abbreviation = (short.len() >= FUZZY_MIN_ABBREVIATION
+        && short.len() * 2 >= long.len()
+        && short[0] == long[0]
+        && is_subsequence(short, long))
+    .then(|| short.len() as f64 / longest);
+
+    match (typos, abbreviation) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions
+/// and swaps of neighbours each count as one edit
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>(); a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = d;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Whether all of `short` appears in `long`, in order
+fn is_subsequence(short: &[char], long: &[char]) -> bool {
+    let mut long = long.iter();
+    short.iter().all(|c| long.any(|l| l == c))
+}
+
+/// Up to `n` of `names` that look most like `name`, best first, for "did
+/// you mean" hints: names containing it, then ones a few typos away
+pub fn closest_names(name: &str, names: &[String], n: usize) -> Vec<String> {
+    let wanted = fold(name, true);
+    let wanted_chars = wanted.chars().collect::<Vec<_>>();
+    if wanted_chars.is_empty() {
+        return vec![];
+    }
+
+    let mut ranked = names
+        .iter()
+        .filter_map(|candidate| {
+            let folded = fold(candidate, true);
+            let distance = match folded.contains(&wanted) {
+                true => 0,
+                false => edit_distance(&wanted_chars, &folded.chars().collect::<Vec<_>>()),
+            };
+            (distance <= wanted_chars.len() / 3 + 1).then_some((distance, candidate))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|&(distance, candidate)| (distance, candidate.len()));
+
+    let mut closest = Vec::new();
+    for (_, candidate) in ranked {
+        if closest.len() == n {
+            break;
+        }
+        if !closest.contains(candidate) {
+            closest.push(candidate.clone());
+        }
+    }
+    closest
+}
+
+/// Parts of a stream the search terms are looked for in
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MatchField {
+    /// Stream title
+    Title,
+    /// Display and login name of the channel
+    Name,
+    /// Stream tags
+    Tags,
+    /// Game or category name
+    Game,
+}
+
+impl StreamEntry {
+    /// The texts of `fields` that search terms are looked for in
+    fn searched_texts(&self, fields: &[MatchField]) -> Vec<&str> {
+        let mut texts = Vec::new();
+        for field in fields {
+            match field {
+                MatchField::Title => texts.push(self.title.as_str()),
+                MatchField::Name => {
+                    texts.extend([&self.display_name, &self.user_login].map(String::as_str))
+                }
+                MatchField::Tags => texts.extend(self.tags.iter().map(String::as_str)),
+                MatchField::Game => texts.push(self.game_name.as_str()),
+            }
+        }
+        texts
+    }
+
+    pub fn title_fits(&self, max_len: Option<usize>) -> bool {
+        max_len.is_none_or(|max_len| self.title.chars().count() <= max_len)
+    }
+}
+
+/// Everything a stream is matched against in one search
+#[derive(Debug, Clone)]
+pub struct SearchFilter {
+    pub terms: Terms,
+    /// Where to look for the terms
+    pub fields: Vec<MatchField>,
+    /// Folded terms that rule out a title containing any of them
+    pub not: Vec<String>,
+    pub blocklist: TitleBlocklist,
+    /// Lowercase channel names to leave out
+    pub exclude: Vec<String>,
+    /// Languages to show, or all if empty
+    pub langs: Vec<String>,
+    /// Lowercase tags a stream must all carry
+    pub tags: Vec<String>,
+    /// Require all terms rather than any
+    pub all: bool,
+    pub viewers: ViewerLimits,
+    pub max_title_len: Option<usize>,
+}
+
+impl SearchFilter {
+    pub fn matches(&self, entry: &StreamEntry) -> bool {
+        // Logins differ from display names written in other scripts
+        if self.exclude.contains(&entry.display_name.to_lowercase())
+            || self.exclude.contains(&entry.user_login)
+        {
+            return false;
+        }
+
+        if !self.viewers.contains(entry.viewer_count) {
+            return false;
+        }
+
+        if !self.langs.is_empty() && !self.langs.contains(&entry.lang) {
+            return false;
+        }
+
+        let has_tag = |tag: &String| entry.tags.iter().any(|t| t.to_lowercase() == *tag);
+        if !self.tags.iter().all(has_tag) {
+            return false;
+        }
+
+        let title = fold(&entry.title, self.terms.normalize);
+        if self.not.iter().any(|term| title.contains(term))
+            || self.blocklist.blocks(&entry.title, &title)
+        {
+            return false;
+        }
+
+        entry.title_fits(self.max_title_len)
+            && self
+                .terms
+                .found_in(&entry.searched_texts(&self.fields), self.all)
+    }
+
+    /// The search terms found in `entry`, see [`Terms::matched`]
+    pub fn matched_terms(&self, entry: &StreamEntry) -> Vec<String> {
+        self.terms.matched(&entry.searched_texts(&self.fields))
+    }
+
+    /// How well `entry` matches fuzzy terms, see [`Terms::score`]
+    pub fn score(&self, entry: &StreamEntry) -> f64 {
+        self.terms.score(&entry.searched_texts(&self.fields))
+    }
+}
+
+/// Search terms for a bare title, as used for videos and clips
+#[derive(Debug, Clone)]
+pub struct TitleFilter {
+    pub terms: Terms,
+    /// Folded terms that rule out a title containing any of them
+    pub not: Vec<String>,
+    pub blocklist: TitleBlocklist,
+    pub all: bool,
+}
+
+impl TitleFilter {
+    pub fn matches(&self, title: &str) -> bool {
+        let folded = fold(title, self.terms.normalize);
+        !self.not.iter().any(|term| folded.contains(term))
+            && !self.blocklist.blocks(title, &folded)
+            && self.terms.found_in(&[title], self.all)
+    }
+}
+
+/// A filter matching `terms` in titles, in any language, for tests
+#[cfg(test)]
+pub(crate) fn test_filter(terms: &[&str]) -> SearchFilter {
+    let terms = terms.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+    SearchFilter {
+        terms: Terms::plain(&terms, true),
+        fields: vec![MatchField::Title],
+        not: vec![],
+        blocklist: TitleBlocklist::default(),
+        exclude: vec![],
+        langs: vec![],
+        tags: vec![],
+        all: false,
+        viewers: ViewerLimits::default(),
+        max_title_len: None,
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Excluded terms -
+// -----------------------------------------------------------------------------
+pub fn exclusions(exclude: Option<Vec<String>>) -> Vec<String> {
+    let mut excluded = match exclude {
+        Some(exclusions) => exclusions.iter().map(|x| x.to_lowercase()).collect(),
+        None => vec![],
+    };
+
+    if let Ok(ignore_list) = env::var("TWITCH_IGNORE") {
+        excluded.extend(ignore_list.split(',').map(str::to_lowercase));
+    }
+
+    excluded
+}
+
+// -----------------------------------------------------------------------------
+//     - Duplicate titles -
+// -----------------------------------------------------------------------------
+
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DedupeTitles {
+    /// Keep only the most watched stream of each group
+    Collapse,
+    /// Remove every stream that shares its title with another channel
+    Drop,
+}
+
+/// Reduces a title to its lowercase words, so titles differing only in
+/// punctuation, emotes or spacing compare equal.
+fn title_key(title: &str) -> Vec<char> {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect()
+}
+
+/// Titles count as near-identical with at most one edit per this many
+/// characters, so "Day 12 of rust" and "Day 13 of rust" are, but short
+/// titles have to match exactly
+const CHARS_PER_TITLE_EDIT: usize = 10;
+
+/// Letter counts of a title key. Half the difference between two of them is
+/// a cheap lower bound of the edit distance, ruling out most pairs.
+fn letter_counts(key: &[char]) -> [usize; 37] {
+    let mut counts = [0; 37];
+    for &c in key {
+        let bucket = match c {
+            'a'..='z' => c as usize - 'a' as usize,
+            '0'..='9' => 26 + c as usize - '0' as usize,
+            _ => 36,
+        };
+        counts[bucket] += 1;
+    }
+    counts
+}
+
+/// Puts each entry in a group of near-identical titles, returning the group
+/// number of every entry. Each title joins the first group whose first title
+/// it is close enough to.
+fn title_groups(entries: &[StreamEntry]) -> Vec<usize> {
+    let mut groups: Vec<(Vec<char>, [usize; 37])> = Vec::new();
+    let mut exact: HashMap<Vec<char>, usize> = HashMap::new();
+    entries
+        .iter()
+        .map(|entry| {
+            let key = title_key(&entry.title);
+            if let Some(&group) = exact.get(&key) {
+                return group;
+            }
+            let counts = letter_counts(&key);
+            let similar = groups.iter().position(|(first, first_counts)| {
+                let max = key.len().max(first.len()) / CHARS_PER_TITLE_EDIT;
+                let difference = counts
+                    .iter()
+                    .zip(first_counts)
+                    .map(|(a, b)| a.abs_diff(*b))
+                    .sum::<usize>();
+                key.len().abs_diff(first.len()) <= max
+                    && difference / 2 <= max
+                    && edit_distance(&key, first) <= max
+            });
+            let group = similar.unwrap_or_else(|| {
+                groups.push((key.clone(), counts));
+                groups.len() - 1
+            });
+            exact.insert(key, group);
+            group
+        })
+        .collect()
+}
+
+/// Marks entries whose title is shared by other channels, word for word or
+/// nearly so, a common sign of bots and restreams, and optionally collapses
+/// or drops them.
+pub fn detect_duplicate_titles(entries: &mut Vec<StreamEntry>, dedupe: Option<DedupeTitles>) {
+    let groups = title_groups(entries);
+    let mut channels: HashMap<usize, HashSet<&str>> = HashMap::new();
+    for (entry, &group) in entries.iter().zip(&groups) {
+        channels.entry(group).or_default().insert(&entry.user_id);
+    }
+    let counts = groups
+        .iter()
+        .map(|group| channels[group].len())
+        .collect::<Vec<_>>();
+    for (entry, count) in entries.iter_mut().zip(counts) {
+        entry.same_title = count;
+    }
+
+    match dedupe {
+        None => {}
+        Some(DedupeTitles::Drop) => entries.retain(|e| e.same_title == 1),
+        Some(DedupeTitles::Collapse) => {
+            let mut best: HashMap<usize, (i64, &str)> = HashMap::new();
+            for (entry, group) in entries.iter().zip(groups) {
+                let top = best
+                    .entry(group)
+                    .or_insert((entry.viewer_count, &entry.user_id));
+                if entry.viewer_count > top.0 {
+                    *top = (entry.viewer_count, &entry.user_id);
+                }
+            }
+            let keep = best
+                .into_values()
+                .map(|(_, user_id)| user_id.to_string())
+                .collect::<HashSet<_>>();
+            entries.retain(|e| keep.contains(&e.user_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+
+    #[test]
+    fn long_titles() {
+        let titled = |title: &str| StreamEntry {
+            title: title.to_string(),
+            ..test_entry("Crab", 10)
+        };
+        // Counted in characters rather than bytes
+        assert!(titled("Rüst Rüst").title_fits(Some(10)));
+        assert!(titled("Rust 12345").title_fits(Some(10)));
+        assert!(!titled("Rust 123456").title_fits(Some(10)));
+        assert!(titled(&"Rust ".repeat(40)).title_fits(None));
+    }
+
+    #[test]
+    fn duplicate_titles() {
+        let titled = |name: &str, viewers: i64, title: &str| StreamEntry {
+            title: title.to_string(),
+            ..test_entry(name, viewers)
+        };
+        let entries = vec![
+            titled("Bot1", 5, "Day 12 of learning Rust!"),
+            titled("Solo", 50, "Rust"),
+            titled("Bot2", 9, "day 13 of learning rust"),
+            titled("Bot3", 7, "DAY 12 OF LEARNING RUST"),
+            titled("Short", 8, "Rest"),
+        ];
+        let same_titles = |dedupe| {
+            let mut entries = entries.clone();
+            detect_duplicate_titles(&mut entries, dedupe);
+            entries
+                .iter()
+                .map(|e| (e.display_name.clone(), e.same_title))
+                .collect::<Vec<_>>()
+        };
+        let owned = |pairs: &[(&str, usize)]| {
+            pairs
+                .iter()
+                .map(|&(name, n)| (name.to_string(), n))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            same_titles(None),
+            owned(&[
+                ("Bot1", 3),
+                ("Solo", 1),
+                ("Bot2", 3),
+                ("Bot3", 3),
+                ("Short", 1)
+            ])
+        );
+        assert_eq!(
+            same_titles(Some(DedupeTitles::Drop)),
+            owned(&[("Solo", 1), ("Short", 1)])
+        );
+        assert_eq!(
+            same_titles(Some(DedupeTitles::Collapse)),
+            owned(&[("Solo", 1), ("Bot2", 3), ("Short", 1)])
+        );
+    }
+
+    fn titled(name: &str, title: &str) -> StreamEntry {
+        StreamEntry {
+            title: title.to_string(),
+            ..test_entry(name, 10)
+        }
+    }
+
+    fn spans(terms: &Terms, text: &str) -> Vec<(usize, usize)> {
+        let folded = Folded::new(text, terms.normalize);
+        terms.term_spans(0, text, &folded)
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn any_or_all_terms() {
+        let only_rust = titled("Crab", "Writing Rust today");
+        let both = titled("Crab", "Rust and Zig");
+        let any = test_filter(&["rust", "zig"]);
+        let all = SearchFilter {
+            all: true,
+            ..any.clone()
+        };
+
+        assert!(any.matches(&only_rust));
+        assert!(any.matches(&both));
+        assert!(!all.matches(&only_rust));
+        assert!(all.matches(&both));
+    }
+
+    #[test]
+    fn whole_words() {
+        let mut word = test_filter(&["rust"]);
+        word.terms.whole_word = true;
+        assert!(word.matches(&titled("Crab", "Rust, today")));
+        assert!(!word.matches(&titled("Crab", "Rusty code")));
+        assert!(test_filter(&["rust"]).matches(&titled("Crab", "Rusty code")));
+        assert!(word.matches(&titled("Crab", "Rust's borrow checker")));
+
+        let word = |term| {
+            let mut word = test_filter(&[term]);
+            word.terms.whole_word = true;
+            word
+        };
+        for (term, title) in [
+            ("f1", "F1 race"),
+            ("c++", "Learning C++"),
+            ("100", "100% run"),
+        ] {
+            assert!(word(term).matches(&titled("Crab", title)), "{term}");
+        }
+        assert!(!word("100").matches(&titled("Crab", "1000 subs")));
+    }
+
+    #[test]
+    fn blocklisted_titles() {
+        let blocklist = TitleBlocklist::new(
+            &strings(&["Giveaway"]),
+            &strings(&[r"free \w+ skins"]),
+            true,
+        )
+        .unwrap();
+        let search = SearchFilter {
+            blocklist,
+            ..test_filter(&["rust"])
+        };
+        assert!(search.matches(&titled("Crab", "Rust")));
+        assert!(!search.matches(&titled("Crab", "Rust GIVEAWÁY")));
+        assert!(!search.matches(&titled("Crab", "Rust: FREE CS skins")));
+    }
+
+    #[test]
+    fn excluded_channels() {
+        let search = SearchFilter {
+            exclude: strings(&["crab"]),
+            ..test_filter(&["rust"])
+        };
+        assert!(!search.matches(&titled("Crab", "Rust")));
+        assert!(search.matches(&titled("Ferris", "Rust")));
+        let by_login = StreamEntry {
+            display_name: "カニ".to_string(),
+            ..titled("Crab", "Rust")
+        };
+        assert!(!search.matches(&by_login));
+    }
+
+    #[test]
+    fn languages() {
+        let search = SearchFilter {
+            langs: strings(&["en", "sv"]),
+            ..test_filter(&["rust"])
+        };
+        let speaking = |lang: &str| StreamEntry {
+            lang: lang.to_string(),
+            ..titled("Crab", "Rust")
+        };
+        assert!(search.matches(&speaking("sv")));
+        assert!(!search.matches(&speaking("de")));
+        assert!(test_filter(&["rust"]).matches(&speaking("de")));
+    }
+
+    #[test]
+    fn viewer_limits() {
+        let limits = ViewerLimits {
+            min: Some(10),
+            max: Some(100),
+        };
+        assert!(!limits.contains(9));
+        assert!(limits.contains(10));
+        assert!(limits.contains(100));
+        assert!(!limits.contains(101));
+        assert!(ViewerLimits::default().contains(0));
+
+        let search = SearchFilter {
+            viewers: ViewerLimits {
+                min: Some(20),
+                max: None,
+            },
+            ..test_filter(&["rust"])
+        };
+        let crab = |viewers| StreamEntry {
+            title: "Rust".to_string(),
+            ..test_entry("Crab", viewers)
+        };
+        assert!(!search.matches(&crab(19)));
+        assert!(search.matches(&crab(20)));
+    }
+
+    #[test]
+    fn regex_terms() {
+        let search = SearchFilter {
+            terms: Terms::regex(&strings(&[r"day \d+"]), false).unwrap(),
+            ..test_filter(&[])
+        };
+        assert!(search.matches(&titled("Crab", "DAY 12 of Rust")));
+        assert!(!search.matches(&titled("Crab", "Day twelve")));
+        let mut word = search;
+        word.terms.whole_word = true;
+        assert!(!word.matches(&titled("Crab", "Today 12")));
+        assert!(matches!(
+            Terms::regex(&strings(&["(rust"]), false),
+            Err(AppError::InvalidRegex(pattern, _)) if pattern == "(rust"
+        ));
+    }
+
+    #[test]
+    fn regex_term_spans() {
+        let terms = Terms::regex(&strings(&[r"day \d+"]), false).unwrap();
+        assert_eq!(spans(&terms, "Day 12 of 100"), vec![(0, 6)]);
+    }
+
+    #[test]
+    fn terms_in_other_fields() {
+        let tagged = StreamEntry {
+            tags: strings(&["Rust", "English"]),
+            ..titled("RustyCrab", "Coding")
+        };
+        assert!(!test_filter(&["rust"]).matches(&tagged));
+
+        let in_field = |field| SearchFilter {
+            fields: vec![field],
+            ..test_filter(&["rust"])
+        };
+        assert!(in_field(MatchField::Name).matches(&tagged));
+        assert!(in_field(MatchField::Tags).matches(&tagged));
+        assert!(!in_field(MatchField::Game).matches(&tagged));
+        let by_login = StreamEntry {
+            display_name: "カニ".to_string(),
+            ..tagged.clone()
+        };
+        assert!(in_field(MatchField::Name).matches(&by_login));
+    }
+
+    #[test]
+    fn required_tags() {
+        let tagged = StreamEntry {
+            tags: strings(&["Rust", "English"]),
+            ..titled("Crab", "Rust")
+        };
+        let with_tags = |tags: &[&str]| SearchFilter {
+            tags: strings(tags),
+            ..test_filter(&["rust"])
+        };
+        assert!(with_tags(&["rust"]).matches(&tagged));
+        assert!(with_tags(&["rust", "english"]).matches(&tagged));
+        assert!(!with_tags(&["rust", "chill"]).matches(&tagged));
+        assert!(!with_tags(&["rus"]).matches(&tagged));
+    }
+
+    #[test]
+    fn not_terms_rule_out_titles() {
+        let search = SearchFilter {
+            not: vec!["giveaway".to_string()],
+            ..test_filter(&["rust"])
+        };
+        assert!(search.matches(&titled("Crab", "Rust")));
+        assert!(!search.matches(&titled("Crab", "Rust GIVEAWAY")));
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance(&chars("rust"), &chars("rust")), 0);
+        assert_eq!(edit_distance(&chars("rust"), &chars("rsut")), 1);
+        assert_eq!(edit_distance(&chars("rust"), &chars("rusty")), 1);
+        assert_eq!(edit_distance(&chars("rust"), &chars("rst")), 1);
+        assert_eq!(edit_distance(&chars("rust"), &chars("bust")), 1);
+        assert_eq!(edit_distance(&chars(""), &chars("abc")), 3);
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+    }
+
+    #[test]
+    fn similarities() {
+        let similarity = |term, word| similarity(&chars(term), &chars(word));
+        assert_eq!(similarity("rust", "rust"), Some(1.0));
+        assert_eq!(similarity("rust", "rsut"), Some(0.75));
+        // Too many typos for the length, or in the first letter
+        assert_eq!(similarity("rust", "rsat"), None);
+        assert_eq!(similarity("rust", "bust"), None);
+        // Abbreviations either way, if at least half as long
+        assert_eq!(similarity("nvim", "neovim"), Some(4.0 / 6.0));
+        assert_eq!(similarity("neovim", "nvim"), Some(4.0 / 6.0));
+        assert_eq!(similarity("nv", "neovim"), None);
+        assert_eq!(similarity("vim", "neovim"), None);
+    }
+
+    #[test]
+    fn fuzzy_typos_and_abbreviations() {
+        let text = "Learning programming in Rust";
+        let (score, (start, end)) = fuzzy_match("rsut", text).unwrap();
+        assert_eq!(&text[start..end], "Rust");
+        assert!(score < 1.0);
+
+        assert_eq!(fuzzy_match("rust", text).unwrap().0, 1.0);
+        let (_, (start, end)) = fuzzy_match("prgrmming", text).unwrap();
+        assert_eq!(&text[start..end], "programming");
+
+        // The first letter has to be right
+        assert_eq!(fuzzy_match("just", "rust"), None);
+        assert_eq!(fuzzy_match("python", text), None);
+    }
+
+    #[test]
+    fn closest_category_names() {
+        let names = strings(&[
+            "Minecraft",
+            "Just Chatting",
+            "Fortnite",
+            "Minecraft Dungeons",
+            "Music",
+        ]);
+        assert_eq!(
+            closest_names("minecarft", &names, 3),
+            strings(&["Minecraft"])
+        );
+        assert_eq!(
+            closest_names("minecraft", &names, 3),
+            strings(&["Minecraft", "Minecraft Dungeons"])
+        );
+        assert_eq!(
+            closest_names("fortnight", &names, 3),
+            strings(&["Fortnite"])
+        );
+        assert_eq!(closest_names("chess", &names, 3), Vec::<String>::new());
+        assert_eq!(closest_names("", &names, 3), Vec::<String>::new());
+    }
+
+    #[test]
+    fn fuzzy_joined_words() {
+        let text = "Grinding leet code";
+        let (_, (start, end)) = fuzzy_match("leetcode", text).unwrap();
+        assert_eq!(&text[start..end], "leet code");
+    }
+
+    #[test]
+    fn fuzzy_ranking() {
+        let terms = Terms::fuzzy(&strings(&["rust"]), true);
+        assert_eq!(terms.score(&["Learning Rust"]), 1.0);
+        assert_eq!(terms.score(&["Lerning Rsut", "rust"]), 1.0);
+        assert!(terms.score(&["Rsut"]) < terms.score(&["Rust"]));
+        assert_eq!(terms.score(&["Zig"]), 0.0);
+        assert_eq!(
+            Terms::plain(&strings(&["rust"]), true).score(&["Rust"]),
+            0.0
+        );
+    }
+
+    #[test]
+    fn accents_fold_when_normalizing() {
+        assert!(test_filter(&["cafe"]).matches(&titled("Crab", "Café stream")));
+        let exact = SearchFilter {
+            terms: Terms::plain(&strings(&["cafe"]), false),
+            ..test_filter(&[])
+        };
+        assert!(!exact.matches(&titled("Crab", "Café stream")));
+    }
+
+    #[test]
+    fn plain_term_spans() {
+        let terms = Terms::plain(&strings(&["rust"]), true);
+        let text = "RUST and rusty";
+        assert_eq!(spans(&terms, text), vec![(0, 4), (9, 13)]);
+
+        let whole = Terms {
+            whole_word: true,
+            ..terms
+        };
+        assert_eq!(spans(&whole, text), vec![(0, 4)]);
+    }
+
+    #[test]
+    fn term_spans_in_the_original_text() {
+        // "É" is two bytes, but folds to one
+        let terms = Terms::plain(&strings(&["ecole"]), true);
+        let text = "À l'École";
+        let (start, end) = spans(&terms, text)[0];
+        assert_eq!(&text[start..end], "École");
+    }
+
+    #[test]
+    fn overlapping_spans_merge() {
+        let terms = Terms::plain(&strings(&["rust", "stream", "zig"]), true);
+        let text = "Ruststream and Zig";
+        assert_eq!(terms.spans(text), vec![(0, 10), (15, 18)]);
+    }
+}
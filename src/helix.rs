@@ -0,0 +1,225 @@
+//! Typed Helix responses, holding only the fields we use
+
+use serde::{Deserialize, Deserializer};
+
+/// A page of results from a Helix list endpoint
+#[derive(Debug, Deserialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub pagination: Pagination,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Pagination {
+    /// Absent on the last page
+    pub cursor: Option<String>,
+}
+
+/// An entry of `streams`
+#[derive(Debug, Deserialize)]
+pub struct Stream {
+    pub id: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub game_id: String,
+    pub game_name: String,
+    pub title: String,
+    pub language: String,
+    pub viewer_count: i64,
+    /// Missing or empty for a moment while a stream is starting
+    #[serde(default)]
+    pub started_at: Option<String>,
+    #[serde(default)]
+    pub thumbnail_url: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub tags: Vec<String>,
+}
+
+/// An entry of `games`, `games/top` and `search/categories`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Game {
+    pub id: String,
+    pub name: String,
+}
+
+/// An entry of `users/blocks`
+#[derive(Debug, Deserialize)]
+pub struct BlockedUser {
+    pub user_id: String,
+    pub user_login: String,
+    pub display_name: String,
+}
+
+/// An entry of `search/channels`
+#[derive(Debug, Deserialize)]
+pub struct SearchedChannel {
+    pub broadcaster_login: String,
+    pub display_name: String,
+    pub broadcaster_language: String,
+    pub game_name: String,
+    pub title: String,
+    pub is_live: bool,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub tags: Vec<String>,
+}
+
+/// An entry of `videos`
+#[derive(Debug, Deserialize)]
+pub struct Video {
+    pub id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub title: String,
+    pub published_at: String,
+    pub url: String,
+    pub view_count: i64,
+    pub language: String,
+    /// Like `3h8m33s`
+    pub duration: String,
+}
+
+/// An entry of `clips`
+#[derive(Debug, Deserialize)]
+pub struct Clip {
+    pub id: String,
+    pub url: String,
+    pub broadcaster_name: String,
+    pub creator_name: String,
+    pub title: String,
+    pub language: String,
+    pub view_count: i64,
+    pub created_at: String,
+    /// In seconds
+    pub duration: f64,
+}
+
+/// The answer of creating a clip, which Twitch finishes a few seconds later
+#[derive(Debug, Deserialize)]
+pub struct CreatedClip {
+    pub id: String,
+    /// Where the clip's title and length can be changed
+    pub edit_url: String,
+}
+
+/// An entry of `bits/leaderboard`
+#[derive(Debug, Deserialize)]
+pub struct BitsLeader {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub rank: i64,
+    /// Bits cheered in the period
+    pub score: i64,
+}
+
+/// The answer of `bits/leaderboard`
+#[derive(Debug, Deserialize)]
+pub struct BitsLeaderboard {
+    pub data: Vec<BitsLeader>,
+    /// Empty for the `all` period
+    pub date_range: DateRange,
+}
+
+/// A period of a report, in RFC 3339, or empty strings for all time
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DateRange {
+    pub started_at: String,
+    pub ended_at: String,
+}
+
+/// An entry of `subscriptions`
+#[derive(Debug, Deserialize)]
+pub struct Subscriber {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    /// `1000`, `2000` or `3000`
+    pub tier: String,
+    pub is_gift: bool,
+    /// Empty unless gifted
+    #[serde(default)]
+    pub gifter_login: String,
+}
+
+/// A page of `subscriptions`, with the totals of the channel
+#[derive(Debug, Deserialize)]
+pub struct Subscribers {
+    pub data: Vec<Subscriber>,
+    #[serde(default)]
+    pub pagination: Pagination,
+    /// Subscribers, the broadcaster included
+    pub total: i64,
+    /// Subscriber points, counting tier 2 twice and tier 3 six times
+    pub points: i64,
+}
+
+/// An entry of `analytics/games`
+#[derive(Debug, Deserialize)]
+pub struct GameAnalytics {
+    pub game_id: String,
+    /// Where to download the CSV report, for a few minutes
+    #[serde(rename = "URL")]
+    pub url: String,
+    pub date_range: DateRange,
+}
+
+/// An entry of `users`
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub id: String,
+    pub broadcaster_type: String,
+    pub description: String,
+    pub created_at: String,
+}
+
+/// An entry of `channels`
+#[derive(Debug, Deserialize)]
+pub struct Channel {
+    pub broadcaster_id: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub content_classification_labels: Vec<String>,
+}
+
+/// The parts of `channels/followers` we ask for
+#[derive(Debug, Deserialize)]
+pub struct Followers {
+    pub total: i64,
+}
+
+/// An entry of `eventsub/subscriptions`
+#[derive(Debug, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    pub status: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub condition: Condition,
+    pub transport: Transport,
+}
+
+/// What an EventSub subscription is about, holding the keys we use
+#[derive(Debug, Deserialize)]
+pub struct Condition {
+    #[serde(default)]
+    pub broadcaster_user_id: Option<String>,
+}
+
+/// How EventSub delivers events: `webhook` to a callback, or `websocket`
+#[derive(Debug, Deserialize)]
+pub struct Transport {
+    pub method: String,
+    #[serde(default)]
+    pub callback: Option<String>,
+}
+
+/// Twitch sends null rather than an empty list for some fields
+fn null_as_empty<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
@@ -0,0 +1,117 @@
+use crate::error::AppError;
+
+/// Broadcaster languages offered by Twitch
+const LANGUAGES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("asl", "American Sign Language"),
+    ("bg", "Bulgarian"),
+    ("ca", "Catalan"),
+    ("cs", "Czech"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("el", "Greek"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fi", "Finnish"),
+    ("fr", "French"),
+    ("he", "Hebrew"),
+    ("hi", "Hindi"),
+    ("hu", "Hungarian"),
+    ("id", "Indonesian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("ms", "Malay"),
+    ("nl", "Dutch"),
+    ("no", "Norwegian"),
+    ("other", "Other"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ro", "Romanian"),
+    ("ru", "Russian"),
+    ("sk", "Slovak"),
+    ("sv", "Swedish"),
+    ("th", "Thai"),
+    ("tl", "Tagalog"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("vi", "Vietnamese"),
+    ("zh", "Chinese"),
+    ("zh-hk", "Cantonese"),
+];
+
+pub fn language_name(code: &str) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, name)| *name)
+}
+
+/// Turns a language code or (possibly misspelled) name into a code
+pub fn resolve_language(input: &str) -> Result<String, AppError> {
+    let lower = input.trim().to_lowercase();
+    if lower.is_empty() {
+        return Err(AppError::UnknownLanguage(input.to_string()));
+    }
+
+    if let Some((code, _)) = LANGUAGES.iter().find(|(code, _)| *code == lower) {
+        return Ok(code.to_string());
+    }
+
+    let best = LANGUAGES
+        .iter()
+        .map(|(code, name)| {
+            let name = name.to_lowercase();
+            let distance = match name.starts_with(&lower) {
+                true => 0,
+                false => edit_distance(&lower, &name),
+            };
+            (distance, *code)
+        })
+        .min_by_key(|(distance, _)| *distance);
+
+    match best {
+        // Allow about one typo per four letters
+        Some((distance, code)) if distance <= lower.chars().count() / 4 => Ok(code.to_string()),
+        _ => Err(AppError::UnknownLanguage(input.to_string())),
+    }
+}
+
+/// Levenshtein distance, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_names() {
+        assert_eq!(language_name("sv"), Some("Swedish"));
+        assert_eq!(language_name("ZH-HK"), Some("Cantonese"));
+        assert_eq!(language_name("xx"), None);
+    }
+
+    #[test]
+    fn languages_by_code_or_name() {
+        let resolve = |input| resolve_language(input).ok();
+        assert_eq!(resolve("EN").as_deref(), Some("en"));
+        assert_eq!(resolve(" Swedish ").as_deref(), Some("sv"));
+        assert_eq!(resolve("port").as_deref(), Some("pt"));
+        assert_eq!(resolve("Japanse").as_deref(), Some("ja"));
+        assert_eq!(resolve("klingon"), None);
+        assert_eq!(resolve(""), None);
+    }
+}
@@ -0,0 +1,43 @@
+//! Search live Twitch streams by title, language and category.
+//!
+//! The library holds the Helix client and the filtering and formatting used
+//! by the `twitch-search` binary, whose command line lives in `cli`.
+//!
+//! Everything that talks to Twitch or the terminal is behind the default
+//! `client` feature. Without it, the core still parses Helix responses
+//! ([`parse_streams`]) and filters and formats the streams exactly like the
+//! binary, and builds for `wasm32-unknown-unknown`.
+
+#[cfg(feature = "client")]
+pub mod api;
+#[cfg(feature = "client")]
+pub mod auth;
+#[cfg(feature = "client")]
+pub mod cli;
+pub mod config;
+mod entry;
+pub mod error;
+pub mod filter;
+pub mod helix;
+pub mod languages;
+pub mod matcher;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+#[cfg(feature = "client")]
+pub mod player;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "client")]
+pub mod search;
+#[cfg(feature = "sqlite")]
+pub mod sessions;
+pub mod table;
+#[cfg(feature = "client")]
+pub mod ui;
+
+#[cfg(feature = "client")]
+pub use api::{StreamQuery, Streams, TwitchClient};
+pub use entry::{json_schema, parse_streams, Change, StreamEntry, UserInfo, JSON_SCHEMA_VERSION};
+pub use error::{AccessTokenError, AppError};
+#[cfg(feature = "client")]
+pub use search::{Search, SearchEvent};
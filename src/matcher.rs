@@ -0,0 +1,161 @@
+//! Folding text before comparing it to search terms, so "Rúst" and "ＲＵＳＴ"
+//! both match "rust", and finding where words begin and end.
+
+use unicode_normalization::char::{decompose_compatible, is_combining_mark};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Lowercases `text`, and with `normalize` also replaces compatibility forms
+/// like fullwidth letters and ligatures with their plain equivalents and strips
+/// accents and other diacritics
+pub fn fold(text: &str, normalize: bool) -> String {
+    Folded::new(text, normalize).text
+}
+
+/// Text folded for comparison, as by [`fold`], that remembers which part of
+/// the original text each byte came from
+#[derive(Debug, Clone)]
+pub struct Folded {
+    pub text: String,
+    /// Byte range of the original character behind each byte of `text`, and
+    /// the empty range at the end of the original text
+    origins: Vec<(usize, usize)>,
+}
+
+impl Folded {
+    pub fn new(text: &str, normalize: bool) -> Self {
+        let mut folded = Folded {
+            text: String::with_capacity(text.len()),
+            origins: Vec::with_capacity(text.len()),
+        };
+        for (i, c) in text.char_indices() {
+            let origin = (i, i + c.len_utf8());
+            let mut push = |c: char| {
+                for lower in c.to_lowercase() {
+                    folded.text.push(lower);
+                    folded.origins.extend([origin].repeat(lower.len_utf8()));
+                }
+            };
+            match normalize {
+                true => decompose_compatible(c, |c| {
+                    if !is_combining_mark(c) {
+                        push(c)
+                    }
+                }),
+                false => push(c),
+            }
+        }
+        folded.origins.push((text.len(), text.len()));
+        folded
+    }
+
+    /// The byte range of the original text that `start..end` of the folded
+    /// text came from, widened to whole characters
+    pub fn original_range(&self, (start, end): (usize, usize)) -> (usize, usize) {
+        match start < end {
+            true => (self.origins[start].0, self.origins[end - 1].1),
+            false => (self.origins[start].0, self.origins[start].0),
+        }
+    }
+}
+
+/// Byte offsets of `text` where words begin and end, by Unicode word
+/// segmentation: "f1" and "100" are words, while the symbols of "c++" are each
+/// a segment of their own. Apostrophes end words too, so "rust's" has "rust".
+#[derive(Debug, Clone)]
+pub struct WordBounds(Vec<usize>);
+
+impl WordBounds {
+    pub fn new(text: &str) -> Self {
+        let starts = text.split_word_bound_indices().map(|(i, _)| i);
+        let apostrophes = text
+            .match_indices(['\'', '’'])
+            .flat_map(|(i, a)| [i, i + a.len()]);
+        let mut bounds = starts
+            .chain(apostrophes)
+            .chain([text.len()])
+            .collect::<Vec<_>>();
+        bounds.sort_unstable();
+        bounds.dedup();
+        WordBounds(bounds)
+    }
+
+    /// Whether `start..end` neither begins nor ends inside a word
+    pub fn is_whole(&self, (start, end): (usize, usize)) -> bool {
+        self.0.binary_search(&start).is_ok() && self.0.binary_search(&end).is_ok()
+    }
+
+    /// Every place `term` occurs in `text` as whole words, overlapping or not
+    pub fn find<'a>(
+        &'a self,
+        text: &'a str,
+        term: &'a str,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.0
+            .iter()
+            .filter(move |&&start| text[start..].starts_with(term))
+            .map(move |&start| (start, start + term.len()))
+            .filter(|&span| self.is_whole(span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_lowercases_and_strips_accents() {
+        assert_eq!(fold("Rúst Café", true), "rust cafe");
+        assert_eq!(fold("Rúst Café", false), "rúst café");
+    }
+
+    #[test]
+    fn fold_replaces_compatibility_forms() {
+        assert_eq!(fold("ＲＵＳＴ", true), "rust");
+        assert_eq!(fold("ﬁle", true), "file");
+        assert_eq!(fold("x²", true), "x2");
+    }
+
+    #[test]
+    fn folded_maps_back_to_original_characters() {
+        let text = "Ｒúst!";
+        let folded = Folded::new(text, true);
+        assert_eq!(folded.text, "rust!");
+        // "u" came from the two bytes of "ú", "r" from the three of "Ｒ"
+        assert_eq!(folded.original_range((1, 2)), (3, 5));
+        assert_eq!(folded.original_range((0, 4)), (0, "Ｒúst".len()));
+        assert_eq!(&text[3..5], "ú");
+    }
+
+    #[test]
+    fn word_bounds_on_non_ascii_text() {
+        let text = "día de rust’s";
+        let bounds = WordBounds::new(text);
+        assert!(bounds.is_whole((0, "día".len())));
+        assert!(!bounds.is_whole((0, "dí".len())));
+        let spans = bounds.find(text, "rust").collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [(text.find("rust").unwrap(), text.find("’").unwrap())]
+        );
+        assert_eq!(bounds.find(text, "de").count(), 1);
+    }
+
+    #[test]
+    fn word_bounds_split_symbols() {
+        let text = "c++ and c#";
+        let bounds = WordBounds::new(text);
+        assert_eq!(bounds.find(text, "c++").count(), 1);
+        assert_eq!(bounds.find(text, "c").count(), 2);
+        assert_eq!(bounds.find(text, "an").count(), 0);
+    }
+
+    #[test]
+    fn folded_maps_empty_ranges() {
+        let text = "Ａb";
+        let folded = Folded::new(text, true);
+        assert_eq!(folded.original_range((1, 1)), (3, 3));
+        // The empty range at the end maps to the end of the original text
+        let end = folded.text.len();
+        assert_eq!(folded.original_range((end, end)), (text.len(), text.len()));
+    }
+}
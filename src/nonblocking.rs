@@ -0,0 +1,286 @@
+//! Async Helix client for use inside async applications, behind the `async`
+//! feature. It covers the stream search of [`TwitchClient`](crate::TwitchClient)
+//! without the blocking extras: no token cache, throttling or retries.
+
+use std::collections::HashSet;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::api::{SeenCursors, HELIX_URL, SUGGESTED_GAMES};
+use crate::auth::{client_id, client_secret, TokenResponse};
+use crate::entry::{parse_streams, StreamEntry};
+use crate::error::AppError;
+use crate::filter::closest_names;
+use crate::helix::{Game, Page};
+
+/// Authenticated async client for the Helix API. Needs a tokio runtime.
+#[derive(Debug, Clone)]
+pub struct AsyncTwitchClient {
+    http: reqwest::Client,
+    client_id: String,
+    access_token: String,
+    /// Where Helix is, [`HELIX_URL`] unless told otherwise
+    helix_url: String,
+}
+
+impl AsyncTwitchClient {
+    pub fn new(client_id: String, access_token: String) -> Self {
+        AsyncTwitchClient {
+            http: reqwest::Client::new(),
+            client_id,
+            access_token,
+            helix_url: HELIX_URL.to_string(),
+        }
+    }
+
+    /// Sends the Helix requests to `url` instead of [`HELIX_URL`], like
+    /// [`TwitchClient::with_helix_url`](crate::TwitchClient::with_helix_url)
+    pub fn with_helix_url(mut self, url: impl Into<String>) -> Self {
+        self.helix_url = url.into();
+        self
+    }
+
+    /// Creates a client with a new app access token for the credentials in
+    /// `TWITCH_CLIENT_ID` and `TWITCH_CLIENT_SECRET`
+    pub async fn from_env() -> Result<Self, AppError> {
+        let client_id = client_id()?;
+        let client_secret = client_secret()?;
+
+        let http = reqwest::Client::new();
+        let token = http
+            .post("https://id.twitch.tv/oauth2/token")
+            .form(&[
+                ("client_id", client_id.as_str()),
+                ("client_secret", &client_secret),
+                ("grant_type", "client_credentials"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<TokenResponse>()
+            .await?;
+
+        Ok(AsyncTwitchClient {
+            http,
+            client_id,
+            access_token: token.access_token,
+            helix_url: HELIX_URL.to_string(),
+        })
+    }
+
+    /// GETs `endpoint` (relative to the Helix URL) with the given query parameters
+    async fn get(&self, endpoint: &str, query: &[(&str, &str)]) -> Result<Value, AppError> {
+        Ok(self
+            .http
+            .get(format!("{}/{endpoint}", self.helix_url))
+            .bearer_auth(&self.access_token)
+            .header("Client-Id", &self.client_id)
+            .query(query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    async fn get_typed<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, AppError> {
+        let json = self.get(endpoint, query).await?;
+        T::deserialize(json).map_err(|e| AppError::UnexpectedResponse(endpoint.to_string(), e))
+    }
+
+    /// Turns a game name or id into an id, like [`TwitchClient::resolve_game`](crate::TwitchClient::resolve_game)
+    pub async fn resolve_game(&self, name_or_id: &str) -> Result<String, AppError> {
+        if !name_or_id.is_empty() && name_or_id.chars().all(|c| c.is_ascii_digit()) {
+            return Ok(name_or_id.to_string());
+        }
+
+        let games = self
+            .get_typed::<Page<Game>>("games", &[("name", name_or_id)])
+            .await?;
+        match games.data.into_iter().next() {
+            Some(game) => Ok(game.id),
+            None => Err(AppError::UnknownGame(
+                name_or_id.to_string(),
+                self.similar_games(name_or_id).await,
+            )),
+        }
+    }
+
+    /// Names of categories close to `name`, like the blocking client suggests
+    async fn similar_games(&self, name: &str) -> Vec<String> {
+        let query = [("query", name), ("first", "100")];
+        let mut names = Vec::new();
+        for (endpoint, query) in [
+            ("search/categories", &query[..]),
+            ("games/top", &query[1..]),
+        ] {
+            if let Ok(games) = self.get_typed::<Page<Game>>(endpoint, query).await {
+                names.extend(games.data.into_iter().map(|game| game.name));
+            }
+        }
+        closest_names(name, &names, SUGGESTED_GAMES)
+    }
+
+    /// Fetches one page of streams of a game, most watched first, with the
+    /// cursor of the next page if there is one
+    pub async fn fetch_streams_page(
+        &self,
+        game_id: &str,
+        after: Option<&str>,
+    ) -> Result<(Vec<StreamEntry>, Option<String>), AppError> {
+        let mut query = vec![("first", "100"), ("game_id", game_id)];
+        if let Some(after) = after {
+            query.push(("after", after));
+        }
+        parse_streams(&self.get("streams", &query).await?)
+    }
+
+    /// Fetches up to `max_pages` pages of streams of each game and merges
+    /// them, dropping channels already seen. Stops with
+    /// [`AppError::RunawayPagination`] if the API hands back a cursor it has
+    /// already given us.
+    pub async fn fetch_games(
+        &self,
+        game_ids: &[String],
+        max_pages: usize,
+    ) -> Result<Vec<StreamEntry>, AppError> {
+        let mut entries = Vec::new();
+        let mut seen = HashSet::new();
+        for game_id in game_ids {
+            let mut seen_cursors = SeenCursors::default();
+            let mut cursor = None;
+            for _ in 0..max_pages {
+                let (page, next) = self.fetch_streams_page(game_id, cursor.as_deref()).await?;
+                entries.extend(page.into_iter().filter(|e| seen.insert(e.user_id.clone())));
+
+                cursor = seen_cursors.check(next, || format!("for game {game_id}"))?;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+
+    use super::*;
+    use crate::entry::test_stream;
+
+    /// Runs `future`, which must finish without waiting on any request
+    fn now_or_never<T>(future: impl Future<Output = T>) -> T {
+        let mut cx = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("sent a request"),
+        }
+    }
+
+    #[test]
+    fn requests_only_when_needed() {
+        // Any request would need a runtime and a token
+        let client = AsyncTwitchClient::new(String::new(), String::new());
+        assert_eq!(
+            now_or_never(client.resolve_game("509658")).unwrap(),
+            "509658"
+        );
+
+        let games = ["509658".to_string()];
+        assert!(now_or_never(client.fetch_games(&games, 0))
+            .unwrap()
+            .is_empty());
+    }
+
+    /// Answers requests for streams like Helix, with a page of one channel
+    /// named by `page` from the game id and the cursor asked for, and the
+    /// cursor of the page after it. Returns where to send them.
+    fn helix(page: fn(&str, Option<&str>) -> (String, Option<String>)) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(&stream);
+                    let mut request = String::new();
+                    while reader.read_line(&mut request).is_ok_and(|n| n > 0) {
+                        let mut header = String::new();
+                        while reader.read_line(&mut header).is_ok_and(|n| n > 2) {
+                            header.clear();
+                        }
+                        let target = request.split(' ').nth(1).unwrap_or_default();
+                        let url = reqwest::Url::parse(&format!("http://helix{target}")).unwrap();
+                        let query = url.query_pairs().collect::<HashMap<_, _>>();
+                        let (name, cursor) =
+                            page(&query["game_id"], query.get("after").map(|a| &**a));
+                        let body = serde_json::json!({
+                            "data": [test_stream(&name, 10)],
+                            "pagination": { "cursor": cursor },
+                        })
+                        .to_string();
+                        let head =
+                            format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len());
+                        if (&stream).write_all((head + &body).as_bytes()).is_err() {
+                            break;
+                        }
+                        request.clear();
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    /// Runs `future` to the end on a runtime of its own
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn fetches_the_pages_of_every_game() {
+        // Two pages a game, their cursors alike in both
+        let url = helix(|game, after| {
+            let page = after.map_or(0, |after| after.parse().unwrap());
+            (
+                format!("{game}-{page}"),
+                (page == 0).then(|| "1".to_string()),
+            )
+        });
+        let client = AsyncTwitchClient::new(String::new(), String::new()).with_helix_url(url);
+        let games = ["1".to_string(), "2".to_string()];
+        let logins = |max_pages| {
+            let entries = block_on(client.fetch_games(&games, max_pages)).unwrap();
+            entries
+                .into_iter()
+                .map(|e| e.user_login)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(logins(10), ["1-0", "1-1", "2-0", "2-1"]);
+        assert_eq!(logins(1), ["1-0", "2-0"]);
+    }
+
+    #[test]
+    fn stops_on_a_repeated_cursor() {
+        let url = helix(|game, _| (game.to_string(), Some("again".to_string())));
+        let client = AsyncTwitchClient::new(String::new(), String::new()).with_helix_url(url);
+        let error = block_on(client.fetch_games(&["1".to_string()], 10)).unwrap_err();
+        assert!(matches!(error, AppError::RunawayPagination(_)));
+    }
+}
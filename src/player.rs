@@ -0,0 +1,61 @@
+//! Watching streams in the browser or an external player
+
+use std::process::Command;
+
+use crate::AppError;
+
+/// Player command used when neither `--player` nor the config file gives one
+pub const DEFAULT_PLAYER: &str = "streamlink {url} {quality}";
+
+/// Stream quality asked for when none is given
+pub const DEFAULT_QUALITY: &str = "best";
+
+/// Runs the player command `template` on `url` and waits for it to exit.
+/// The command is split on whitespace, and `{url}` and `{quality}` in any
+/// part of it are replaced. The player shares our terminal, so its messages
+/// show up as is.
+pub fn play(template: &str, url: &str, quality: &str) -> Result<(), AppError> {
+    let mut parts = command_line(template, url, quality).into_iter();
+    let program = parts.next().ok_or(AppError::EmptyPlayer)?;
+
+    let status = Command::new(&program)
+        .args(parts)
+        .status()
+        .map_err(|e| AppError::StartPlayer(program.clone(), e))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(AppError::PlayerFailed(program, status)),
+    }
+}
+
+/// The parts of the player command `template` for `url` in `quality`
+fn command_line(template: &str, url: &str, quality: &str) -> Vec<String> {
+    template
+        .split_whitespace()
+        .map(|part| part.replace("{url}", url).replace("{quality}", quality))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_commands() {
+        let url = "https://twitch.tv/crab";
+        assert_eq!(
+            command_line(DEFAULT_PLAYER, url, "720p"),
+            ["streamlink", url, "720p"]
+        );
+        assert_eq!(
+            command_line("mpv  --title=crab@{quality} {url}", url, "best"),
+            ["mpv", "--title=crab@best", url]
+        );
+
+        assert!(matches!(play(" ", url, "best"), Err(AppError::EmptyPlayer)));
+        assert!(matches!(
+            play("twitch-search-no-such-player {url}", url, "best"),
+            Err(AppError::StartPlayer(program, _)) if program == "twitch-search-no-such-player"
+        ));
+    }
+}
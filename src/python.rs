@@ -0,0 +1,272 @@
+//! The `twitch_search` Python module, for pulling matched streams straight
+//! into a notebook:
+//!
+//! ```python
+//! import pandas as pd
+//! import twitch_search
+//!
+//! client = twitch_search.Client()
+//! rust = twitch_search.Filter(["rust"], languages=["en"])
+//! df = pd.DataFrame(client.search(rust, games=["Software and Game Development"]))
+//! ```
+//!
+//! Streams come back as dicts with the keys of `--output json`, see
+//! [`json_schema`].
+
+use clap::ValueEnum;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use serde_json::Value;
+
+use crate::filter::{MatchField, SearchFilter, Terms, TitleBlocklist, ViewerLimits};
+use crate::languages::resolve_language;
+use crate::matcher::fold;
+use crate::{json_schema, AppError, Search, StreamQuery, TwitchClient};
+
+create_exception!(twitch_search, TwitchSearchError, PyException);
+
+impl From<AppError> for PyErr {
+    fn from(e: AppError) -> Self {
+        TwitchSearchError::new_err(e.to_string())
+    }
+}
+
+/// Hands `json` to Python as dicts and lists, by way of the `json` module
+fn to_python(py: Python<'_>, json: &Value) -> PyResult<PyObject> {
+    let loads = py.import("json")?.getattr("loads")?;
+    Ok(loads.call1((json.to_string(),))?.unbind())
+}
+
+fn languages(languages: &[String]) -> Result<Vec<String>, AppError> {
+    languages
+        .iter()
+        .map(|lang| resolve_language(lang))
+        .collect()
+}
+
+/// A Helix client with an app access token for `TWITCH_CLIENT_ID` and
+/// `TWITCH_CLIENT_SECRET`
+#[pyclass(name = "Client", module = "twitch_search")]
+struct PyClient {
+    client: TwitchClient,
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    #[pyo3(signature = (use_cache = true))]
+    fn new(use_cache: bool) -> PyResult<Self> {
+        let client = TwitchClient::from_env(use_cache).map_err(AppError::from)?;
+        Ok(PyClient { client })
+    }
+
+    /// The live streams of `games` (names or ids, Software and Game
+    /// Development and Science & Technology by default), most watched first
+    #[pyo3(signature = (games = vec![], languages = vec![], limit = None))]
+    fn streams(
+        &self,
+        py: Python<'_>,
+        games: Vec<String>,
+        languages: Vec<String>,
+        limit: Option<usize>,
+    ) -> PyResult<PyObject> {
+        let query = self.query(&games, &languages)?;
+        let streams = py.allow_threads(|| {
+            self.client
+                .streams(query)
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|entry| entry.map(|entry| entry.to_json()))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        to_python(py, &Value::Array(streams))
+    }
+
+    /// The live streams of `games` that `filter` matches
+    #[pyo3(signature = (filter, games = vec![], languages = vec![], max_pages = None, limit = None))]
+    fn search(
+        &self,
+        py: Python<'_>,
+        filter: &PyFilter,
+        games: Vec<String>,
+        languages: Vec<String>,
+        max_pages: Option<usize>,
+        limit: Option<usize>,
+    ) -> PyResult<PyObject> {
+        let search = Search {
+            client: &self.client,
+            query: self.query(&games, &languages)?,
+            filter: filter.filter.clone(),
+            max_pages,
+            limit,
+        };
+        let matches = py.allow_threads(|| search.run(|_| {}))?;
+        let matches = matches.iter().map(|entry| entry.to_json()).collect();
+        to_python(py, &Value::Array(matches))
+    }
+}
+
+impl PyClient {
+    fn query(&self, games: &[String], langs: &[String]) -> Result<StreamQuery, AppError> {
+        Ok(StreamQuery {
+            game_ids: games
+                .iter()
+                .map(|game| self.client.resolve_game(game))
+                .collect::<Result<_, _>>()?,
+            languages: languages(langs)?,
+        })
+    }
+}
+
+/// What a stream must look like to match, like the options of a search
+#[pyclass(name = "Filter", module = "twitch_search")]
+struct PyFilter {
+    filter: SearchFilter,
+}
+
+#[pymethods]
+impl PyFilter {
+    #[new]
+    #[pyo3(signature = (
+        terms,
+        *,
+        regex = false,
+        fuzzy = false,
+        word = false,
+        all = false,
+        not_terms = vec![],
+        exclude_channels = vec![],
+        languages = vec![],
+        tags = vec![],
+        fields = vec!["title".to_string()],
+        min_viewers = None,
+        max_viewers = None,
+        max_title_len = None,
+        normalize = true,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        terms: Vec<String>,
+        regex: bool,
+        fuzzy: bool,
+        word: bool,
+        all: bool,
+        not_terms: Vec<String>,
+        exclude_channels: Vec<String>,
+        languages: Vec<String>,
+        tags: Vec<String>,
+        fields: Vec<String>,
+        min_viewers: Option<i64>,
+        max_viewers: Option<i64>,
+        max_title_len: Option<usize>,
+        normalize: bool,
+    ) -> PyResult<Self> {
+        let terms = match (regex, fuzzy) {
+            (true, _) => Terms::regex(&terms, normalize)?,
+            (_, true) => Terms::fuzzy(&terms, normalize),
+            _ => Terms::plain(&terms, normalize),
+        };
+        let fields = fields
+            .iter()
+            .map(|field| MatchField::from_str(field, true))
+            .collect::<Result<_, _>>()
+            .map_err(PyValueError::new_err)?;
+        let filter = SearchFilter {
+            terms: Terms {
+                whole_word: word,
+                ..terms
+            },
+            fields,
+            not: not_terms.iter().map(|t| fold(t, normalize)).collect(),
+            blocklist: TitleBlocklist::default(),
+            exclude: exclude_channels.iter().map(|c| c.to_lowercase()).collect(),
+            langs: self::languages(&languages)?,
+            tags: tags.iter().map(|t| t.to_lowercase()).collect(),
+            all,
+            viewers: ViewerLimits {
+                min: min_viewers,
+                max: max_viewers,
+            },
+            max_title_len,
+        };
+        Ok(PyFilter { filter })
+    }
+}
+
+/// The JSON Schema of the stream dicts
+#[pyfunction]
+fn schema(py: Python<'_>) -> PyResult<PyObject> {
+    to_python(py, &json_schema())
+}
+
+#[pymodule]
+fn twitch_search(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClient>()?;
+    m.add_class::<PyFilter>()?;
+    m.add_function(wrap_pyfunction!(schema, m)?)?;
+    m.add("TwitchSearchError", m.py().get_type::<TwitchSearchError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+    use crate::StreamEntry;
+
+    fn entry(title: &str, viewers: i64) -> StreamEntry {
+        StreamEntry {
+            title: title.to_string(),
+            ..test_entry("Crab", viewers)
+        }
+    }
+
+    fn filter(terms: &[&str], not_terms: &[&str], min_viewers: Option<i64>) -> PyResult<PyFilter> {
+        PyFilter::new(
+            terms.iter().map(|t| t.to_string()).collect(),
+            false,
+            false,
+            false,
+            false,
+            not_terms.iter().map(|t| t.to_string()).collect(),
+            vec![],
+            vec!["English".to_string()],
+            vec![],
+            vec!["title".to_string()],
+            min_viewers,
+            None,
+            None,
+            true,
+        )
+    }
+
+    #[test]
+    fn filters_like_the_command_line() {
+        let rust = filter(&["RUST"], &["giveaway"], Some(5)).unwrap().filter;
+        assert_eq!(rust.langs, ["en"]);
+        assert!(rust.matches(&entry("Rust and coffee", 10)));
+        assert!(!rust.matches(&entry("Rust giveaway", 10)));
+        assert!(!rust.matches(&entry("Rust and coffee", 2)));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let fields = PyFilter::new(
+            vec!["rust".to_string()],
+            false,
+            false,
+            false,
+            false,
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec!["nope".to_string()],
+            None,
+            None,
+            None,
+            true,
+        );
+        assert!(fields.is_err());
+    }
+}
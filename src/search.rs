@@ -0,0 +1,84 @@
+//! A stream search that reports how it is getting on, for programs that show
+//! progress or act on matches as they come: see [`Search::run`].
+
+use std::time::Duration;
+
+use crate::api::{StreamPages, StreamQuery};
+use crate::filter::SearchFilter;
+use crate::{AppError, StreamEntry, TwitchClient};
+
+/// What happens during [`Search::run`], in order
+#[derive(Debug)]
+pub enum SearchEvent<'a> {
+    /// A page of `streams` streams of `game_id` came in, the `page`th of the
+    /// search (counting from 1)
+    PageFetched {
+        game_id: &'a str,
+        page: usize,
+        streams: usize,
+    },
+    /// A stream on the page just fetched matches the filter
+    MatchFound(&'a StreamEntry),
+    /// The rate limit holds the next request up for this long
+    RateLimited(Duration),
+    /// The search is over, after this many pages and matches
+    Finished { pages: usize, matches: usize },
+}
+
+/// The live streams of `query` that `filter` matches
+#[derive(Debug)]
+pub struct Search<'a> {
+    pub client: &'a TwitchClient,
+    pub query: StreamQuery,
+    pub filter: SearchFilter,
+    /// Pages to fetch at most, across all games
+    pub max_pages: Option<usize>,
+    /// Stop once this many streams match
+    pub limit: Option<usize>,
+}
+
+impl Search<'_> {
+    /// Fetches the pages one after the other, telling `on_event` about each,
+    /// the matches on it and waits for the rate limit. Returns the matches,
+    /// most watched first within each game. On an error, `Finished` is not
+    /// sent.
+    pub fn run(
+        &self,
+        mut on_event: impl FnMut(SearchEvent<'_>),
+    ) -> Result<Vec<StreamEntry>, AppError> {
+        let mut pages = StreamPages::new(self.client, self.query.clone());
+        let mut matches = Vec::new();
+        let mut fetched = 0;
+        while self.max_pages.is_none_or(|max| fetched < max)
+            && self.limit.is_none_or(|limit| matches.len() < limit)
+        {
+            let mut on_rate_limit = |wait| on_event(SearchEvent::RateLimited(wait));
+            let Some(page) = pages.next_page(&mut on_rate_limit) else {
+                break;
+            };
+            let (game_id, entries) = page?;
+            fetched += 1;
+            on_event(SearchEvent::PageFetched {
+                game_id: &game_id,
+                page: fetched,
+                streams: entries.len(),
+            });
+
+            for entry in entries {
+                if self.limit.is_some_and(|limit| matches.len() >= limit) {
+                    break;
+                }
+                if self.filter.matches(&entry) {
+                    on_event(SearchEvent::MatchFound(&entry));
+                    matches.push(entry);
+                }
+            }
+        }
+
+        on_event(SearchEvent::Finished {
+            pages: fetched,
+            matches: matches.len(),
+        });
+        Ok(matches)
+    }
+}
@@ -0,0 +1,215 @@
+//! Long-term record of stream sessions in SQLite, behind the `sqlite`
+//! feature. Every run with `--db` adds the streams it saw, so a niche category
+//! can be followed over weeks.
+
+use std::path::Path;
+
+use chrono::prelude::*;
+use rusqlite::{params, Connection};
+
+use crate::entry::StreamEntry;
+use crate::error::AppError;
+use crate::matcher::fold;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    stream_id   TEXT PRIMARY KEY,
+    user_id     TEXT NOT NULL,
+    channel     TEXT NOT NULL,
+    lang        TEXT NOT NULL,
+    game        TEXT NOT NULL,
+    title       TEXT NOT NULL,
+    max_viewers INTEGER NOT NULL,
+    started_at  TEXT,
+    first_seen  TEXT NOT NULL,
+    last_seen   TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sessions_channel ON sessions (channel COLLATE NOCASE);
+";
+
+/// One live stream of a channel, from when it started to when it was last seen
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub stream_id: String,
+    pub channel: String,
+    pub lang: String,
+    pub game: String,
+    /// The latest title seen
+    pub title: String,
+    pub max_viewers: i64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl Session {
+    /// How long the stream was live as far as we know, as HH:MM
+    pub fn duration(&self) -> String {
+        let minutes = (self.last_seen - self.started_at.unwrap_or(self.last_seen)).num_minutes();
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+/// Stream sessions stored in an SQLite database file
+#[derive(Debug)]
+pub struct SessionDb {
+    connection: Connection,
+}
+
+impl SessionDb {
+    /// Opens the database at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SessionDb { connection })
+    }
+
+    /// Adds the streams seen now, or updates the sessions they are part of:
+    /// the latest title and name, the highest viewer count and when they were
+    /// last seen
+    pub fn record(&mut self, entries: &[StreamEntry]) -> Result<(), AppError> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let transaction = self.connection.transaction()?;
+        {
+            let mut upsert = transaction.prepare(
+                "INSERT INTO sessions (stream_id, user_id, channel, lang, game, title,
+                    max_viewers, started_at, first_seen, last_seen)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+                ON CONFLICT (stream_id) DO UPDATE SET
+                    channel = excluded.channel,
+                    game = excluded.game,
+                    title = excluded.title,
+                    max_viewers = max(max_viewers, excluded.max_viewers),
+                    last_seen = excluded.last_seen",
+            )?;
+            for entry in entries {
+                let started_at = entry
+                    .started_at
+                    .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true));
+                upsert.execute(params![
+                    entry.id,
+                    entry.user_id,
+                    entry.display_name,
+                    entry.lang,
+                    entry.game_name,
+                    entry.title,
+                    entry.viewer_count,
+                    started_at,
+                    now,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// The sessions of `channel` (any if `None`) in `langs` (any if empty)
+    /// with titles containing all `terms`, ignoring case and accents, most
+    /// recent first. At most `limit` of them, or all if it is 0.
+    pub fn find(
+        &self,
+        terms: &[String],
+        channel: Option<&str>,
+        langs: &[String],
+        limit: usize,
+    ) -> Result<Vec<Session>, AppError> {
+        let mut select = self.connection.prepare(
+            "SELECT stream_id, channel, lang, game, title, max_viewers, started_at, last_seen
+            FROM sessions
+            WHERE ?1 IS NULL OR channel = ?1 COLLATE NOCASE
+            ORDER BY last_seen DESC, max_viewers DESC",
+        )?;
+        let rows = select.query_map(params![channel], |row| {
+            let time = |text: Option<String>| {
+                text.and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+                    .map(|t| t.with_timezone(&Utc))
+            };
+            Ok(Session {
+                stream_id: row.get(0)?,
+                channel: row.get(1)?,
+                lang: row.get(2)?,
+                game: row.get(3)?,
+                title: row.get(4)?,
+                max_viewers: row.get(5)?,
+                started_at: time(row.get(6)?),
+                last_seen: time(row.get(7)?).unwrap_or_default(),
+            })
+        })?;
+
+        // Matched here rather than in SQL, whose LIKE only folds ASCII
+        let terms = terms.iter().map(|t| fold(t, true)).collect::<Vec<_>>();
+        let mut sessions = Vec::new();
+        for session in rows {
+            let session = session?;
+            let title = fold(&session.title, true);
+            let in_lang = langs.is_empty() || langs.contains(&session.lang);
+            if in_lang && terms.iter().all(|term| title.contains(term)) {
+                sessions.push(session);
+                if sessions.len() == limit {
+                    break;
+                }
+            }
+        }
+        Ok(sessions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+
+    fn streaming(name: &str, viewers: i64, lang: &str, title: &str) -> StreamEntry {
+        StreamEntry {
+            lang: lang.to_string(),
+            title: title.to_string(),
+            ..test_entry(name, viewers)
+        }
+    }
+
+    #[test]
+    fn recorded_sessions() {
+        let mut db = SessionDb::open(Path::new(":memory:")).unwrap();
+        db.record(&[
+            streaming("Crab", 30, "en", "Rust café"),
+            streaming("Ferris", 10, "sv", "Rust på svenska"),
+        ])
+        .unwrap();
+        db.record(&[streaming("Crab", 20, "en", "More Rust at the café")])
+            .unwrap();
+
+        let sessions = db.find(&[], Some("crab"), &[], 0).unwrap();
+        assert_eq!(sessions.len(), 1);
+        // The highest viewer count, but the latest title
+        assert_eq!(sessions[0].max_viewers, 30);
+        assert_eq!(sessions[0].title, "More Rust at the café");
+
+        let find = |terms: &[&str], langs: &[&str], limit| {
+            let terms = terms.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            let langs = langs.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+            let sessions = db.find(&terms, None, &langs, limit).unwrap();
+            sessions.into_iter().map(|s| s.channel).collect::<Vec<_>>()
+        };
+        assert_eq!(find(&["CAFE", "rust"], &[], 0), ["Crab"]);
+        assert_eq!(find(&["rust"], &["sv"], 0), ["Ferris"]);
+        assert_eq!(find(&["rust"], &[], 1).len(), 1);
+        assert!(find(&["zig"], &[], 0).is_empty());
+    }
+
+    #[test]
+    fn session_duration() {
+        let last_seen = Utc::now();
+        let session = |started_at| Session {
+            stream_id: String::new(),
+            channel: String::new(),
+            lang: String::new(),
+            game: String::new(),
+            title: String::new(),
+            max_viewers: 0,
+            started_at,
+            last_seen,
+        };
+        let started = last_seen - chrono::Duration::minutes(150);
+        assert_eq!(session(Some(started)).duration(), "02:30");
+        assert_eq!(session(None).duration(), "00:00");
+    }
+}
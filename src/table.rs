@@ -0,0 +1,1193 @@
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::{cmp, env, fmt};
+
+use chrono::prelude::*;
+use clap::ValueEnum;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::entry::StreamEntry;
+use crate::filter::Terms;
+use crate::languages::language_name;
+
+// -----------------------------------------------------------------------------
+//     - Table formatting -
+// -----------------------------------------------------------------------------
+
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ExtraColumn {
+    /// Game or category being streamed
+    Category,
+    /// Partner or affiliate status
+    BroadcasterType,
+    /// Channel description (truncated)
+    Description,
+    /// Content classification labels
+    Ccls,
+    /// Follower count
+    Followers,
+    /// Viewers per follower
+    ViewerRatio,
+    /// Stream tags
+    Tags,
+}
+
+impl ExtraColumn {
+    /// Whether the column needs user details beyond the stream itself
+    pub fn needs_hydration(self) -> bool {
+        Column::from(self).needs_hydration()
+    }
+}
+
+/// A column of the results table, for `--columns`
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Column {
+    /// Language code, or name with --lang-names
+    Lang,
+    /// Channel link
+    Name,
+    /// Viewer count
+    Viewers,
+    /// Time live
+    Uptime,
+    /// Stream title
+    Title,
+    /// Game or category being streamed
+    #[clap(alias = "game")]
+    Category,
+    /// Partner or affiliate status
+    BroadcasterType,
+    /// Channel description (truncated)
+    Description,
+    /// Content classification labels
+    Ccls,
+    /// Follower count
+    Followers,
+    /// Viewers per follower
+    ViewerRatio,
+    /// Stream tags
+    Tags,
+    /// Link to a preview image of the stream
+    Thumbnail,
+    /// Search terms found in the stream
+    Matched,
+    /// NEW, or the change in viewers since the previous run, with --diff
+    Change,
+}
+
+impl From<ExtraColumn> for Column {
+    fn from(column: ExtraColumn) -> Self {
+        match column {
+            ExtraColumn::Category => Column::Category,
+            ExtraColumn::BroadcasterType => Column::BroadcasterType,
+            ExtraColumn::Description => Column::Description,
+            ExtraColumn::Ccls => Column::Ccls,
+            ExtraColumn::Followers => Column::Followers,
+            ExtraColumn::ViewerRatio => Column::ViewerRatio,
+            ExtraColumn::Tags => Column::Tags,
+        }
+    }
+}
+
+impl Column {
+    /// The default layout, with `extra` between the uptime and the title
+    pub fn layout(extra: &[ExtraColumn]) -> Vec<Column> {
+        let mut columns = vec![Column::Lang, Column::Name, Column::Viewers, Column::Uptime];
+        columns.extend(extra.iter().copied().map(Column::from));
+        columns.push(Column::Title);
+        columns
+    }
+
+    /// Adds `column` just before the title, unless it is already there
+    pub fn insert(columns: &mut Vec<Column>, column: Column) {
+        if !columns.contains(&column) {
+            let at = columns
+                .iter()
+                .position(|&c| c == Column::Title)
+                .unwrap_or(columns.len());
+            columns.insert(at, column);
+        }
+    }
+
+    /// Whether the column needs user details beyond the stream itself
+    pub fn needs_hydration(self) -> bool {
+        matches!(
+            self,
+            Column::BroadcasterType
+                | Column::Description
+                | Column::Ccls
+                | Column::Followers
+                | Column::ViewerRatio
+        )
+    }
+
+    pub fn align(self) -> Align {
+        match self {
+            Column::Viewers | Column::Uptime | Column::Change => Align::Right,
+            _ => Align::Left,
+        }
+    }
+
+    pub fn header(self) -> String {
+        match self {
+            Column::Name => "channel".to_string(),
+            _ => self
+                .to_possible_value()
+                .map_or(String::new(), |value| value.get_name().replace('-', "_")),
+        }
+    }
+}
+
+impl StreamEntry {
+    pub fn format_row(self, columns: &Columns) -> Vec<String> {
+        let user = self.user.clone().unwrap_or_default();
+        let cell = |column: Column| match column {
+            Column::Lang => match columns.lang_names {
+                true => language_name(&self.lang).map_or(self.lang.clone(), str::to_string),
+                false => self.lang.clone(),
+            },
+            Column::Name => {
+                let link = format!("https://twitch.tv/{}", self.display_name);
+                match columns.color {
+                    true => hyperlink(&link),
+                    false => link,
+                }
+            }
+            Column::Viewers => match columns.plain {
+                true => self.viewer_count.to_string(),
+                false => format!("{} viewers", self.viewer_count),
+            },
+            Column::Uptime => self.live_duration.clone(),
+            Column::Title => {
+                let mut title = self.title.replace(|c: char| c.is_control(), " ");
+                if let (true, Some(terms)) = (columns.color || columns.brackets, &columns.highlight)
+                {
+                    title = highlight(&title, terms, columns.color);
+                }
+                if self.same_title > 1 && !columns.plain {
+                    title = format!("[{}x same title] {title}", self.same_title);
+                }
+                // Cut last, so the whole cell fits
+                if let Some(width) = columns.max_title_width {
+                    title = fit_width(&title, width);
+                }
+                title
+            }
+            Column::Category => self.game_name.clone(),
+            Column::Tags => self.tags.join(","),
+            Column::Matched => self.matched_terms.join(","),
+            Column::Change => self.change.map_or(String::new(), |c| c.to_string()),
+            Column::Thumbnail => self
+                .thumbnail_url
+                .replace("{width}", THUMBNAIL_WIDTH)
+                .replace("{height}", THUMBNAIL_HEIGHT),
+            Column::BroadcasterType => user.broadcaster_type.clone(),
+            Column::Description => truncate(&user.description, DESCRIPTION_WIDTH),
+            Column::Ccls => user.content_labels.join(","),
+            Column::Followers => match (user.followers, columns.plain) {
+                (Some(f), true) => f.to_string(),
+                (Some(f), false) => format!("{f} followers"),
+                (None, _) => String::new(),
+            },
+            Column::ViewerRatio => match (user.viewer_ratio(self.viewer_count), columns.plain) {
+                (Some(r), true) => format!("{r:.2}"),
+                (Some(r), false) => format!("{r:.2} v/f"),
+                (None, _) => String::new(),
+            },
+        };
+        let dim = columns.color && self.viewer_count < DIM_BELOW_VIEWERS;
+        columns
+            .list
+            .iter()
+            .map(|&column| match dim {
+                true => paint(&cell(column), DIM, NORMAL_INTENSITY),
+                false => cell(column),
+            })
+            .collect()
+    }
+}
+
+/// Size of the preview images linked by the thumbnail column
+const THUMBNAIL_WIDTH: &str = "320";
+const THUMBNAIL_HEIGHT: &str = "180";
+
+/// Longest description shown in the description column
+const DESCRIPTION_WIDTH: usize = 40;
+
+/// Flattens `text` onto one line and cuts it to at most `width` columns
+fn truncate(text: &str, width: usize) -> String {
+    let text = text
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    fit_width(&text, width)
+}
+
+/// Cuts `text` to at most `width` columns on screen, ending it with an
+/// ellipsis if anything was cut
+pub fn fit_width(text: &str, width: usize) -> String {
+    if visible_width(text) <= width {
+        return text.to_string();
+    }
+
+    let end = split_at_width(text, width.saturating_sub(1));
+    // Styles started before the cut would otherwise carry on past it
+    let reset = match text.contains('\x1b') {
+        true => RESET,
+        false => "",
+    };
+    format!("{}…{reset}", &text[..end])
+}
+
+/// Breaks `text` into lines of at most `width` columns, at spaces where
+/// possible
+fn wrap_lines(text: &str, width: usize) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut rest = text;
+    while visible_width(rest) > width {
+        let mut end = split_at_width(rest, width);
+        // A word longer than the line is broken up instead
+        if !rest[end..].starts_with(' ') {
+            if let Some(space) = rest[..end].rfind(' ').filter(|&space| space > 0) {
+                end = space;
+            }
+        }
+        if end == 0 {
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        lines.push(&rest[..end]);
+        rest = rest[end..].trim_start_matches(' ');
+    }
+    lines.push(rest);
+    lines
+}
+
+/// Which columns [`StreamEntry::format_row`] produces, and how
+#[derive(Debug, Default, Clone)]
+pub struct Columns {
+    pub list: Vec<Column>,
+    pub lang_names: bool,
+    /// Bare values for spreadsheets rather than decorated ones for reading
+    pub plain: bool,
+    /// Style cells with ANSI escapes
+    pub color: bool,
+    /// Terms to highlight in titles when coloring, or with `brackets`
+    pub highlight: Option<Terms>,
+    /// Highlight with [brackets] when not coloring
+    pub brackets: bool,
+    /// Cut titles to this many columns
+    pub max_title_width: Option<usize>,
+}
+
+impl Columns {
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(self.list.len());
+        for (i, column) in self.list.iter().enumerate() {
+            table.set_align(i, column.align());
+        }
+        table.set_headers(self.headers());
+        table
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        self.list.iter().map(|c| c.header()).collect()
+    }
+}
+
+/// What to do with a last column that doesn't fit the terminal
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Wrap {
+    /// Cut it, ending with …
+    Truncate,
+    /// Continue it on the next lines, indented to the column
+    Wrap,
+    /// Leave it to the terminal
+    Off,
+}
+
+/// The last column gets at least this much room, however wide the others are
+const MIN_LAST_WIDTH: usize = 10;
+
+#[allow(unused)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug)]
+pub struct Table {
+    align: Vec<Align>,
+    widths: Vec<usize>,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    /// Group headings, with the index of the row each one comes before
+    headings: Vec<(usize, String)>,
+}
+
+impl Table {
+    pub fn new(columns: usize) -> Self {
+        Table {
+            align: vec![Align::Left; columns],
+            widths: vec![0; columns],
+            headers: Vec::new(),
+            rows: Vec::new(),
+            headings: Vec::new(),
+        }
+    }
+
+    /// Column names, written by the delimited printers only
+    pub fn set_headers(&mut self, headers: Vec<String>) {
+        self.headers = headers;
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn set_align(&mut self, column: usize, align: Align) {
+        self.align[column] = align;
+    }
+
+    pub fn push(&mut self, row: Vec<String>) {
+        let n = self.widths.len();
+        for (width, cell) in self.widths.iter_mut().zip(&row).take(n - 1) {
+            *width = cmp::max(*width, visible_width(cell));
+        }
+        self.rows.push(row);
+    }
+
+    /// Starts a group of rows under `heading`. Only the aligned printers show
+    /// headings, the others just list the rows.
+    pub fn push_heading(&mut self, heading: String) {
+        self.headings.push((self.rows.len(), heading));
+    }
+
+    pub fn print(&self) {
+        self.print_to_width(None, Wrap::Off);
+    }
+
+    pub fn print_to_width(&self, width: Option<usize>, wrap: Wrap) {
+        // Panics on failure, like println!
+        self.write_to_width(&mut io::stdout().lock(), width, wrap)
+            .expect("failed printing to stdout");
+    }
+
+    /// Writes aligned columns, fitting the last one into what is left of
+    /// `width` as `wrap` says
+    pub fn write_to_width(
+        &self,
+        out: &mut dyn Write,
+        width: Option<usize>,
+        wrap: Wrap,
+    ) -> io::Result<()> {
+        let n = self.widths.len();
+        // Where the last column starts
+        let indent = self.widths[..n - 1].iter().map(|w| w + 3).sum::<usize>();
+        let room = width.map(|width| width.saturating_sub(indent).max(MIN_LAST_WIDTH));
+        let mut headings = self.headings.iter().peekable();
+        for (i, row) in self.rows.iter().enumerate() {
+            while let Some((_, heading)) = headings.next_if(|(at, _)| *at == i) {
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{heading}")?;
+            }
+            for ((align, row), width) in self.align.iter().zip(row).zip(&self.widths).take(n - 1) {
+                // Padded by hand, as format widths would count escape codes too
+                let pad = width.saturating_sub(visible_width(row));
+                let (before, after) = match align {
+                    Align::Left => (0, pad),
+                    Align::Center => (pad / 2, pad - pad / 2),
+                    Align::Right => (pad, 0),
+                };
+                write!(out, "{:before$}{row}{:after$} | ", "", "")?;
+            }
+            // last column always left aligned
+            match (room, wrap) {
+                (Some(room), Wrap::Truncate) => writeln!(out, "{}", fit_width(&row[n - 1], room))?,
+                (Some(room), Wrap::Wrap) => {
+                    for (i, line) in wrap_lines(&row[n - 1], room).into_iter().enumerate() {
+                        match i {
+                            0 => writeln!(out, "{line}")?,
+                            _ => writeln!(out, "{:indent$}{line}", "")?,
+                        }
+                    }
+                }
+                _ => writeln!(out, "{}", row[n - 1])?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes RFC 4180 CSV, quoting fields where needed
+    pub fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.write_delimited(out, ",", csv_field)
+    }
+
+    /// Writes tab separated values. TSV has no quoting, so tabs and line
+    /// breaks inside fields become spaces.
+    pub fn write_tsv(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.write_delimited(out, "\t", |field| field.replace(['\t', '\n', '\r'], " "))
+    }
+
+    fn write_delimited(
+        &self,
+        out: &mut dyn Write,
+        delimiter: &str,
+        escape: impl Fn(&str) -> String,
+    ) -> io::Result<()> {
+        let headers = Some(&self.headers).filter(|h| !h.is_empty());
+        for row in headers.into_iter().chain(&self.rows) {
+            let row = row.iter().map(|f| escape(f)).collect::<Vec<_>>();
+            writeln!(out, "{}", row.join(delimiter))?;
+        }
+        Ok(())
+    }
+}
+
+impl Table {
+    /// Writes a standalone HTML page holding the table. URLs become links, or
+    /// images if they point at one, and clicking a header sorts by it.
+    pub fn write_html(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{HTML_HEAD}")?;
+        let headers = self
+            .headers
+            .iter()
+            .map(|h| format!("<th>{}</th>", html_escape(h)));
+        writeln!(
+            out,
+            "<thead><tr>{}</tr></thead>",
+            headers.collect::<String>()
+        )?;
+        writeln!(out, "<tbody>")?;
+        for row in &self.rows {
+            let cells = row
+                .iter()
+                .map(|cell| format!("<td>{}</td>", html_cell(cell)));
+            writeln!(out, "<tr>{}</tr>", cells.collect::<String>())?;
+        }
+        writeln!(out, "</tbody>")?;
+        writeln!(out, "{HTML_FOOT}")
+    }
+}
+
+const HTML_HEAD: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>twitch-search</title>
+<style>
+body { font-family: sans-serif; margin: 1em; }
+table { border-collapse: collapse; }
+th, td { padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; text-align: left; vertical-align: middle; }
+th { cursor: pointer; user-select: none; }
+img { display: block; width: 160px; }
+</style>
+</head>
+<body>
+<table>"#;
+
+const HTML_FOOT: &str = r#"</table>
+<script>
+document.querySelectorAll("th").forEach((th, i) => th.addEventListener("click", () => {
+  const body = th.closest("table").tBodies[0];
+  const descending = th.dataset.descending !== "true";
+  th.dataset.descending = descending;
+  const text = row => row.cells[i].textContent;
+  const rows = [...body.rows].sort((a, b) => {
+    const difference = parseFloat(text(a)) - parseFloat(text(b));
+    const order = isNaN(difference) ? text(a).localeCompare(text(b)) : difference;
+    return descending ? -order : order;
+  });
+  body.append(...rows);
+}));
+</script>
+</body>
+</html>"#;
+
+fn html_cell(field: &str) -> String {
+    let text = html_escape(field);
+    if !field.starts_with("https://") {
+        return text;
+    }
+    match [".jpg", ".png", ".webp"]
+        .iter()
+        .any(|ext| field.ends_with(ext))
+    {
+        true => format!(r#"<img src="{text}" alt="" loading="lazy">"#),
+        false => format!(r#"<a href="{text}">{text}</a>"#),
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Feeds -
+// -----------------------------------------------------------------------------
+
+/// Writes an RSS 2.0 feed with an item per stream. The guid is the stream id,
+/// so feed readers show a stream once however often the feed is fetched, and
+/// again when the channel goes live the next time.
+pub fn write_rss(out: &mut dyn Write, title: &str, entries: &[StreamEntry]) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<rss version="2.0">"#)?;
+    writeln!(out, "<channel>")?;
+    writeln!(out, "<title>{}</title>", html_escape(title))?;
+    writeln!(out, "<link>https://twitch.tv</link>")?;
+    writeln!(
+        out,
+        "<description>Live streams matching a search</description>"
+    )?;
+    writeln!(
+        out,
+        "<lastBuildDate>{}</lastBuildDate>",
+        Utc::now().to_rfc2822()
+    )?;
+    for entry in entries {
+        let mut description = format!("{} viewers", entry.viewer_count);
+        if !entry.live_duration.is_empty() {
+            description += &format!(", live for {}", entry.live_duration);
+        }
+        if !entry.game_name.is_empty() {
+            description += &format!(", playing {}", entry.game_name);
+        }
+
+        writeln!(out, "<item>")?;
+        writeln!(
+            out,
+            "<title>{}</title>",
+            html_escape(&format!("{}: {}", entry.display_name, entry.title))
+        )?;
+        writeln!(out, "<link>{}</link>", html_escape(&entry.url()))?;
+        writeln!(
+            out,
+            "<description>{}</description>",
+            html_escape(&description)
+        )?;
+        writeln!(
+            out,
+            r#"<guid isPermaLink="false">{}</guid>"#,
+            html_escape(&entry.id)
+        )?;
+        if let Some(started_at) = entry.started_at {
+            writeln!(out, "<pubDate>{}</pubDate>", started_at.to_rfc2822())?;
+        }
+        writeln!(out, "</item>")?;
+    }
+    writeln!(out, "</channel>")?;
+    writeln!(out, "</rss>")
+}
+
+// -----------------------------------------------------------------------------
+//     - Styling -
+// -----------------------------------------------------------------------------
+
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ColorChoice {
+    /// When printing to a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                // https://no-color.org
+                env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// Streams with fewer viewers than this are dimmed
+const DIM_BELOW_VIEWERS: i64 = 10;
+
+const DIM: &str = "\x1b[2m";
+const NORMAL_INTENSITY: &str = "\x1b[22m";
+const YELLOW: &str = "\x1b[33m";
+const DEFAULT_COLOR: &str = "\x1b[39m";
+const RESET: &str = "\x1b[0m";
+
+/// Wraps `text` in escape codes. Each style is turned off with its own code,
+/// so styles can nest.
+fn paint(text: &str, on: &str, off: &str) -> String {
+    format!("{on}{text}{off}")
+}
+
+/// Colors the parts of `text` that match `terms`, or puts them in brackets
+/// without `color`
+fn highlight(text: &str, terms: &Terms, color: bool) -> String {
+    let (on, off) = match color {
+        true => (YELLOW, DEFAULT_COLOR),
+        false => ("[", "]"),
+    };
+    let mut styled = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end) in terms.spans(text) {
+        styled.push_str(&text[last..start]);
+        styled.push_str(&paint(&text[start..end], on, off));
+        last = end;
+    }
+    styled.push_str(&text[last..]);
+    styled
+}
+
+/// Makes `url` clickable in terminals that support OSC 8 links
+fn hyperlink(url: &str) -> String {
+    format!("\x1b]8;;{url}\x1b\\{url}\x1b]8;;\x1b\\")
+}
+
+/// Width of `text` on screen, not counting escape codes. Wide characters
+/// like CJK and most emoji take two columns, combining marks none.
+pub fn visible_width(text: &str) -> usize {
+    strip_escapes(text).width()
+}
+
+fn strip_escapes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        match escape_len(&text[i..]) {
+            0 => {
+                stripped.push(c);
+                i += c.len_utf8();
+            }
+            len => i += len,
+        }
+    }
+    stripped
+}
+
+/// Byte index of `text` where the first `width` columns end. Escape codes
+/// and combining marks right after the cut are kept.
+fn split_at_width(text: &str, width: usize) -> usize {
+    let mut used = 0;
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        match escape_len(&text[i..]) {
+            0 => {
+                used += c.width().unwrap_or(0);
+                if used > width {
+                    break;
+                }
+                i += c.len_utf8();
+            }
+            len => i += len,
+        }
+    }
+    i
+}
+
+/// Length in bytes of the escape code `text` starts with, or 0
+fn escape_len(text: &str) -> usize {
+    let mut chars = text.char_indices();
+    if chars.next().map(|(_, c)| c) != Some('\x1b') {
+        return 0;
+    }
+    let end = match chars.next() {
+        // CSI, up to a final byte in @..~
+        Some((_, '[')) => chars.find(|(_, c)| ('@'..='~').contains(c)),
+        // OSC, up to BEL or ESC \
+        Some((_, ']')) => {
+            let mut escaped = false;
+            chars.find(|&(_, c)| {
+                let end = c == '\x07' || (escaped && c == '\\');
+                escaped = c == '\x1b';
+                end
+            })
+        }
+        other => other,
+    };
+    end.map_or(text.len(), |(i, c)| i + c.len_utf8())
+}
+
+// -----------------------------------------------------------------------------
+//     - Sorting -
+// -----------------------------------------------------------------------------
+
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SortKey {
+    /// Viewer count, most watched first
+    Viewers,
+    /// Time live, longest running first
+    Uptime,
+    /// Channel name, A to Z
+    Name,
+    /// Language code, A to Z
+    Lang,
+}
+
+impl SortKey {
+    /// Whether the key puts the largest values first unless told otherwise
+    pub fn descending_by_default(self) -> bool {
+        matches!(self, SortKey::Viewers | SortKey::Uptime)
+    }
+}
+
+/// Sorts `entries` by `key`. The sort is stable, so ties keep their API order.
+pub fn sort_entries(entries: &mut [StreamEntry], key: SortKey, descending: bool) {
+    // Streams without a start time are treated as just started
+    let uptime = |e: &StreamEntry| cmp::Reverse(e.started_at.unwrap_or(DateTime::<Utc>::MAX_UTC));
+
+    entries.sort_by(|a, b| {
+        let ordering = match key {
+            SortKey::Viewers => a.viewer_count.cmp(&b.viewer_count),
+            SortKey::Uptime => uptime(a).cmp(&uptime(b)),
+            SortKey::Name => a
+                .display_name
+                .to_lowercase()
+                .cmp(&b.display_name.to_lowercase()),
+            SortKey::Lang => a.lang.cmp(&b.lang),
+        };
+        match descending {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+    });
+}
+
+// -----------------------------------------------------------------------------
+//     - Grouping -
+// -----------------------------------------------------------------------------
+
+#[derive(ValueEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum GroupBy {
+    /// Game or category
+    Game,
+    /// Stream language
+    Lang,
+}
+
+impl GroupBy {
+    /// Name of the group `entry` belongs in
+    pub fn key(self, entry: &StreamEntry) -> String {
+        match self {
+            GroupBy::Game if entry.game_name.is_empty() => "No category".to_string(),
+            GroupBy::Game => entry.game_name.clone(),
+            GroupBy::Lang => match language_name(&entry.lang) {
+                Some(name) => format!("{name} ({})", entry.lang),
+                None => entry.lang.clone(),
+            },
+        }
+    }
+}
+
+/// A run of entries in the same group, see [`group_entries`]
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub name: String,
+    pub len: usize,
+    /// Viewers of all the group's streams together
+    pub viewers: i64,
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let streams = match self.len {
+            1 => "stream",
+            _ => "streams",
+        };
+        write!(
+            f,
+            "{}: {} {streams}, {} viewers",
+            self.name, self.len, self.viewers
+        )
+    }
+}
+
+/// Moves `entries` into groups by `key`, the most watched group first. The
+/// entries keep their order within each group.
+pub fn group_entries(entries: &mut [StreamEntry], key: GroupBy) -> Vec<Group> {
+    let mut viewers = HashMap::<String, i64>::new();
+    for entry in entries.iter() {
+        *viewers.entry(key.key(entry)).or_default() += entry.viewer_count;
+    }
+    entries.sort_by_cached_key(|entry| {
+        let name = key.key(entry);
+        (cmp::Reverse(viewers[&name]), name)
+    });
+
+    let mut groups = Vec::<Group>::new();
+    for entry in entries.iter() {
+        let name = key.key(entry);
+        match groups.last_mut() {
+            Some(group) if group.name == name => group.len += 1,
+            _ => groups.push(Group {
+                viewers: viewers[&name],
+                name,
+                len: 1,
+            }),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::test_entry;
+    use crate::UserInfo;
+
+    fn row(entry: StreamEntry, list: &[Column]) -> Vec<String> {
+        let columns = Columns {
+            list: list.to_vec(),
+            plain: true,
+            ..Columns::default()
+        };
+        entry.format_row(&columns)
+    }
+
+    #[test]
+    fn category_column() {
+        assert_eq!(
+            row(test_entry("Crab", 10), &[Column::Category]),
+            ["Software and Game Development"]
+        );
+        assert!(!ExtraColumn::Category.needs_hydration());
+        assert!(ExtraColumn::Description.needs_hydration());
+    }
+
+    #[test]
+    fn chosen_columns() {
+        let mut layout = Column::layout(&[ExtraColumn::Category]);
+        assert_eq!(
+            layout,
+            [
+                Column::Lang,
+                Column::Name,
+                Column::Viewers,
+                Column::Uptime,
+                Column::Category,
+                Column::Title,
+            ]
+        );
+        Column::insert(&mut layout, Column::Tags);
+        Column::insert(&mut layout, Column::Tags);
+        assert_eq!(layout[5..], [Column::Tags, Column::Title]);
+
+        let mut list = vec![Column::Viewers, Column::Name];
+        Column::insert(&mut list, Column::Category);
+        assert_eq!(list, [Column::Viewers, Column::Name, Column::Category]);
+
+        assert_eq!(Column::from_str("game", true), Ok(Column::Category));
+        let headers = [Column::Name, Column::ViewerRatio, Column::Title].map(Column::header);
+        assert_eq!(headers, ["channel", "viewer_ratio", "title"]);
+        assert_eq!(
+            row(test_entry("Crab", 10), &[Column::Viewers, Column::Name]),
+            ["10", "https://twitch.tv/Crab"]
+        );
+    }
+
+    #[test]
+    fn colors() {
+        let columns = |color| Columns {
+            list: vec![Column::Name, Column::Title],
+            color,
+            highlight: Some(Terms::plain(&["rust".to_string()], false)),
+            ..Columns::default()
+        };
+        let row = test_entry("Crab", 10).format_row(&columns(true));
+        assert_eq!(row[1], format!("{YELLOW}Rust{DEFAULT_COLOR}"));
+        assert_eq!(visible_width(&row[0]), "https://twitch.tv/Crab".len());
+        assert!(row[0].starts_with("\x1b]8;;https://twitch.tv/Crab\x1b\\"));
+
+        let small = test_entry("Crab", DIM_BELOW_VIEWERS - 1).format_row(&columns(true));
+        assert!(small
+            .iter()
+            .all(|cell| cell.starts_with(DIM) && cell.ends_with(NORMAL_INTENSITY)));
+
+        assert_eq!(
+            test_entry("Crab", 10).format_row(&columns(false)),
+            ["https://twitch.tv/Crab", "Rust"]
+        );
+        assert!(ColorChoice::Always.enabled());
+        assert!(!ColorChoice::Never.enabled());
+    }
+
+    #[test]
+    fn bracket_highlights() {
+        let columns = Columns {
+            list: vec![Column::Title],
+            highlight: Some(Terms::plain(&["rust".to_string()], true)),
+            brackets: true,
+            ..Columns::default()
+        };
+        let entry = StreamEntry {
+            title: "Rust, rust and RUSTY".to_string(),
+            ..test_entry("Crab", 10)
+        };
+        assert_eq!(entry.format_row(&columns), ["[Rust], [rust] and [RUST]Y"]);
+    }
+
+    #[test]
+    fn description_on_one_short_line() {
+        let described = |description: &str| StreamEntry {
+            user: Some(UserInfo {
+                description: description.to_string(),
+                ..UserInfo::default()
+            }),
+            ..test_entry("Crab", 10)
+        };
+        assert_eq!(
+            row(described("Rust\nand\t  Zig"), &[Column::Description]),
+            ["Rust and Zig"]
+        );
+        let long = row(
+            described(&"Writing Rust ".repeat(10)),
+            &[Column::Description],
+        );
+        assert_eq!(visible_width(&long[0]), DESCRIPTION_WIDTH);
+        assert!(long[0].ends_with('…'));
+        assert_eq!(row(test_entry("Crab", 10), &[Column::Description]), [""]);
+    }
+
+    #[test]
+    fn language_column() {
+        let columns = |lang_names| Columns {
+            list: vec![Column::Lang],
+            lang_names,
+            ..Columns::default()
+        };
+        assert_eq!(test_entry("Crab", 10).format_row(&columns(false))[0], "en");
+        assert_eq!(
+            test_entry("Crab", 10).format_row(&columns(true))[0],
+            "English"
+        );
+        let unknown = StreamEntry {
+            lang: "xx".to_string(),
+            ..test_entry("Crab", 10)
+        };
+        assert_eq!(unknown.format_row(&columns(true))[0], "xx");
+    }
+
+    #[test]
+    fn csv_and_tsv() {
+        let mut table = Table::new(2);
+        table.set_headers(vec!["channel".to_string(), "title".to_string()]);
+        table.push(vec!["Crab".to_string(), "Rust, \"live\"\tnow".to_string()]);
+        table.push(vec!["Ferris".to_string(), "two\nlines".to_string()]);
+
+        let mut csv = Vec::new();
+        table.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "channel,title\nCrab,\"Rust, \"\"live\"\"\tnow\"\nFerris,\"two\nlines\"\n"
+        );
+
+        let mut tsv = Vec::new();
+        table.write_tsv(&mut tsv).unwrap();
+        assert_eq!(
+            String::from_utf8(tsv).unwrap(),
+            "channel\ttitle\nCrab\tRust, \"live\" now\nFerris\ttwo lines\n"
+        );
+    }
+
+    #[test]
+    fn sorting() {
+        let started = |name: &str, viewers, hours: Option<i64>| StreamEntry {
+            started_at: hours.map(|h| Utc::now() - chrono::Duration::hours(h)),
+            ..test_entry(name, viewers)
+        };
+        let entries = vec![
+            started("crab", 10, Some(1)),
+            started("Ferris", 30, Some(5)),
+            started("Corro", 10, None),
+            started("bob", 20, Some(3)),
+        ];
+        let sorted = |key, descending| {
+            let mut entries = entries.clone();
+            sort_entries(&mut entries, key, descending);
+            entries
+                .into_iter()
+                .map(|e| e.display_name)
+                .collect::<Vec<_>>()
+        };
+
+        // Ties keep their order either way
+        assert_eq!(
+            sorted(SortKey::Viewers, true),
+            ["Ferris", "bob", "crab", "Corro"]
+        );
+        assert_eq!(
+            sorted(SortKey::Viewers, false),
+            ["crab", "Corro", "bob", "Ferris"]
+        );
+        assert_eq!(
+            sorted(SortKey::Uptime, true),
+            ["Ferris", "bob", "crab", "Corro"]
+        );
+        assert_eq!(
+            sorted(SortKey::Name, false),
+            ["bob", "Corro", "crab", "Ferris"]
+        );
+    }
+
+    #[test]
+    fn fit_width_leaves_short_text() {
+        assert_eq!(fit_width("Rust", 4), "Rust");
+        assert_eq!(fit_width("", 0), "");
+    }
+
+    #[test]
+    fn fit_width_cuts_with_ellipsis() {
+        assert_eq!(fit_width("Learning Rust", 6), "Learn…");
+        assert_eq!(visible_width(&fit_width("Learning Rust", 6)), 6);
+        assert_eq!(fit_width("Rust", 1), "…");
+    }
+
+    #[test]
+    fn fit_width_counts_columns() {
+        // Wide characters take two columns each
+        let fitted = fit_width("日本語の配信", 7);
+        assert_eq!(fitted, "日本語…");
+        assert!(visible_width(&fitted) <= 7);
+    }
+
+    #[test]
+    fn fit_width_resets_styles() {
+        let styled = format!("\x1b[1mLearning Rust{RESET}");
+        let fitted = fit_width(&styled, 6);
+        assert!(fitted.ends_with(&format!("…{RESET}")));
+        assert_eq!(visible_width(&fitted), 6);
+    }
+
+    #[test]
+    fn wrap_lines_at_spaces() {
+        assert_eq!(wrap_lines("Rust", 10), vec!["Rust"]);
+        assert_eq!(
+            wrap_lines("Learning Rust with friends", 13),
+            vec!["Learning Rust", "with friends"]
+        );
+    }
+
+    #[test]
+    fn wrap_lines_breaks_long_words() {
+        assert_eq!(wrap_lines("abcdefgh ij", 3), vec!["abc", "def", "gh", "ij"]);
+        assert!(wrap_lines("日本語の配信", 4)
+            .iter()
+            .all(|line| visible_width(line) <= 4));
+    }
+
+    #[test]
+    fn html_page() {
+        let mut table = Table::new(3);
+        table.set_headers(vec!["channel".into(), "thumbnail".into(), "title".into()]);
+        table.push(vec![
+            "https://twitch.tv/crab".to_string(),
+            "https://static-cdn.jtvnw.net/crab-320x180.jpg".to_string(),
+            "<b>Rust</b> & \"Zig\"".to_string(),
+        ]);
+
+        let mut html = Vec::new();
+        table.write_html(&mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<thead><tr><th>channel</th><th>thumbnail</th><th>title</th>"));
+        assert!(html.contains(concat!(
+            r#"<tr><td><a href="https://twitch.tv/crab">https://twitch.tv/crab</a></td>"#,
+            r#"<td><img src="https://static-cdn.jtvnw.net/crab-320x180.jpg" alt="" "#,
+            r#"loading="lazy"></td>"#,
+            "<td>&lt;b&gt;Rust&lt;/b&gt; &amp; &quot;Zig&quot;</td></tr>",
+        )));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn grouped_by_game() {
+        let playing = |name: &str, viewers, game: &str| StreamEntry {
+            game_name: game.to_string(),
+            ..test_entry(name, viewers)
+        };
+        let mut entries = vec![
+            playing("Crab", 50, "Rust"),
+            playing("Ferris", 40, "Zig"),
+            playing("Corro", 30, "Rust"),
+            playing("Ziggy", 20, "Zig"),
+            playing("Bob", 100, ""),
+        ];
+        let groups = group_entries(&mut entries, GroupBy::Game);
+        let groups = groups
+            .iter()
+            .map(|g| (g.name.as_str(), g.len))
+            .collect::<Vec<_>>();
+        assert_eq!(groups, [("No category", 1), ("Rust", 2), ("Zig", 2)]);
+        // Streams keep their order within a group
+        let names = entries.iter().map(|e| e.display_name.as_str());
+        assert_eq!(
+            names.collect::<Vec<_>>(),
+            ["Bob", "Crab", "Corro", "Ferris", "Ziggy"]
+        );
+    }
+
+    #[test]
+    fn grouped_by_language() {
+        let speaking = |name: &str, viewers, lang: &str| StreamEntry {
+            lang: lang.to_string(),
+            ..test_entry(name, viewers)
+        };
+        let mut entries = vec![
+            speaking("Crab", 5, "sv"),
+            speaking("Ferris", 40, "en"),
+            speaking("Corro", 30, "sv"),
+            speaking("Bob", 1, "xx"),
+        ];
+        let groups = group_entries(&mut entries, GroupBy::Lang);
+        let headings = groups.iter().map(Group::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            headings,
+            [
+                "English (en): 1 stream, 40 viewers",
+                "Swedish (sv): 2 streams, 35 viewers",
+                "xx: 1 stream, 1 viewers",
+            ]
+        );
+    }
+
+    #[test]
+    fn rss_feed() {
+        let entry = StreamEntry {
+            title: "Rust & Zig <live>".to_string(),
+            live_duration: "01:30".to_string(),
+            ..test_entry("Crab", 10)
+        };
+        let mut rss = Vec::new();
+        write_rss(&mut rss, "Twitch streams: rust", &[entry]).unwrap();
+        let rss = String::from_utf8(rss).unwrap();
+        assert!(rss.contains("<title>Twitch streams: rust</title>"));
+        assert!(rss.contains(concat!(
+            "<item>\n",
+            "<title>Crab: Rust &amp; Zig &lt;live&gt;</title>\n",
+            "<link>https://twitch.tv/crab</link>\n",
+            "<description>10 viewers, live for 01:30, playing Software and Game Development",
+            "</description>\n",
+            "<guid isPermaLink=\"false\">crab-stream</guid>\n",
+            "<pubDate>Fri, 16 Oct 2026 12:00:00 +0000</pubDate>\n",
+            "</item>\n",
+        )));
+        assert!(rss.trim_end().ends_with("</rss>"));
+    }
+}
@@ -0,0 +1,1051 @@
+//! Full-screen browser for search results, see [`browse`]
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use clap::ValueEnum;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Alignment, Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, List, ListState, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::config::Theme;
+use crate::helix::Game;
+use crate::table::{sort_entries, visible_width, Align, Column, Columns, SortKey};
+use crate::{AppError, Change, StreamEntry};
+
+const PICKER_HELP: &str = "type a category  up/down pick  enter switch  esc cancel";
+
+/// How long typing must pause before categories are looked up
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Category look-ups kept, all forgotten at once past this
+const LOOKUPS_KEPT: usize = 100;
+
+/// Looks up the categories whose name matches a query
+pub type Categories<'a> = &'a dyn Fn(&str) -> Result<Vec<Game>, AppError>;
+
+/// Blocks the channel of a stream
+pub type BlockChannel<'a> = &'a dyn Fn(&StreamEntry) -> Result<(), AppError>;
+
+/// Clips the stream, returning where to edit the clip
+pub type ClipStream<'a> = &'a dyn Fn(&StreamEntry) -> Result<String, AppError>;
+
+/// What the browser can do besides showing streams, each left out when
+/// `None`
+#[derive(Clone, Copy, Default)]
+pub struct Actions<'a> {
+    /// `c` switches to a category picked as its name is typed
+    pub categories: Option<Categories<'a>>,
+    /// `b` blocks the channel of the selected stream, once confirmed, and
+    /// drops its streams
+    pub block: Option<BlockChannel<'a>>,
+    /// `C` clips the selected stream
+    pub clip: Option<ClipStream<'a>>,
+}
+
+/// Shows `entries` in a scrollable table until the user quits. `r`, and
+/// every `interval` if given, replaces them with whatever `refresh` returns,
+/// marking the streams that are new or changed viewers. Terms in
+/// `columns.highlight` are picked out in titles. `refresh` is given the id
+/// of the category switched to with `c` of `actions`, if any.
+pub fn browse(
+    entries: Vec<StreamEntry>,
+    sort: Option<(SortKey, bool)>,
+    columns: Columns,
+    styles: Styles,
+    interval: Option<Duration>,
+    mut refresh: impl FnMut(Option<&str>) -> Result<Vec<StreamEntry>, AppError>,
+    actions: Actions,
+) -> Result<(), AppError> {
+    let mut keys = "/ filter  s/S sort  enter open  o copy url".to_string();
+    if actions.categories.is_some() {
+        keys += "  c category";
+    }
+    if actions.block.is_some() {
+        keys += "  b block";
+    }
+    if actions.clip.is_some() {
+        keys += "  C clip";
+    }
+    keys += "  r refresh  q quit";
+    let mut browser = Browser {
+        entries,
+        visible: Vec::new(),
+        filter: String::new(),
+        editing: false,
+        sort,
+        columns,
+        styles,
+        interval,
+        refreshed: Instant::now(),
+        table: TableState::default().with_selected(0),
+        page: 1,
+        status: String::new(),
+        keys,
+        category: None,
+        picker: None,
+        looked_up: HashMap::new(),
+        blocking: None,
+    };
+    browser.update();
+
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal, &mut refresh, actions);
+    ratatui::restore();
+    result
+}
+
+#[derive(Debug)]
+struct Browser {
+    entries: Vec<StreamEntry>,
+    /// Entries passing the filter box, in display order
+    visible: Vec<StreamEntry>,
+    filter: String,
+    /// Whether keys go to the filter box
+    editing: bool,
+    sort: Option<(SortKey, bool)>,
+    columns: Columns,
+    styles: Styles,
+    /// How often to refresh without being asked
+    interval: Option<Duration>,
+    refreshed: Instant,
+    table: TableState,
+    /// Rows scrolled by page up/down, the height of the table when last drawn
+    page: u16,
+    status: String,
+    /// The keys shown in the status line, those of the actions given
+    keys: String,
+    /// The category switched to with `c`
+    category: Option<Game>,
+    /// The category box, while it is open
+    picker: Option<Picker>,
+    /// Categories by the query they were looked up with
+    looked_up: HashMap<String, Vec<Game>>,
+    /// The stream whose channel `b` asks to block, until answered
+    blocking: Option<StreamEntry>,
+}
+
+/// The category box of `c`, looking up categories as a name is typed
+#[derive(Debug, Default)]
+struct Picker {
+    query: String,
+    /// When the query last changed, while it waits to be looked up
+    typed: Option<Instant>,
+    matches: Vec<Game>,
+    list: ListState,
+    status: String,
+}
+
+impl Browser {
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        refresh: &mut impl FnMut(Option<&str>) -> Result<Vec<StreamEntry>, AppError>,
+        actions: Actions,
+    ) -> Result<(), AppError> {
+        let categories = actions.categories;
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let typed = self.picker.as_ref().and_then(|picker| picker.typed);
+            let due = [
+                self.interval
+                    .map(|interval| interval.saturating_sub(self.refreshed.elapsed())),
+                typed.map(|typed| DEBOUNCE.saturating_sub(typed.elapsed())),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+            if let Some(due) = due {
+                if !event::poll(due)? {
+                    match (typed, categories) {
+                        (Some(typed), Some(categories)) if typed.elapsed() >= DEBOUNCE => {
+                            self.look_up(categories)
+                        }
+                        _ => self.refresh(terminal, refresh)?,
+                    }
+                    continue;
+                }
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                return Ok(());
+            }
+
+            if let (Some(_), Some(categories)) = (&self.picker, categories) {
+                self.pick_category(key, terminal, refresh, categories)?;
+                continue;
+            }
+            if let Some(entry) = self.blocking.take() {
+                self.status = match (key.code, actions.block) {
+                    (KeyCode::Char('y'), Some(block)) => self.block(&entry, block),
+                    _ => format!("Did not block {}", entry.display_name),
+                };
+                continue;
+            }
+            if self.editing {
+                self.edit_filter(key);
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                KeyCode::PageDown => self.table.scroll_down_by(self.page),
+                KeyCode::PageUp => self.table.scroll_up_by(self.page),
+                KeyCode::Home | KeyCode::Char('g') => self.table.select_first(),
+                KeyCode::End | KeyCode::Char('G') => self.table.select_last(),
+                KeyCode::Char('/') => self.editing = true,
+                KeyCode::Char('s') => {
+                    self.sort = next_sort(self.sort);
+                    self.update();
+                }
+                KeyCode::Char('S') => {
+                    self.sort = self.sort.map(|(key, descending)| (key, !descending));
+                    self.update();
+                }
+                KeyCode::Enter => {
+                    if let Some(url) = self.selected_url() {
+                        self.status = match open_in_browser(&url) {
+                            Ok(()) => format!("Opened {url}"),
+                            Err(e) => format!("Failed to open {url}: {e}"),
+                        };
+                    }
+                }
+                KeyCode::Char('o') => {
+                    if let Some(url) = self.selected_url() {
+                        self.status = match copy_to_clipboard(&url) {
+                            Ok(()) => format!("Copied {url}"),
+                            Err(e) => format!("Failed to copy {url}: {e}"),
+                        };
+                    }
+                }
+                KeyCode::Char('c') if categories.is_some() => {
+                    self.picker = Some(Picker {
+                        status: PICKER_HELP.to_string(),
+                        ..Default::default()
+                    });
+                }
+                KeyCode::Char('b') if actions.block.is_some() => {
+                    if let Some(entry) = self.selected().cloned() {
+                        self.status = format!("Block {} on Twitch? y/n", entry.display_name);
+                        self.blocking = Some(entry);
+                    }
+                }
+                KeyCode::Char('C') => {
+                    if let (Some(entry), Some(clip)) = (self.selected(), actions.clip) {
+                        self.status = match clip(entry) {
+                            Ok(edit_url) => format!("Clipped {}: {edit_url}", entry.display_name),
+                            Err(e) => format!("Failed to clip {}: {e}", entry.display_name),
+                        };
+                    }
+                }
+                KeyCode::Char('r') => self.refresh(terminal, refresh)?,
+                _ => {}
+            }
+        }
+    }
+
+    fn pick_category(
+        &mut self,
+        key: KeyEvent,
+        terminal: &mut DefaultTerminal,
+        refresh: &mut impl FnMut(Option<&str>) -> Result<Vec<StreamEntry>, AppError>,
+        categories: Categories,
+    ) -> Result<(), AppError> {
+        let Some(picker) = &mut self.picker else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.picker = None,
+            KeyCode::Down => picker.list.select_next(),
+            KeyCode::Up => picker.list.select_previous(),
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.typed = Some(Instant::now());
+            }
+            KeyCode::Char(c) => {
+                picker.query.push(c);
+                picker.typed = Some(Instant::now());
+            }
+            KeyCode::Enter => {
+                // Not waiting for a pause in typing that won't come
+                if picker.typed.is_some() {
+                    self.look_up(categories);
+                }
+                let picked = self.picker.as_ref().and_then(|picker| {
+                    let at = picker.list.selected()?;
+                    picker.matches.get(at).cloned()
+                });
+                if let Some(game) = picked {
+                    self.picker = None;
+                    self.switch(game, terminal, refresh)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Fills the category box with the categories matching its query,
+    /// looked up once per query
+    fn look_up(&mut self, categories: Categories) {
+        let Some(picker) = &mut self.picker else {
+            return;
+        };
+        picker.typed = None;
+        let query = picker.query.trim().to_lowercase();
+        if query.is_empty() {
+            picker.matches.clear();
+            picker.status = PICKER_HELP.to_string();
+            return;
+        }
+        if !self.looked_up.contains_key(&query) {
+            match categories(&query) {
+                Ok(games) => {
+                    if self.looked_up.len() >= LOOKUPS_KEPT {
+                        self.looked_up.clear();
+                    }
+                    self.looked_up.insert(query.clone(), games);
+                }
+                Err(e) => {
+                    picker.status = format!("Looking up categories failed: {e}");
+                    return;
+                }
+            }
+        }
+        picker.matches = self.looked_up[&query].clone();
+        picker
+            .list
+            .select((!picker.matches.is_empty()).then_some(0));
+        picker.status = match picker.matches.len() {
+            0 => "No categories match".to_string(),
+            n => format!("{n} categories | {PICKER_HELP}"),
+        };
+    }
+
+    /// Blocks the channel of `entry` and drops its streams, returning what
+    /// became of it
+    fn block(&mut self, entry: &StreamEntry, block: BlockChannel) -> String {
+        match block(entry) {
+            Ok(()) => {
+                self.entries.retain(|e| e.user_id != entry.user_id);
+                self.update();
+                format!("Blocked {}", entry.display_name)
+            }
+            Err(e) => format!("Failed to block {}: {e}", entry.display_name),
+        }
+    }
+
+    /// Replaces the entries with those of `game`, searched from then on
+    fn switch(
+        &mut self,
+        game: Game,
+        terminal: &mut DefaultTerminal,
+        refresh: &mut impl FnMut(Option<&str>) -> Result<Vec<StreamEntry>, AppError>,
+    ) -> Result<(), AppError> {
+        self.status = format!("Loading {}...", game.name);
+        terminal.draw(|frame| self.draw(frame))?;
+        let result = refresh(Some(&game.id));
+        self.refreshed = Instant::now();
+        self.status = match result {
+            Ok(entries) => {
+                let status = format!("Switched to {}", game.name);
+                self.entries = entries;
+                self.category = Some(game);
+                self.update();
+                self.table.select(Some(0));
+                status
+            }
+            Err(e) => format!("Switching to {} failed: {e}", game.name),
+        };
+        Ok(())
+    }
+
+    /// Replaces the entries with new ones, marked by how they changed, and
+    /// keeps the same stream selected if it is still there
+    fn refresh(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        refresh: &mut impl FnMut(Option<&str>) -> Result<Vec<StreamEntry>, AppError>,
+    ) -> Result<(), AppError> {
+        self.status = "Refreshing...".to_string();
+        terminal.draw(|frame| self.draw(frame))?;
+        let result = refresh(self.category.as_ref().map(|game| game.id.as_str()));
+        // Counted from the end, so a slow refresh doesn't start the next at once
+        self.refreshed = Instant::now();
+        self.status = match result {
+            Ok(mut entries) => {
+                let new = mark_changes(&mut entries, &self.entries);
+                let selected = self.selected().map(|e| e.user_id.clone());
+                self.entries = entries;
+                Column::insert(&mut self.columns.list, Column::Change);
+                self.update();
+                if let Some(user_id) = selected {
+                    if let Some(at) = self.visible.iter().position(|e| e.user_id == user_id) {
+                        self.table.select(Some(at));
+                    }
+                }
+                format!(
+                    "Refreshed at {}, {new} new",
+                    Local::now().format("%H:%M:%S")
+                )
+            }
+            Err(e) => format!("Refresh failed: {e}"),
+        };
+        Ok(())
+    }
+
+    fn edit_filter(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter | KeyCode::Esc => self.editing = false,
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.update();
+            }
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.update();
+            }
+            _ => {}
+        }
+    }
+
+    /// Reapplies the filter box and sort order to the entries
+    fn update(&mut self) {
+        let needle = self.filter.to_lowercase();
+        self.visible = self
+            .entries
+            .iter()
+            .filter(|e| {
+                e.title.to_lowercase().contains(&needle)
+                    || e.display_name.to_lowercase().contains(&needle)
+                    || e.game_name.to_lowercase().contains(&needle)
+            })
+            .cloned()
+            .collect();
+
+        if let Some((key, descending)) = self.sort {
+            sort_entries(&mut self.visible, key, descending);
+        }
+    }
+
+    fn selected(&self) -> Option<&StreamEntry> {
+        self.visible.get(self.table.selected()?)
+    }
+
+    fn selected_url(&self) -> Option<String> {
+        self.selected().map(StreamEntry::url)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [filter_area, table_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        if let Some(picker) = &mut self.picker {
+            let input = Paragraph::new(picker.query.as_str())
+                .style(Style::new().add_modifier(Modifier::BOLD))
+                .block(Block::bordered().title(" Category "));
+            frame.render_widget(input, filter_area);
+            let x = filter_area.x + 1 + visible_width(&picker.query) as u16;
+            frame.set_cursor_position((
+                x.min(filter_area.right().saturating_sub(2)),
+                filter_area.y + 1,
+            ));
+            let names = picker.matches.iter().map(|game| game.name.as_str());
+            let list = List::new(names).highlight_style(self.styles.selection);
+            frame.render_stateful_widget(list, table_area, &mut picker.list);
+            frame.render_widget(Paragraph::new(picker.status.as_str()), status_area);
+            return;
+        }
+
+        let filter_style = match self.editing {
+            true => Style::new().add_modifier(Modifier::BOLD),
+            false => Style::new(),
+        };
+        let filter = Paragraph::new(self.filter.as_str())
+            .style(filter_style)
+            .block(Block::bordered().title(" Filter "));
+        frame.render_widget(filter, filter_area);
+        if self.editing {
+            let x = filter_area.x + 1 + visible_width(&self.filter) as u16;
+            frame.set_cursor_position((
+                x.min(filter_area.right().saturating_sub(2)),
+                filter_area.y + 1,
+            ));
+        }
+
+        let headers = self.columns.headers();
+        let list = &self.columns.list;
+        let rows = self
+            .visible
+            .iter()
+            .map(|e| {
+                let mut row = e.clone().format_row(&self.columns);
+                if let Some(at) = list.iter().position(|&c| c == Column::Change) {
+                    row[at] = change_badge(e.change);
+                }
+                row
+            })
+            .collect::<Vec<_>>();
+
+        // Every column but the title is as wide as its widest cell
+        let mut widths = headers.iter().map(|h| visible_width(h)).collect::<Vec<_>>();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(visible_width(cell));
+            }
+        }
+        let mut constraints = widths
+            .iter()
+            .map(|w| Constraint::Length(*w as u16))
+            .collect::<Vec<_>>();
+        // The title takes what is left, or the last column without one
+        let fill = list
+            .iter()
+            .position(|&c| c == Column::Title)
+            .unwrap_or(list.len().saturating_sub(1));
+        if let Some(title) = constraints.get_mut(fill) {
+            *title = Constraint::Fill(1);
+        }
+
+        let rows = rows.into_iter().zip(&self.visible).map(|(row, entry)| {
+            Row::new(
+                row.into_iter()
+                    .zip(list)
+                    .map(|(cell, &column)| self.cell(cell, column, entry.change)),
+            )
+        });
+        let table = Table::new(rows, constraints)
+            .header(Row::new(headers).style(self.styles.header))
+            .row_highlight_style(self.styles.selection)
+            .column_spacing(2);
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+        self.page = table_area.height.saturating_sub(1).max(1);
+
+        let sort = match self.sort {
+            Some((key, descending)) => format!(
+                "{} {}",
+                key.to_possible_value().map_or("", |v| v.get_name()),
+                if descending { "desc" } else { "asc" }
+            ),
+            None => "unsorted".to_string(),
+        };
+        let status = if self.status.is_empty() {
+            &self.keys
+        } else {
+            &self.status
+        };
+        let category = match &self.category {
+            Some(game) => format!(" in {}", game.name),
+            None => String::new(),
+        };
+        let line = format!(
+            "{}/{} streams{category}, {sort} | {status}",
+            self.visible.len(),
+            self.entries.len()
+        );
+        frame.render_widget(Paragraph::new(line), status_area);
+    }
+}
+
+impl Browser {
+    /// A cell of `column`, styled by the theme, with the search terms picked
+    /// out if it is the title. `change` is that of the row's stream.
+    fn cell(&self, text: String, column: Column, change: Option<Change>) -> Cell<'static> {
+        let spans = match (column, &self.columns.highlight) {
+            (Column::Title, Some(terms)) => terms.spans(&text),
+            _ => vec![],
+        };
+        let mut parts = Vec::new();
+        let mut last = 0;
+        for (start, end) in spans {
+            parts.push(Span::raw(text[last..start].to_string()));
+            parts.push(Span::styled(
+                text[start..end].to_string(),
+                self.styles.highlight,
+            ));
+            last = end;
+        }
+        parts.push(Span::raw(text[last..].to_string()));
+
+        let line = match column.align() {
+            Align::Right => Line::from(parts).alignment(Alignment::Right),
+            _ => Line::from(parts),
+        };
+        let style = match (column, change) {
+            (Column::Change, Some(Change::New)) => self.styles.new,
+            (Column::Change, Some(Change::Viewers(delta))) if delta > 0 => self.styles.up,
+            (Column::Change, Some(Change::Viewers(delta))) if delta < 0 => self.styles.down,
+            _ => self.styles.column(column),
+        };
+        Cell::from(line).style(style)
+    }
+}
+
+/// Marks each of `entries` as new, or with its change in viewers since
+/// `before`, and returns how many are new
+fn mark_changes(entries: &mut [StreamEntry], before: &[StreamEntry]) -> usize {
+    let before = before
+        .iter()
+        .map(|e| (e.user_id.as_str(), e.viewer_count))
+        .collect::<HashMap<_, _>>();
+    let mut new = 0;
+    for entry in entries {
+        entry.change = Some(match before.get(entry.user_id.as_str()) {
+            Some(viewers) => Change::Viewers(entry.viewer_count - viewers),
+            None => {
+                new += 1;
+                Change::New
+            }
+        });
+    }
+    new
+}
+
+/// NEW, or arrows with the change in viewers
+fn change_badge(change: Option<Change>) -> String {
+    match change {
+        Some(Change::New) => "NEW".to_string(),
+        Some(Change::Viewers(delta)) if delta > 0 => format!("▲{delta}"),
+        Some(Change::Viewers(delta)) if delta < 0 => format!("▼{}", -delta),
+        _ => String::new(),
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Theme -
+// -----------------------------------------------------------------------------
+
+/// How the parts of the browser look, from the `[theme]` of the config
+#[derive(Debug, Clone)]
+pub struct Styles {
+    pub header: Style,
+    pub selection: Style,
+    pub highlight: Style,
+    /// Badges of streams new since the last refresh
+    pub new: Style,
+    /// Viewer counts that went up or down since the last refresh
+    pub up: Style,
+    pub down: Style,
+    pub columns: Vec<(Column, Style)>,
+}
+
+impl Default for Styles {
+    /// Bold and reversed text only, for any terminal colors
+    fn default() -> Self {
+        Styles {
+            header: Style::new().add_modifier(Modifier::BOLD),
+            selection: Style::new().add_modifier(Modifier::REVERSED),
+            highlight: Style::new().add_modifier(Modifier::BOLD),
+            new: Style::new().add_modifier(Modifier::BOLD),
+            up: Style::new(),
+            down: Style::new(),
+            columns: vec![],
+        }
+    }
+}
+
+impl Styles {
+    /// Colors for light text on a dark background
+    pub fn dark() -> Self {
+        Styles {
+            header: Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            selection: Style::new().fg(Color::Black).bg(Color::Cyan),
+            highlight: Style::new()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+            new: Style::new()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            up: Style::new().fg(Color::LightGreen),
+            down: Style::new().fg(Color::LightRed),
+            columns: vec![
+                (Column::Lang, Style::new().fg(Color::DarkGray)),
+                (Column::Viewers, Style::new().fg(Color::LightGreen)),
+                (Column::Uptime, Style::new().fg(Color::DarkGray)),
+                (Column::Category, Style::new().fg(Color::Cyan)),
+            ],
+        }
+    }
+
+    /// Colors for dark text on a light background
+    pub fn light() -> Self {
+        Styles {
+            header: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+            selection: Style::new().fg(Color::White).bg(Color::Blue),
+            highlight: Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+            new: Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            up: Style::new().fg(Color::Green),
+            down: Style::new().fg(Color::Red),
+            columns: vec![
+                (Column::Lang, Style::new().fg(Color::Gray)),
+                (Column::Viewers, Style::new().fg(Color::Green)),
+                (Column::Uptime, Style::new().fg(Color::Gray)),
+                (Column::Category, Style::new().fg(Color::Magenta)),
+            ],
+        }
+    }
+
+    /// The preset of `theme`, changed by its other keys
+    pub fn new(theme: &Theme) -> Result<Self, AppError> {
+        let invalid = |key: &str, e: String| AppError::InvalidStyle(format!("theme.{key}"), e);
+        let mut styles = match theme.preset.as_deref() {
+            None => Styles::default(),
+            Some("dark") => Styles::dark(),
+            Some("light") => Styles::light(),
+            Some(preset) => {
+                let e = format!("no preset {preset:?}, only dark and light");
+                return Err(invalid("preset", e));
+            }
+        };
+
+        let parts = [
+            ("header", &theme.header, &mut styles.header),
+            ("selection", &theme.selection, &mut styles.selection),
+            ("highlight", &theme.highlight, &mut styles.highlight),
+            ("new", &theme.new, &mut styles.new),
+            ("up", &theme.up, &mut styles.up),
+            ("down", &theme.down, &mut styles.down),
+        ];
+        for (key, spec, style) in parts {
+            if let Some(spec) = spec {
+                *style = parse_style(spec).map_err(|e| invalid(key, e))?;
+            }
+        }
+        for (name, spec) in &theme.columns {
+            let key = format!("columns.{name}");
+            let column = Column::from_str(name, true).map_err(|e| invalid(&key, e))?;
+            let style = parse_style(spec).map_err(|e| invalid(&key, e))?;
+            styles.columns.retain(|&(c, _)| c != column);
+            styles.columns.push((column, style));
+        }
+        Ok(styles)
+    }
+
+    fn column(&self, column: Column) -> Style {
+        self.columns
+            .iter()
+            .find(|&&(c, _)| c == column)
+            .map_or(Style::new(), |&(_, style)| style)
+    }
+}
+
+/// Reads a style like `bold yellow on blue`, see [`Theme`]
+fn parse_style(spec: &str) -> Result<Style, String> {
+    let color = |name: &str| {
+        Color::from_str(name).map_err(|_| format!("{name:?} is not a color or modifier"))
+    };
+    let mut style = Style::new();
+    let mut words = spec.split_whitespace();
+    while let Some(word) = words.next() {
+        style = match word.to_lowercase().as_str() {
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style.add_modifier(Modifier::REVERSED),
+            "on" => match words.next() {
+                Some(name) => style.bg(color(name)?),
+                None => return Err("\"on\" needs a background color after it".to_string()),
+            },
+            _ => style.fg(color(word)?),
+        };
+    }
+    Ok(style)
+}
+
+/// Cycles through no sorting and each sort key in its default direction
+fn next_sort(sort: Option<(SortKey, bool)>) -> Option<(SortKey, bool)> {
+    let keys = SortKey::value_variants();
+    let next = match sort {
+        None => keys.first(),
+        Some((key, _)) => keys.iter().skip_while(|k| **k != key).nth(1),
+    };
+    next.map(|key| (*key, key.descending_by_default()))
+}
+
+/// Opens `url` with the desktop's default handler
+pub fn open_in_browser(url: &str) -> io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut command = Command::new("xdg-open");
+
+    let mut child = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Asks the terminal to put `text` on the clipboard (OSC 52), which works
+/// over SSH as well
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::entry::test_entry;
+    use crate::AccessTokenError;
+
+    fn browser(entries: Vec<StreamEntry>) -> Browser {
+        Browser {
+            entries,
+            visible: Vec::new(),
+            filter: String::new(),
+            editing: false,
+            sort: None,
+            columns: Columns::default(),
+            styles: Styles::default(),
+            interval: None,
+            refreshed: Instant::now(),
+            table: TableState::default(),
+            page: 1,
+            status: String::new(),
+            keys: String::new(),
+            category: None,
+            picker: None,
+            looked_up: HashMap::new(),
+            blocking: None,
+        }
+    }
+
+    #[test]
+    fn filter_box_narrows_the_list() {
+        let mut browser = browser(vec![
+            StreamEntry {
+                title: "Learning Rust".to_string(),
+                ..test_entry("Crab", 10)
+            },
+            StreamEntry {
+                game_name: "Just Chatting".to_string(),
+                ..test_entry("Ferris", 20)
+            },
+        ]);
+        browser.update();
+        assert_eq!(browser.visible.len(), 2);
+
+        let names = |browser: &Browser| {
+            let visible = browser.visible.iter().map(|e| e.display_name.clone());
+            visible.collect::<Vec<_>>()
+        };
+        browser.editing = true;
+        for c in "LEARN".chars() {
+            browser.edit_filter(KeyEvent::from(KeyCode::Char(c)));
+        }
+        assert_eq!(names(&browser), ["Crab"]);
+        browser.filter = "chatting".to_string();
+        browser.update();
+        assert_eq!(names(&browser), ["Ferris"]);
+        browser.filter = "ferr".to_string();
+        browser.edit_filter(KeyEvent::from(KeyCode::Backspace));
+        assert_eq!(names(&browser), ["Ferris"]);
+        browser.edit_filter(KeyEvent::from(KeyCode::Enter));
+        assert!(!browser.editing);
+
+        browser.filter.clear();
+        browser.sort = Some((SortKey::Viewers, true));
+        browser.update();
+        assert_eq!(names(&browser), ["Ferris", "Crab"]);
+    }
+
+    #[test]
+    fn sort_key_cycles() {
+        let mut sort = None;
+        let mut seen = Vec::new();
+        for _ in 0..=SortKey::value_variants().len() {
+            sort = next_sort(sort);
+            seen.push(sort);
+        }
+        assert_eq!(seen[0], Some((SortKey::Viewers, true)));
+        assert_eq!(seen[2], Some((SortKey::Name, false)));
+        assert_eq!(seen.last(), Some(&None));
+    }
+
+    #[test]
+    fn clipboard_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(
+            base64("https://twitch.tv/crab".as_bytes()),
+            "aHR0cHM6Ly90d2l0Y2gudHYvY3JhYg=="
+        );
+    }
+
+    #[test]
+    fn categories_are_looked_up_once_per_query() {
+        let mut browser = browser(Vec::new());
+        browser.picker = Some(Picker::default());
+        let calls = std::cell::Cell::new(0);
+        let search = |query: &str| {
+            calls.set(calls.get() + 1);
+            Ok(vec![Game {
+                id: "1469308723".to_string(),
+                name: format!("{query} and Game Development"),
+            }])
+        };
+
+        for query in ["Software", "software ", "Software"] {
+            let picker = browser.picker.as_mut().unwrap();
+            picker.query = query.to_string();
+            picker.typed = Some(Instant::now());
+            browser.look_up(&search);
+        }
+        assert_eq!(calls.get(), 1);
+        let picker = browser.picker.as_ref().unwrap();
+        assert_eq!(picker.typed, None);
+        assert_eq!(picker.list.selected(), Some(0));
+        assert_eq!(picker.matches[0].name, "software and Game Development");
+    }
+
+    #[test]
+    fn blocking_drops_the_channel() {
+        let mut browser = browser(vec![test_entry("a", 10), test_entry("b", 20)]);
+        browser.update();
+        let fails = |_: &StreamEntry| Err(AppError::from(AccessTokenError::MissingUserToken));
+        assert!(browser
+            .block(&test_entry("a", 10), &fails)
+            .starts_with("Failed"));
+        assert_eq!(browser.visible.len(), 2);
+
+        assert_eq!(
+            browser.block(&test_entry("a", 10), &|_| Ok(())),
+            "Blocked a"
+        );
+        let left = browser
+            .visible
+            .iter()
+            .map(|e| &*e.user_id)
+            .collect::<Vec<_>>();
+        assert_eq!(left, ["b"]);
+    }
+
+    #[test]
+    fn refreshed_entries_are_marked() {
+        let before = [test_entry("a", 10), test_entry("b", 20)];
+        let mut after = [test_entry("b", 15), test_entry("c", 5), test_entry("a", 10)];
+        assert_eq!(mark_changes(&mut after, &before), 1);
+        let changes = after.iter().map(|e| e.change).collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                Some(Change::Viewers(-5)),
+                Some(Change::New),
+                Some(Change::Viewers(0))
+            ]
+        );
+        let badges = changes.into_iter().map(change_badge).collect::<Vec<_>>();
+        assert_eq!(badges, ["▼5", "NEW", ""]);
+        assert_eq!(change_badge(Some(Change::Viewers(3))), "▲3");
+        assert_eq!(change_badge(None), "");
+    }
+
+    #[test]
+    fn styles_from_words() {
+        assert_eq!(
+            parse_style("bold yellow on #102030"),
+            Ok(Style::new()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Yellow)
+                .bg(Color::Rgb(0x10, 0x20, 0x30)))
+        );
+        assert_eq!(
+            parse_style("light-red"),
+            Ok(Style::new().fg(Color::LightRed))
+        );
+        assert_eq!(parse_style("208"), Ok(Style::new().fg(Color::Indexed(208))));
+        assert_eq!(parse_style(""), Ok(Style::new()));
+        assert!(parse_style("yellow on").is_err());
+        assert!(parse_style("blinking").is_err());
+    }
+
+    #[test]
+    fn theme_changes_its_preset() {
+        let theme = Theme {
+            preset: Some("dark".to_string()),
+            header: Some("underlined".to_string()),
+            columns: BTreeMap::from([("viewers".to_string(), "red".to_string())]),
+            ..Theme::default()
+        };
+        let styles = Styles::new(&theme).unwrap();
+        assert_eq!(
+            styles.header,
+            Style::new().add_modifier(Modifier::UNDERLINED)
+        );
+        assert_eq!(styles.selection, Styles::dark().selection);
+        assert_eq!(styles.column(Column::Viewers), Style::new().fg(Color::Red));
+        assert_eq!(styles.column(Column::Title), Style::new());
+    }
+
+    #[test]
+    fn theme_errors_name_the_key() {
+        let theme = Theme {
+            columns: BTreeMap::from([("nope".to_string(), "red".to_string())]),
+            ..Theme::default()
+        };
+        assert!(matches!(
+            Styles::new(&theme),
+            Err(AppError::InvalidStyle(key, _)) if key == "theme.columns.nope"
+        ));
+        let theme = Theme {
+            preset: Some("solarized".to_string()),
+            ..Theme::default()
+        };
+        assert!(Styles::new(&theme).is_err());
+    }
+}